@@ -0,0 +1,122 @@
+use crate::{ray::Ray, vector::Vector3};
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3, max: Vector3) -> Self {
+        Self { min, max }
+    }
+
+    /// Box that contains nothing. It is the identity for `union`.
+    pub fn empty() -> Self {
+        Self {
+            min: Vector3::xyz(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vector3::xyz(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn grow(&self, point: Vector3) -> Aabb {
+        Aabb::new(self.min.min(point), self.max.max(point))
+    }
+
+    pub fn centroid(&self) -> Vector3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn extent(&self) -> Vector3 {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.extent();
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Index of the axis along which the box is the longest.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.extent();
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for axis in 0..3 {
+            let inv_d = 1.0 / direction[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // `max`/`min` ignore NaN, which appears for rays lying in a slab plane.
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn unit() -> Aabb {
+        Aabb::new(Vector3::xyz(0.0, 0.0, 0.0), Vector3::xyz(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn union() {
+        let a = unit();
+        let b = Aabb::new(Vector3::xyz(-1.0, 0.5, 0.0), Vector3::xyz(0.5, 2.0, 0.5));
+
+        let expected = Aabb::new(Vector3::xyz(-1.0, 0.0, 0.0), Vector3::xyz(1.0, 2.0, 1.0));
+        assert_eq!(a.union(&b), expected);
+        assert_eq!(Aabb::empty().union(&a), a);
+    }
+
+    #[test]
+    fn surface_area() {
+        assert_eq!(unit().surface_area(), 6.0);
+        assert_eq!(Aabb::empty().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn hit() {
+        let aabb = unit();
+        let inside = Ray::new(Vector3::xyz(0.5, 0.5, -1.0), Vector3::xyz(0.0, 0.0, 1.0));
+        let outside = Ray::new(Vector3::xyz(2.0, 0.5, -1.0), Vector3::xyz(0.0, 0.0, 1.0));
+        let away = Ray::new(Vector3::xyz(0.5, 0.5, -1.0), Vector3::xyz(0.0, 0.0, -1.0));
+
+        assert!(aabb.hit(&inside, 0.0, f32::INFINITY));
+        assert!(!aabb.hit(&inside, 0.0, 0.5));
+        assert!(!aabb.hit(&outside, 0.0, f32::INFINITY));
+        assert!(!aabb.hit(&away, 0.0, f32::INFINITY));
+    }
+}
@@ -0,0 +1,213 @@
+//! Adaptive sampling: pixels stop receiving samples once the estimated error
+//! of their mean drops below a threshold.
+
+use crate::{
+    canvas::{Canvas, ImageCanvas},
+    color::Color,
+};
+
+pub struct AdaptiveOptions {
+    /// Samples every pixel gets before its error is estimated. The maximum
+    /// is `RenderOptions::samples`.
+    pub min_samples: u32,
+    /// Relative standard error of the pixel luminance below which a pixel is
+    /// considered converged.
+    pub threshold: f32,
+}
+
+impl Default for AdaptiveOptions {
+    fn default() -> Self {
+        Self {
+            min_samples: 8,
+            threshold: 0.01,
+        }
+    }
+}
+
+/// Running mean of the samples of a pixel together with the variance of
+/// their luminance, updated with Welford's algorithm.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelStats {
+    samples: u32,
+    mean: Color,
+    m2: f32,
+}
+
+/// Luminance below which the relative error is measured against this value
+/// instead, so black pixels can converge.
+const MIN_LUMINANCE: f32 = 0.01;
+
+impl PixelStats {
+    pub fn add(&mut self, color: Color) {
+        self.samples += 1;
+        let delta = color.luminance() - self.mean.luminance();
+        self.mean = self.mean + (color - self.mean) / self.samples as f32;
+        self.m2 += delta * (color.luminance() - self.mean.luminance());
+    }
+
+    /// Combines the statistics of two disjoint sets of samples.
+    pub fn merge(&mut self, other: &PixelStats) {
+        if other.samples == 0 {
+            return;
+        }
+        if self.samples == 0 {
+            *self = *other;
+            return;
+        }
+        let samples = self.samples + other.samples;
+        let weight = other.samples as f32 / samples as f32;
+        let delta = other.mean.luminance() - self.mean.luminance();
+        self.mean = self.mean + (other.mean - self.mean) * weight;
+        self.m2 += other.m2 + delta * delta * self.samples as f32 * weight;
+        self.samples = samples;
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Mean of the samples, black if there are none.
+    pub fn mean(&self) -> Color {
+        match self.samples {
+            0 => Color::new(),
+            _ => self.mean,
+        }
+    }
+
+    /// Sample variance of the luminance.
+    pub fn variance(&self) -> f32 {
+        match self.samples {
+            0 | 1 => 0.0,
+            n => self.m2 / (n - 1) as f32,
+        }
+    }
+
+    /// Standard error of the mean luminance relative to the mean itself.
+    pub fn relative_error(&self) -> f32 {
+        match self.samples {
+            0 | 1 => f32::INFINITY,
+            n => {
+                let error = (self.variance() / n as f32).sqrt();
+                error / self.mean.luminance().max(MIN_LUMINANCE)
+            }
+        }
+    }
+
+    pub fn is_converged(&self, options: &AdaptiveOptions) -> bool {
+        self.samples >= options.min_samples && self.relative_error() < options.threshold
+    }
+}
+
+/// Number of samples taken in every pixel, stored row by row.
+#[derive(Clone)]
+pub struct SampleCounts {
+    width: u32,
+    height: u32,
+    counts: Vec<u32>,
+}
+
+impl SampleCounts {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            counts: vec![0; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u32 {
+        self.counts[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, count: u32) {
+        self.counts[(y * self.width + x) as usize] = count;
+    }
+
+    pub fn max(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn mean(&self) -> f32 {
+        let total = self.counts.iter().map(|&c| c as u64).sum::<u64>();
+        total as f32 / self.counts.len().max(1) as f32
+    }
+
+    /// Grayscale image of the counts, white is the largest one.
+    pub fn to_image(&self) -> ImageCanvas {
+        let mut image = ImageCanvas::new(self.width, self.height);
+        let max = self.max().max(1) as f32;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = self.get(x, y) as f32 / max;
+                image.draw_point(&Color::rgb(value, value, value), x, y);
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stats(values: &[f32]) -> PixelStats {
+        let mut stats = PixelStats::default();
+        for &v in values {
+            stats.add(Color::rgb(v, v, v));
+        }
+        stats
+    }
+
+    #[test]
+    fn welford_matches_two_pass() {
+        let values = [0.5, 0.25, 1.0, 0.75, 0.0];
+        let stats = stats(&values);
+        let mean = values.iter().sum::<f32>() / 5.0;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / 4.0;
+
+        assert_eq!(stats.samples(), 5);
+        assert!((stats.mean().r - mean).abs() < 1e-6);
+        assert!((stats.variance() - variance).abs() < 1e-6);
+    }
+
+    #[test]
+    fn merge_matches_single_pass() {
+        let all = stats(&[0.5, 0.25, 1.0, 0.75, 0.0, 2.0]);
+        let mut merged = stats(&[0.5, 0.25]);
+        merged.merge(&stats(&[1.0, 0.75, 0.0, 2.0]));
+        merged.merge(&PixelStats::default());
+
+        assert_eq!(merged.samples(), all.samples());
+        assert!((merged.mean().g - all.mean().g).abs() < 1e-6);
+        assert!((merged.variance() - all.variance()).abs() < 1e-5);
+
+        let mut empty = PixelStats::default();
+        empty.merge(&all);
+        assert_eq!(empty.samples(), 6);
+    }
+
+    #[test]
+    fn constant_pixels_converge() {
+        let options = AdaptiveOptions {
+            min_samples: 4,
+            threshold: 0.01,
+        };
+        assert!(!stats(&[0.3; 3]).is_converged(&options));
+        assert!(stats(&[0.3; 4]).is_converged(&options));
+        assert!(!stats(&[0.0, 1.0, 0.0, 1.0]).is_converged(&options));
+        assert!(stats(&[0.0; 4]).is_converged(&options));
+    }
+
+    #[test]
+    fn sample_count_image() {
+        let mut counts = SampleCounts::new(2, 1);
+        counts.set(0, 0, 2);
+        counts.set(1, 0, 8);
+
+        assert_eq!(counts.max(), 8);
+        assert_eq!(counts.mean(), 5.0);
+        let image = counts.to_image();
+        assert_eq!(image.pixel(0, 0).r, 0.25);
+        assert_eq!(image.pixel(1, 0).r, 1.0);
+    }
+}
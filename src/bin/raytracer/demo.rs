@@ -0,0 +1,80 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    entity::sphere::Sphere,
+    entity::Entity,
+    material::{Dielectric, Lambertian, Material, Metal},
+    scene::Scene,
+    util::Random,
+    vector::Vector3,
+};
+
+/// The cover scene of "Ray Tracing in One Weekend". `seed` controls the
+/// layout and the materials of the small spheres.
+pub fn create_scene(width: u32, height: u32, seed: u64) -> Scene {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let look_from = Vector3::xyz(8.0, 2.0, 3.0);
+    let look_at = Vector3::xyz(0.0, 0.0, 0.0);
+    let dist_to_focus = (look_from - look_at).length();
+
+    let camera = Camera::new(
+        look_from,
+        look_at,
+        Vector3::xyz(0.0, 1.0, 0.0),
+        60.0,
+        width as f32 / height as f32,
+        0.1,
+        dist_to_focus,
+    );
+
+    let ground_mat = Material::Lambertian(Lambertian::new(Color::rgb(0.5, 0.5, 0.5)));
+
+    let mut scene = Scene::new(camera);
+    scene.add(Entity::Sphere(Sphere::new(
+        Vector3::xyz(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_mat,
+    )));
+
+    let size = 11;
+    for i in -size..size {
+        for j in -size..size {
+            let material = Material::random(&mut rng);
+            let center = Vector3::xyz(
+                i as f32 + 0.9 * rng.gen::<f32>(),
+                0.2,
+                j as f32 + 0.9 * rng.gen::<f32>(),
+            );
+
+            let sphere = Sphere::new(center, 0.2, material);
+
+            scene.add(Entity::Sphere(sphere));
+        }
+    }
+
+    let material1 = Material::Dielectric(Dielectric::new(1.5));
+    scene.add(Entity::Sphere(Sphere::new(
+        Vector3::xyz(0.0, 1.0, 0.0),
+        1.0,
+        material1,
+    )));
+
+    let material2 = Material::Lambertian(Lambertian::new(Color::rgb(0.4, 0.2, 0.1)));
+    scene.add(Entity::Sphere(Sphere::new(
+        Vector3::xyz(-4.0, 1.0, 0.0),
+        1.0,
+        material2,
+    )));
+
+    let material3 = Material::Metal(Metal::new(Color::rgb(0.4, 0.2, 0.1), 0.0));
+    scene.add(Entity::Sphere(Sphere::new(
+        Vector3::xyz(4.0, 1.0, 0.0),
+        1.0,
+        material3,
+    )));
+
+    scene
+}
@@ -0,0 +1,339 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use structopt::StructOpt;
+
+use raytracer::{
+    adaptive::SampleCounts,
+    canvas::{Canvas, ImageCanvas},
+    description::{RenderDescription, SceneDescription},
+    entity::Entity,
+    output::{self, OutputOptions, Transfer},
+    progressive::{Pass, ProgressiveOptions},
+    sampler::SamplerKind,
+    scene::Scene,
+    tile::TileOrder,
+    Raytracer, RenderOptions,
+};
+
+mod demo;
+#[cfg(feature = "sdl")]
+mod window;
+
+#[derive(StructOpt)]
+#[structopt(name = "raytracer", about = "Simple raytracer")]
+enum Command {
+    /// Renders a scene into an image file
+    Render {
+        #[structopt(flatten)]
+        scene: SceneArgs,
+        /// Output image, the format is chosen by the extension (png, ppm, pfm, hdr or exr)
+        #[structopt(short, long, default_value = "render.png", parse(from_os_str))]
+        output: PathBuf,
+        /// Writes the image without opening a preview window
+        #[structopt(long)]
+        headless: bool,
+        /// Also writes an image of the number of samples taken in every pixel
+        #[structopt(long, parse(from_os_str))]
+        sample_map: Option<PathBuf>,
+    },
+    /// Renders a scene in a window
+    View {
+        #[structopt(flatten)]
+        scene: SceneArgs,
+    },
+    /// Prints scene statistics
+    Info {
+        #[structopt(flatten)]
+        scene: SceneArgs,
+    },
+}
+
+#[derive(StructOpt)]
+struct SceneArgs {
+    /// Scene file, the built-in demo scene is used when omitted
+    #[structopt(short, long, parse(from_os_str))]
+    scene: Option<PathBuf>,
+    /// Image width in pixels
+    #[structopt(long)]
+    width: Option<u32>,
+    /// Image height in pixels
+    #[structopt(long)]
+    height: Option<u32>,
+    /// Samples per pixel
+    #[structopt(long)]
+    spp: Option<u32>,
+    /// Maximum number of scattered rays per path
+    #[structopt(long)]
+    depth: Option<u32>,
+    /// Number of worker threads
+    #[structopt(long)]
+    threads: Option<usize>,
+    /// Seed of the sample generator and of the demo scene layout
+    #[structopt(long)]
+    seed: Option<u64>,
+    /// Sampler: independent, stratified, halton or sobol
+    #[structopt(long)]
+    sampler: Option<SamplerKind>,
+    /// Tile width in pixels
+    #[structopt(long)]
+    tile_width: Option<u32>,
+    /// Tile height in pixels
+    #[structopt(long)]
+    tile_height: Option<u32>,
+    /// Tile order: scanline, hilbert or spiral
+    #[structopt(long)]
+    tile_order: Option<TileOrder>,
+    /// Refines the whole image in passes instead of finishing tile by tile
+    #[structopt(long)]
+    progressive: bool,
+    /// Samples per pixel of every progressive pass
+    #[structopt(long, default_value = "1")]
+    pass_spp: u32,
+    /// Stops refining after this many seconds, implies --progressive
+    #[structopt(long)]
+    time_limit: Option<f32>,
+    /// Relative error below which a pixel stops receiving samples, enables adaptive sampling with --spp as the maximum
+    #[structopt(long)]
+    threshold: Option<f32>,
+    /// Samples per pixel before adaptive sampling estimates the error
+    #[structopt(long)]
+    min_spp: Option<u32>,
+}
+
+struct Setup {
+    scene: Scene,
+    options: RenderOptions,
+    progressive: Option<ProgressiveOptions>,
+    width: u32,
+    height: u32,
+}
+
+fn main() {
+    if let Err(error) = run(Command::from_args()) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
+
+fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Render {
+            scene,
+            output,
+            headless,
+            sample_map,
+        } => render(load(&scene)?, &output, headless, sample_map.as_ref()),
+        Command::View { scene } => view(load(&scene)?),
+        Command::Info { scene } => {
+            info(&load(&scene)?);
+            Ok(())
+        }
+    }
+}
+
+fn load(args: &SceneArgs) -> Result<Setup, String> {
+    let (description, base) = match args.scene {
+        Some(ref path) => {
+            let description =
+                SceneDescription::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let base = path.parent().map(PathBuf::from).unwrap_or_default();
+            (Some(description), base)
+        }
+        None => (None, PathBuf::new()),
+    };
+
+    let mut render = description
+        .as_ref()
+        .map(|d| d.render.clone())
+        .unwrap_or_default();
+    override_render(&mut render, args);
+    render.validate().map_err(|e| e.to_string())?;
+
+    let scene = match description {
+        Some(mut description) => {
+            description.render = render.clone();
+            description.build_scene(&base).map_err(|e| e.to_string())?
+        }
+        None => demo::create_scene(render.width, render.height, render.seed),
+    };
+
+    let mut options = render.render_options();
+    if let Some(threads) = args.threads {
+        options.threads = threads;
+    }
+
+    if args
+        .time_limit
+        .is_some_and(|limit| !limit.is_finite() || limit < 0.0)
+    {
+        return Err("--time-limit must be a positive number of seconds".to_string());
+    }
+    let progressive = if args.progressive || args.time_limit.is_some() {
+        Some(ProgressiveOptions {
+            pass_samples: args.pass_spp,
+            time_budget: args.time_limit.map(Duration::from_secs_f32),
+        })
+    } else {
+        None
+    };
+
+    Ok(Setup {
+        scene,
+        options,
+        progressive,
+        width: render.width,
+        height: render.height,
+    })
+}
+
+fn override_render(render: &mut RenderDescription, args: &SceneArgs) {
+    let overrides = [
+        (&mut render.width, args.width),
+        (&mut render.height, args.height),
+        (&mut render.samples, args.spp),
+        (&mut render.max_scatter, args.depth),
+        (&mut render.tile_width, args.tile_width),
+        (&mut render.tile_height, args.tile_height),
+        (&mut render.min_samples, args.min_spp),
+    ];
+    for (value, arg) in overrides {
+        if let Some(arg) = arg {
+            *value = arg;
+        }
+    }
+    if args.threshold.is_some() {
+        render.adaptive_threshold = args.threshold;
+    }
+    if let Some(seed) = args.seed {
+        render.seed = seed;
+    }
+    if let Some(sampler) = args.sampler {
+        render.sampler = sampler;
+    }
+    if let Some(order) = args.tile_order {
+        render.tile_order = order;
+    }
+}
+
+/// Renders the scene into `canvas`, progressively if requested, and returns
+/// the number of samples taken in every pixel.
+fn trace<C, F>(setup: Setup, canvas: &mut C, mut keep_going: F) -> SampleCounts
+where
+    C: Canvas,
+    F: FnMut(&Pass) -> bool,
+{
+    let mut raytracer = Raytracer::new(canvas, setup.options);
+    match setup.progressive {
+        Some(ref progressive) => {
+            raytracer.render_progressive(Arc::new(setup.scene), progressive, |pass| {
+                println!(
+                    "Pass {}: {} spp, {} ms",
+                    pass.index + 1,
+                    pass.samples,
+                    pass.elapsed.as_millis()
+                );
+                keep_going(pass)
+            });
+        }
+        None => raytracer.render(Arc::new(setup.scene)),
+    }
+    let counts = raytracer.sample_counts();
+    println!(
+        "Samples per pixel: {:.1} on average, {} at most",
+        counts.mean(),
+        counts.max()
+    );
+    counts.clone()
+}
+
+fn render(
+    setup: Setup,
+    output: &PathBuf,
+    headless: bool,
+    sample_map: Option<&PathBuf>,
+) -> Result<(), String> {
+    for path in std::iter::once(output).chain(sample_map) {
+        if output::ImageFormat::from_path(path).is_none() {
+            return Err(format!("unsupported image format: {}", path.display()));
+        }
+    }
+
+    let (image, counts) = if headless || cfg!(not(feature = "sdl")) {
+        let mut canvas = ImageCanvas::new(setup.width, setup.height);
+        let counts = trace(setup, &mut canvas, |_| true);
+        (canvas, counts)
+    } else {
+        render_with_preview(setup)?
+    };
+
+    save(output, &image, &OutputOptions::default())?;
+    if let Some(path) = sample_map {
+        let options = OutputOptions {
+            transfer: Transfer::Linear,
+            ..OutputOptions::default()
+        };
+        save(path, &counts.to_image(), &options)?;
+    }
+
+    Ok(())
+}
+
+fn save(path: &PathBuf, image: &ImageCanvas, options: &OutputOptions) -> Result<(), String> {
+    output::save(path, image, options).map_err(|e| format!("{}: {}", path.display(), e))?;
+    println!("Saved {}", path.display());
+    Ok(())
+}
+
+#[cfg(feature = "sdl")]
+fn render_with_preview(setup: Setup) -> Result<(ImageCanvas, SampleCounts), String> {
+    window::render_with_preview(setup)
+}
+
+#[cfg(not(feature = "sdl"))]
+fn render_with_preview(_setup: Setup) -> Result<(ImageCanvas, SampleCounts), String> {
+    unreachable!("the preview window needs the sdl feature")
+}
+
+#[cfg(feature = "sdl")]
+fn view(setup: Setup) -> Result<(), String> {
+    window::view(setup)
+}
+
+#[cfg(not(feature = "sdl"))]
+fn view(_setup: Setup) -> Result<(), String> {
+    Err("the raytracer was built without the sdl feature, use `render` instead".to_string())
+}
+
+fn info(setup: &Setup) {
+    let entities = setup.scene.entities();
+    let count = |f: fn(&Entity) -> bool| entities.iter().filter(|e| f(e)).count();
+    let triangles = entities
+        .iter()
+        .map(|e| match e {
+            Entity::Mesh(mesh) => mesh.faces().len(),
+            Entity::Triangle(_) => 1,
+            _ => 0,
+        })
+        .sum::<usize>();
+
+    println!("Resolution: {}x{}", setup.width, setup.height);
+    println!(
+        "Samples: {}, max scatter: {}, tile: {}x{} ({}), threads: {}",
+        setup.options.samples,
+        setup.options.max_scatter,
+        setup.options.tile_config.width,
+        setup.options.tile_config.height,
+        setup.options.tile_config.order,
+        setup.options.threads
+    );
+    println!(
+        "Entities: {} ({} spheres, {} triangles, {} meshes)",
+        entities.len(),
+        count(|e| matches!(e, Entity::Sphere(_))),
+        count(|e| matches!(e, Entity::Triangle(_))),
+        count(|e| matches!(e, Entity::Mesh(_)))
+    );
+    println!("Triangles: {}", triangles);
+    println!("Lights: {}", setup.scene.lights().lights().len());
+    println!("BVH: {}", setup.scene.bvh().stats());
+}
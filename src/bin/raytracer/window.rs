@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use sdl2::{event::Event, keyboard::Keycode, render::WindowCanvas, EventPump};
+
+use raytracer::{
+    adaptive::SampleCounts,
+    canvas::{Canvas, ImageCanvas},
+    color::Color,
+};
+
+use crate::{trace, Setup};
+
+const FPS: u32 = 60;
+
+/// Draws into the window and keeps a copy of the linear image.
+struct Preview<'a> {
+    window: &'a mut WindowCanvas,
+    image: ImageCanvas,
+}
+
+impl<'a> Canvas for Preview<'a> {
+    fn draw_point(&mut self, color: &Color, x: u32, y: u32) {
+        self.image.draw_point(color, x, y);
+        Canvas::draw_point(self.window, color, x, y);
+    }
+
+    fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    fn clear(&mut self) {
+        self.image.clear();
+        Canvas::clear(self.window);
+    }
+
+    fn flush(&mut self) {
+        Canvas::flush(self.window);
+    }
+}
+
+/// What the user asked for while the window was open.
+#[derive(PartialEq)]
+enum Input {
+    None,
+    /// Any key: stop refining the image.
+    Stop,
+    /// Escape or closing the window.
+    Quit,
+}
+
+fn poll(event_pump: &mut EventPump) -> Input {
+    let mut input = Input::None;
+    for event in event_pump.poll_iter() {
+        match event {
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => return Input::Quit,
+            Event::KeyDown { .. } => input = Input::Stop,
+            _ => {}
+        }
+    }
+    input
+}
+
+fn open(width: u32, height: u32) -> Result<(WindowCanvas, EventPump), String> {
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+
+    let window = video_subsystem
+        .window("Raytracer", width, height)
+        .position_centered()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    let event_pump = sdl_context.event_pump()?;
+
+    Ok((canvas, event_pump))
+}
+
+/// Renders into the window, a key press stops a progressive render early.
+/// Returns the sample counts and whether the user asked to quit.
+fn render(
+    setup: Setup,
+    canvas: &mut Preview<'_>,
+    event_pump: &mut EventPump,
+) -> (SampleCounts, bool) {
+    let mut quit = false;
+    let counts = trace(setup, canvas, |_| match poll(event_pump) {
+        Input::None => true,
+        Input::Stop => false,
+        Input::Quit => {
+            quit = true;
+            false
+        }
+    });
+    (counts, quit)
+}
+
+pub fn render_with_preview(setup: Setup) -> Result<(ImageCanvas, SampleCounts), String> {
+    let (mut window, mut event_pump) = open(setup.width, setup.height)?;
+    let mut preview = Preview {
+        window: &mut window,
+        image: ImageCanvas::new(setup.width, setup.height),
+    };
+    let (counts, _) = render(setup, &mut preview, &mut event_pump);
+
+    Ok((preview.image, counts))
+}
+
+pub fn view(setup: Setup) -> Result<(), String> {
+    let (mut window, mut event_pump) = open(setup.width, setup.height)?;
+    let mut preview = Preview {
+        window: &mut window,
+        image: ImageCanvas::new(setup.width, setup.height),
+    };
+    if render(setup, &mut preview, &mut event_pump).1 {
+        return Ok(());
+    }
+
+    while poll(&mut event_pump) != Input::Quit {
+        window.present();
+
+        std::thread::sleep(Duration::new(0, 1_000_000_000u32 / FPS));
+    }
+    Ok(())
+}
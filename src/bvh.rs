@@ -0,0 +1,425 @@
+use std::fmt;
+
+use crate::{aabb::Aabb, hit::HitRecord, ray::Ray, vector::Vector3};
+
+const BIN_COUNT: usize = 16;
+const MAX_LEAF_SIZE: usize = 4;
+const TRAVERSAL_COST: f32 = 1.0;
+const INTERSECTION_COST: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    /// Range of `Bvh::indices` with the primitives of the leaf.
+    Leaf {
+        bounds: Aabb,
+        first: usize,
+        count: usize,
+    },
+    /// The left child always follows its parent, so only the right one is stored.
+    Interior {
+        bounds: Aabb,
+        right: usize,
+        axis: usize,
+    },
+}
+
+impl Node {
+    fn bounds(&self) -> &Aabb {
+        match self {
+            Node::Leaf { bounds, .. } => bounds,
+            Node::Interior { bounds, .. } => bounds,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BvhStats {
+    pub primitive_count: usize,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_depth: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+    pub average_leaf_size: f32,
+}
+
+impl fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} primitives, {} nodes, {} leaves, depth {}, leaf size {}..{} (avg {:.2})",
+            self.primitive_count,
+            self.node_count,
+            self.leaf_count,
+            self.max_depth,
+            self.min_leaf_size,
+            self.max_leaf_size,
+            self.average_leaf_size
+        )
+    }
+}
+
+/// Bounding volume hierarchy over an indexed list of primitives, built with
+/// the binned surface area heuristic.
+///
+/// The tree only knows the primitive bounds, intersection of the primitives
+/// themselves is delegated to the caller.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+    stats: BvhStats,
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bounds: Aabb,
+    count: usize,
+}
+
+impl Bvh {
+    pub fn build(bounds: &[Aabb]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(bounds.len() * 2),
+            indices: (0..bounds.len()).collect(),
+            stats: BvhStats {
+                primitive_count: bounds.len(),
+                min_leaf_size: usize::MAX,
+                ..BvhStats::default()
+            },
+        };
+        let centroids = bounds.iter().map(|b| b.centroid()).collect::<Vec<_>>();
+        if !bounds.is_empty() {
+            bvh.build_node(bounds, &centroids, 0, bounds.len(), 1);
+        }
+
+        bvh.stats.node_count = bvh.nodes.len();
+        if bvh.stats.leaf_count > 0 {
+            bvh.stats.average_leaf_size = bounds.len() as f32 / bvh.stats.leaf_count as f32;
+        } else {
+            bvh.stats.min_leaf_size = 0;
+        }
+        bvh
+    }
+
+    pub fn stats(&self) -> &BvhStats {
+        &self.stats
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.nodes
+            .first()
+            .map(|node| *node.bounds())
+            .unwrap_or_else(Aabb::empty)
+    }
+
+    /// Finds the closest hit. `hit_primitive` is called with the index of a
+    /// primitive and the current closest distance.
+    pub fn hit<'a, F>(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut hit_primitive: F,
+    ) -> Option<HitRecord<'a>>
+    where
+        F: FnMut(usize, &Ray, f32, f32) -> Option<HitRecord<'a>>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let direction = ray.direction();
+        let mut result = None;
+        let mut t_closest = t_max;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounds().hit(ray, t_min, t_closest) {
+                continue;
+            }
+            match *node {
+                Node::Leaf { first, count, .. } => {
+                    for &primitive in self.indices[first..first + count].iter() {
+                        if let Some(hit) = hit_primitive(primitive, ray, t_min, t_closest) {
+                            t_closest = hit.t;
+                            result = Some(hit);
+                        }
+                    }
+                }
+                Node::Interior { right, axis, .. } => {
+                    // Visit the nearer child first so the farther one can be culled.
+                    if direction[axis] < 0.0 {
+                        stack.push(index + 1);
+                        stack.push(right);
+                    } else {
+                        stack.push(right);
+                        stack.push(index + 1);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn build_node(
+        &mut self,
+        bounds: &[Aabb],
+        centroids: &[Vector3],
+        start: usize,
+        end: usize,
+        depth: usize,
+    ) -> usize {
+        let index = self.nodes.len();
+        let count = end - start;
+
+        let mut node_bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for &primitive in self.indices[start..end].iter() {
+            node_bounds = node_bounds.union(&bounds[primitive]);
+            centroid_bounds = centroid_bounds.grow(centroids[primitive]);
+        }
+
+        let split = if count > 1 {
+            self.find_split(
+                bounds,
+                centroids,
+                start,
+                end,
+                &node_bounds,
+                &centroid_bounds,
+            )
+        } else {
+            None
+        };
+
+        let (axis, mid) = match split {
+            Some(split) => split,
+            None => {
+                self.push_leaf(node_bounds, start, count, depth);
+                return index;
+            }
+        };
+
+        self.nodes.push(Node::Interior {
+            bounds: node_bounds,
+            right: 0,
+            axis,
+        });
+        self.build_node(bounds, centroids, start, mid, depth + 1);
+        let right_index = self.build_node(bounds, centroids, mid, end, depth + 1);
+        if let Node::Interior { ref mut right, .. } = self.nodes[index] {
+            *right = right_index;
+        }
+
+        index
+    }
+
+    /// Returns the split axis and the partition point, or `None` when a leaf
+    /// is cheaper.
+    fn find_split(
+        &mut self,
+        bounds: &[Aabb],
+        centroids: &[Vector3],
+        start: usize,
+        end: usize,
+        node_bounds: &Aabb,
+        centroid_bounds: &Aabb,
+    ) -> Option<(usize, usize)> {
+        let count = end - start;
+        let axis = centroid_bounds.longest_axis();
+        let axis_min = centroid_bounds.min[axis];
+        let axis_extent = centroid_bounds.max[axis] - axis_min;
+
+        if axis_extent <= 0.0 {
+            // All centroids coincide, the only option is an arbitrary split.
+            if count <= MAX_LEAF_SIZE {
+                return None;
+            }
+            return Some((axis, start + count / 2));
+        }
+
+        let bin_of = |primitive: usize| {
+            let offset = (centroids[primitive][axis] - axis_min) / axis_extent;
+            ((offset * BIN_COUNT as f32) as usize).min(BIN_COUNT - 1)
+        };
+
+        let mut bins = [Bin {
+            bounds: Aabb::empty(),
+            count: 0,
+        }; BIN_COUNT];
+        for &primitive in self.indices[start..end].iter() {
+            let bin = &mut bins[bin_of(primitive)];
+            bin.bounds = bin.bounds.union(&bounds[primitive]);
+            bin.count += 1;
+        }
+
+        // Sweep from the right to get the cost of every right partition.
+        let mut right_area = [0.0; BIN_COUNT];
+        let mut right_count = [0; BIN_COUNT];
+        let mut accumulated = Aabb::empty();
+        let mut accumulated_count = 0;
+        for i in (1..BIN_COUNT).rev() {
+            accumulated = accumulated.union(&bins[i].bounds);
+            accumulated_count += bins[i].count;
+            right_area[i] = accumulated.surface_area();
+            right_count[i] = accumulated_count;
+        }
+
+        let mut best_cost = f32::INFINITY;
+        let mut best_split = 0;
+        let mut left = Aabb::empty();
+        let mut left_count = 0;
+        for i in 1..BIN_COUNT {
+            left = left.union(&bins[i - 1].bounds);
+            left_count += bins[i - 1].count;
+            if left_count == 0 || right_count[i] == 0 {
+                continue;
+            }
+            let cost =
+                left.surface_area() * left_count as f32 + right_area[i] * right_count[i] as f32;
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = i;
+            }
+        }
+
+        let leaf_cost = INTERSECTION_COST * count as f32;
+        let split_cost =
+            TRAVERSAL_COST + INTERSECTION_COST * best_cost / node_bounds.surface_area();
+        if count <= MAX_LEAF_SIZE && leaf_cost <= split_cost {
+            return None;
+        }
+
+        let (mut i, mut j) = (start, end);
+        while i < j {
+            if bin_of(self.indices[i]) < best_split {
+                i += 1;
+            } else {
+                j -= 1;
+                self.indices.swap(i, j);
+            }
+        }
+
+        Some((axis, i))
+    }
+
+    fn push_leaf(&mut self, bounds: Aabb, first: usize, count: usize, depth: usize) {
+        self.nodes.push(Node::Leaf {
+            bounds,
+            first,
+            count,
+        });
+        self.stats.leaf_count += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        self.stats.min_leaf_size = self.stats.min_leaf_size.min(count);
+        self.stats.max_leaf_size = self.stats.max_leaf_size.max(count);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        color::Color,
+        entity::sphere::Sphere,
+        hit::Hittable,
+        material::{Lambertian, Material},
+    };
+
+    fn random_point(rng: &mut StdRng, size: f32) -> Vector3 {
+        Vector3::xyz(
+            rng.gen_range(-size, size),
+            rng.gen_range(-size, size),
+            rng.gen_range(-size, size),
+        )
+    }
+
+    fn random_spheres(rng: &mut StdRng, count: usize) -> Vec<Sphere> {
+        let material = Material::Lambertian(Lambertian::new(Color::rgb(0.5, 0.5, 0.5)));
+        (0..count)
+            .map(|_| {
+                Sphere::new(
+                    random_point(rng, 10.0),
+                    rng.gen_range(0.05, 1.0),
+                    material.clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn brute_force(spheres: &[Sphere], ray: &Ray) -> Option<f32> {
+        spheres
+            .iter()
+            .filter_map(|s| s.hit(ray, 0.001, f32::INFINITY))
+            .map(|hit| hit.t)
+            .fold(None, |closest: Option<f32>, t| {
+                Some(closest.map_or(t, |c| c.min(t)))
+            })
+    }
+
+    #[test]
+    fn hits_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let spheres = random_spheres(&mut rng, 500);
+        let bounds = spheres.iter().map(|s| s.bounding_box()).collect::<Vec<_>>();
+        let bvh = Bvh::build(&bounds);
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let origin = random_point(&mut rng, 15.0);
+            let target = random_point(&mut rng, 10.0);
+            let ray = Ray::new(origin, target - origin);
+
+            let expected = brute_force(&spheres, &ray);
+            let actual = bvh
+                .hit(&ray, 0.001, f32::INFINITY, |i, ray, t_min, t_max| {
+                    spheres[i].hit(ray, t_min, t_max)
+                })
+                .map(|hit| hit.t);
+
+            assert_eq!(actual, expected);
+            hits += expected.is_some() as usize;
+        }
+        assert!(hits > 100);
+    }
+
+    #[test]
+    fn stats() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let spheres = random_spheres(&mut rng, 1000);
+        let bounds = spheres.iter().map(|s| s.bounding_box()).collect::<Vec<_>>();
+        let bvh = Bvh::build(&bounds);
+        let stats = bvh.stats();
+
+        assert_eq!(stats.primitive_count, 1000);
+        assert_eq!(stats.node_count, 2 * stats.leaf_count - 1);
+        assert!(stats.min_leaf_size >= 1);
+        assert!(stats.max_leaf_size <= MAX_LEAF_SIZE);
+        assert!(stats.max_depth < 40);
+        assert!((stats.average_leaf_size * stats.leaf_count as f32 - 1000.0).abs() < 1e-2);
+
+        let mut expected = Aabb::empty();
+        bounds.iter().for_each(|b| expected = expected.union(b));
+        assert_eq!(bvh.bounding_box(), expected);
+    }
+
+    #[test]
+    fn empty_and_coincident() {
+        let empty = Bvh::build(&[]);
+        let ray = Ray::new(Vector3::new(), Vector3::xyz(0.0, 0.0, 1.0));
+        assert_eq!(empty.stats().node_count, 0);
+        assert!(empty
+            .hit(&ray, 0.0, f32::INFINITY, |_, _, _, _| None)
+            .is_none());
+
+        let same = vec![Aabb::new(Vector3::new(), Vector3::xyz(1.0, 1.0, 1.0)); 20];
+        let bvh = Bvh::build(&same);
+        assert_eq!(bvh.stats().primitive_count, 20);
+        assert!(bvh.stats().max_leaf_size <= MAX_LEAF_SIZE);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{ray::Ray, util::deg_to_rad, vector::Vector3};
+use crate::{ray::Ray, sampler::concentric_disk, util::deg_to_rad, vector::Vector3};
 
 #[derive(Debug)]
 pub struct Camera {
@@ -6,11 +6,9 @@ pub struct Camera {
     left_bottom: Vector3,
     horizontal: Vector3,
     vertical: Vector3,
-    w: Vector3,
     u: Vector3,
     v: Vector3,
     lens_radius: f32,
-    focus_dist: f32,
 }
 
 impl Camera {
@@ -42,17 +40,17 @@ impl Camera {
             left_bottom,
             horizontal,
             vertical,
-            w,
             u,
             v,
             lens_radius: aperture / 2.0,
-            focus_dist,
         }
     }
 
-    pub fn ray(&self, s: f32, t: f32) -> Ray {
-        let rd = Vector3::random_unit_vector() * self.lens_radius;
-        let offset = self.u * rd.x + self.v * rd.y;
+    /// Ray through the viewport point (`s`, `t`), starting from the lens
+    /// position selected by the `lens` sample in the unit square.
+    pub fn ray(&self, s: f32, t: f32, lens: (f32, f32)) -> Ray {
+        let (x, y) = concentric_disk(lens);
+        let offset = self.lens_radius * (self.u * x + self.v * y);
         Ray::new(
             self.origin + offset,
             self.left_bottom + s * self.horizontal + t * self.vertical - self.origin - offset,
@@ -1,7 +1,10 @@
+#[cfg(feature = "sdl")]
 use sdl2::{pixels, rect::Point, video::Window};
 
-use crate::Color;
+#[cfg(feature = "sdl")]
+use crate::output::{quantize, OutputOptions, Transfer};
 use crate::tile::Tile;
+use crate::Color;
 
 pub trait Canvas {
     fn draw_point(&mut self, color: &Color, x: u32, y: u32);
@@ -21,13 +24,16 @@ pub trait Canvas {
     }
 }
 
+#[cfg(feature = "sdl")]
 impl Canvas for sdl2::render::Canvas<Window> {
     fn draw_point(&mut self, color: &Color, x: u32, y: u32) {
+        // Same curve as saved images, so the preview looks like the file.
+        let transfer = OutputOptions::default().transfer;
         self.set_draw_color(pixels::Color::RGBA(
-            (color.r * 255.0) as u8,
-            (color.g * 255.0) as u8,
-            (color.b * 255.0) as u8,
-            (color.a * 255.0) as u8,
+            quantize(color.r, transfer),
+            quantize(color.g, transfer),
+            quantize(color.b, transfer),
+            quantize(color.a, Transfer::Linear),
         ));
         self.draw_point(Point::new(x as i32, y as i32)).unwrap();
     }
@@ -48,3 +54,95 @@ impl Canvas for sdl2::render::Canvas<Window> {
         self.present();
     }
 }
+
+/// Framebuffer kept in memory. Pixels are stored row by row in linear color,
+/// exactly as the renderer produced them.
+pub struct ImageCanvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl ImageCanvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+}
+
+impl Canvas for ImageCanvas {
+    fn draw_point(&mut self, color: &Color, x: u32, y: u32) {
+        let index = self.index(x, y);
+        self.pixels[index] = *color;
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn clear(&mut self) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = Color::default();
+        }
+    }
+
+    fn flush(&mut self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn image_canvas_new() {
+        let canvas = ImageCanvas::new(4, 3);
+
+        assert_eq!(canvas.width(), 4);
+        assert_eq!(canvas.height(), 3);
+        assert_eq!(canvas.pixels().len(), 12);
+    }
+
+    #[test]
+    fn image_canvas_draw_tile() {
+        let mut canvas = ImageCanvas::new(4, 4);
+        let mut tile = Tile::new(2, 1, 2, 3);
+        for x in 0..tile.width {
+            for y in 0..tile.height {
+                let index = (x * tile.height + y) as usize;
+                tile.data[index] = Color::rgb(x as f32, y as f32, 1.0);
+            }
+        }
+
+        canvas.draw_tile(&tile);
+
+        assert_eq!(canvas.pixel(0, 0).b, 0.0);
+        assert_eq!(canvas.pixel(1, 2).b, 0.0);
+        for x in 0..tile.width {
+            for y in 0..tile.height {
+                let pixel = canvas.pixel(x + tile.x, y + tile.y);
+                assert_eq!(pixel.r, x as f32);
+                assert_eq!(pixel.g, y as f32);
+                assert_eq!(pixel.b, 1.0);
+            }
+        }
+    }
+}
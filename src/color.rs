@@ -18,6 +18,11 @@ impl Color {
     pub fn rgb(r: f32, g: f32, b: f32) -> Color {
         Color::rgba(r, g, b, 1.0)
     }
+
+    /// Relative luminance with the Rec. 709 primaries.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
 }
 
 impl Default for Color {
@@ -53,6 +58,19 @@ impl std::ops::AddAssign for Color {
     }
 }
 
+impl std::ops::Sub for Color {
+    type Output = Color;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Color {
+            r: self.r - rhs.r,
+            g: self.g - rhs.g,
+            b: self.b - rhs.b,
+            a: self.a - rhs.a,
+        }
+    }
+}
+
 impl std::ops::Mul<f32> for Color {
     type Output = Color;
 
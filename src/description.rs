@@ -0,0 +1,2023 @@
+//! Declarative scene files.
+//!
+//! A scene is described in TOML with a `[render]` table for the output and
+//! sampling options, a `[camera]` table with the parameters of
+//! `Camera::new`, named `[textures.<name>]`, `[materials.<name>]` and
+//! `[media.<name>]` tables, a list of `[[entities]]` and a list of
+//! `[[lights]]`:
+//!
+//! ```toml
+//! [render]
+//! width = 640
+//! height = 360
+//! samples = 10
+//! sampler = "sobol"
+//! tile_order = "spiral"
+//!
+//! [camera]
+//! look_from = [8.0, 2.0, 3.0]
+//! look_at = [0.0, 0.0, 0.0]
+//! vfov = 60.0
+//!
+//! [textures.tiles]
+//! type = "checker"
+//! even = [0.9, 0.9, 0.9]
+//! odd = [0.2, 0.2, 0.2]
+//! size = 2.0
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = "tiles"
+//!
+//! [[entities]]
+//! type = "sphere"
+//! center = [0.0, -1000.0, 0.0]
+//! radius = 1000.0
+//! material = "ground"
+//!
+//! [[lights]]
+//! type = "point"
+//! position = [0.0, 5.0, 0.0]
+//! intensity = 50.0
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adaptive::AdaptiveOptions,
+    camera::Camera,
+    color::Color,
+    entity::{sphere::Sphere, triangle::Triangle, Entity},
+    light::{
+        AreaLight, DirectionalLight, EnvironmentMap, Light, PointLight, Shape, Sky, SpotLight,
+    },
+    material::{
+        Conductor, ConductorPreset, Dielectric, DiffuseLight, Lambertian, Material, Metal,
+        Principled, RoughDielectric,
+    },
+    medium::Medium,
+    obj::{self, ObjError},
+    output::hdr,
+    sampler::SamplerKind,
+    scene::{Background, Scene},
+    texture::{load_image, ImageTexture, Noise, NoisePattern, Texture, WrapMode},
+    tile::{TileConfig, TileOrder},
+    vector::Vector3,
+    RenderOptions,
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    Invalid(String),
+    Obj(ObjError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{}", error),
+            SceneError::Parse(error) => write!(f, "{}", error),
+            SceneError::Serialize(error) => write!(f, "{}", error),
+            SceneError::Invalid(message) => write!(f, "{}", message),
+            SceneError::Obj(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(error: io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for SceneError {
+    fn from(error: toml::de::Error) -> Self {
+        SceneError::Parse(error)
+    }
+}
+
+impl From<toml::ser::Error> for SceneError {
+    fn from(error: toml::ser::Error) -> Self {
+        SceneError::Serialize(error)
+    }
+}
+
+impl From<ObjError> for SceneError {
+    fn from(error: ObjError) -> Self {
+        SceneError::Obj(error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub render: RenderDescription,
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub background: BackgroundDescription,
+    /// Medium filling the space outside of the entities.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fog: Option<MediumDescription>,
+    #[serde(default)]
+    pub textures: BTreeMap<String, TextureDescription>,
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDescription>,
+    #[serde(default)]
+    pub media: BTreeMap<String, MediumDescription>,
+    #[serde(default)]
+    pub entities: Vec<EntityDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderDescription {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub max_scatter: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tile_order: TileOrder,
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    /// Enables adaptive sampling, `samples` is then the maximum.
+    pub adaptive_threshold: Option<f32>,
+    pub min_samples: u32,
+}
+
+impl Default for RenderDescription {
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 360,
+            samples: 10,
+            max_scatter: 10,
+            tile_width: 128,
+            tile_height: 72,
+            tile_order: TileOrder::default(),
+            seed: 0,
+            sampler: SamplerKind::default(),
+            adaptive_threshold: None,
+            min_samples: AdaptiveOptions::default().min_samples,
+        }
+    }
+}
+
+impl RenderDescription {
+    /// Checks the options on their own, for renders that don't come from a
+    /// scene file.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        positive("render.width", self.width as f32)?;
+        positive("render.height", self.height as f32)?;
+        positive("render.samples", self.samples as f32)?;
+        positive("render.max_scatter", self.max_scatter as f32)?;
+        positive("render.tile_width", self.tile_width as f32)?;
+        positive("render.tile_height", self.tile_height as f32)?;
+        positive("render.min_samples", self.min_samples as f32)?;
+        if let Some(threshold) = self.adaptive_threshold {
+            positive("render.adaptive_threshold", threshold)?;
+        }
+        Ok(())
+    }
+
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            samples: self.samples,
+            max_scatter: self.max_scatter,
+            tile_config: TileConfig::new(self.tile_width, self.tile_height)
+                .with_order(self.tile_order),
+            seed: self.seed,
+            sampler: self.sampler,
+            adaptive: self.adaptive_threshold.map(|threshold| AdaptiveOptions {
+                min_samples: self.min_samples,
+                threshold,
+            }),
+            ..RenderOptions::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub look_from: [f32; 3],
+    pub look_at: [f32; 3],
+    #[serde(default = "default_up")]
+    pub up: [f32; 3],
+    pub vfov: f32,
+    #[serde(default)]
+    pub aperture: f32,
+    /// Defaults to the distance between `look_from` and `look_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_distance: Option<f32>,
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDescription {
+    Constant {
+        color: [f32; 3],
+    },
+    /// 3D checker board of cubes with sides of `size`.
+    Checker {
+        even: TextureReference,
+        odd: TextureReference,
+        #[serde(default = "default_checker_size")]
+        size: f32,
+    },
+    /// PNG, JPEG or Radiance HDR image, relative to the scene file.
+    Image {
+        file: PathBuf,
+        #[serde(default)]
+        wrap: WrapMode,
+    },
+    Transform {
+        texture: TextureReference,
+        #[serde(default = "default_scale")]
+        scale: [f32; 3],
+        #[serde(default)]
+        offset: [f32; 3],
+    },
+    /// Blends from `low` to `high` by a noise pattern. The same `seed`
+    /// always gives the same pattern, `scale` is its frequency.
+    Noise {
+        #[serde(default)]
+        pattern: NoisePattern,
+        #[serde(default = "default_noise_scale")]
+        scale: f32,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default)]
+        seed: u64,
+        #[serde(default = "default_low")]
+        low: TextureReference,
+        #[serde(default = "default_high")]
+        high: TextureReference,
+    },
+}
+
+fn default_checker_size() -> f32 {
+    1.0
+}
+
+fn default_noise_scale() -> f32 {
+    1.0
+}
+
+fn default_octaves() -> u32 {
+    6
+}
+
+fn default_low() -> TextureReference {
+    TextureReference::Value(0.0)
+}
+
+fn default_high() -> TextureReference {
+    TextureReference::Value(1.0)
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+/// A constant value or color, or the name of one of the `[textures]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TextureReference {
+    Value(f32),
+    Color([f32; 3]),
+    Name(String),
+}
+
+impl TextureReference {
+    fn constant(&self) -> Option<[f32; 3]> {
+        match *self {
+            TextureReference::Value(v) => Some([v, v, v]),
+            TextureReference::Color(c) => Some(c),
+            TextureReference::Name(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureReference,
+    },
+    Metal {
+        albedo: TextureReference,
+        fuzz: TextureReference,
+    },
+    /// Smooth glass, or frosted glass with a `roughness`.
+    Dielectric {
+        ior: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        roughness: Option<TextureReference>,
+    },
+    /// Emits `color` scaled by `intensity` from the front face.
+    DiffuseLight {
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// Rough metal with the index of refraction of one of the presets or
+    /// given as `{ eta = [...], k = [...] }`.
+    Conductor {
+        ior: ConductorIor,
+        #[serde(default = "default_roughness")]
+        roughness: TextureReference,
+    },
+    /// One material for plastic, metal, cloth and glass, see
+    /// [`PrincipledDescription`].
+    Principled(Box<PrincipledDescription>),
+    /// Invisible boundary of the `interior` medium of an entity.
+    Interface,
+}
+
+/// Parameters of the principled BSDF. All of them are optional and, except
+/// `ior`, textures with values between 0 and 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrincipledDescription {
+    pub base_color: TextureReference,
+    pub metallic: TextureReference,
+    pub roughness: TextureReference,
+    pub specular: TextureReference,
+    pub specular_tint: TextureReference,
+    pub sheen: TextureReference,
+    pub sheen_tint: TextureReference,
+    pub clearcoat: TextureReference,
+    pub clearcoat_gloss: TextureReference,
+    pub anisotropic: TextureReference,
+    pub transmission: TextureReference,
+    pub ior: f32,
+}
+
+impl Default for PrincipledDescription {
+    fn default() -> Self {
+        let value = TextureReference::Value;
+        Self {
+            base_color: value(0.8),
+            metallic: value(0.0),
+            roughness: value(0.5),
+            specular: value(0.5),
+            specular_tint: value(0.0),
+            sheen: value(0.0),
+            sheen_tint: value(0.5),
+            clearcoat: value(0.0),
+            clearcoat_gloss: value(1.0),
+            anisotropic: value(0.0),
+            transmission: value(0.0),
+            ior: 1.45,
+        }
+    }
+}
+
+impl PrincipledDescription {
+    /// The texture parameters with their keys.
+    fn textures(&self) -> [(&'static str, &TextureReference); 11] {
+        [
+            ("base_color", &self.base_color),
+            ("metallic", &self.metallic),
+            ("roughness", &self.roughness),
+            ("specular", &self.specular),
+            ("specular_tint", &self.specular_tint),
+            ("sheen", &self.sheen),
+            ("sheen_tint", &self.sheen_tint),
+            ("clearcoat", &self.clearcoat),
+            ("clearcoat_gloss", &self.clearcoat_gloss),
+            ("anisotropic", &self.anisotropic),
+            ("transmission", &self.transmission),
+        ]
+    }
+}
+
+/// Homogeneous medium with `absorption` and `scattering` coefficients per
+/// unit of distance and the asymmetry `g` of its Henyey-Greenstein phase
+/// function, from -1 for back scattering to 1 for forward scattering.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MediumDescription {
+    pub absorption: [f32; 3],
+    pub scattering: [f32; 3],
+    pub g: f32,
+}
+
+fn default_roughness() -> TextureReference {
+    TextureReference::Value(0.0)
+}
+
+/// Complex index of refraction `eta + i k` at red, green and blue
+/// wavelengths.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConductorIor {
+    Preset(ConductorPreset),
+    Complex { eta: [f32; 3], k: [f32; 3] },
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BackgroundDescription {
+    #[default]
+    Gradient,
+    Solid {
+        color: [f32; 3],
+    },
+    /// Equirectangular Radiance HDR image, relative to the scene file,
+    /// rotated by `rotation` degrees around the y axis.
+    Environment {
+        file: PathBuf,
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// Daylight sky with the sun `elevation` degrees above the horizon and
+    /// `azimuth` degrees clockwise from the negative z axis seen from above.
+    Sky {
+        elevation: f32,
+        #[serde(default)]
+        azimuth: f32,
+        #[serde(default = "default_turbidity")]
+        turbidity: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum EntityDescription {
+    /// `interior` names a medium that fills the sphere.
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        interior: Option<String>,
+    },
+    Triangle {
+        vertices: [[f32; 3]; 3],
+        material: String,
+    },
+    /// Wavefront OBJ file, relative to the scene file. The material from the
+    /// scene replaces the ones from the OBJ material libraries, and the
+    /// `interior` medium fills every mesh of the file.
+    Mesh {
+        file: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        interior: Option<String>,
+    },
+}
+
+/// Lights that aren't part of the geometry. They emit `color` scaled by
+/// `intensity`: the intensity for point and spot lights, the irradiance for
+/// directional lights and the radiance for area lights.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LightDescription {
+    Point {
+        position: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// `cone_angle` is measured from the axis to the edge of the cone, the
+    /// light fades out over its outer `falloff` degrees.
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        cone_angle: f32,
+        #[serde(default)]
+        falloff: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// `direction` is the direction the light travels in. The angular
+    /// diameter defaults to the one of the sun.
+    Directional {
+        direction: [f32; 3],
+        #[serde(default = "default_angular_diameter")]
+        angular_diameter: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// Parallelogram emitting towards the cross product of the edges.
+    Rectangle {
+        corner: [f32; 3],
+        edge_u: [f32; 3],
+        edge_v: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+}
+
+fn default_light_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_angular_diameter() -> f32 {
+    0.53
+}
+
+impl SceneDescription {
+    pub fn parse(text: &str) -> Result<Self, SceneError> {
+        let description: SceneDescription = toml::from_str(text)?;
+        description.validate()?;
+        Ok(description)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, SceneError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        Ok(fs::write(path, self.to_toml()?)?)
+    }
+
+    pub fn render_options(&self) -> RenderOptions {
+        self.render.render_options()
+    }
+
+    /// Builds the scene. Mesh files are resolved relative to `base`.
+    pub fn build_scene(&self, base: &Path) -> Result<Scene, SceneError> {
+        self.validate()?;
+
+        let camera = &self.camera;
+        let look_from = vector(camera.look_from);
+        let look_at = vector(camera.look_at);
+        let focus_distance = camera
+            .focus_distance
+            .unwrap_or_else(|| (look_from - look_at).length());
+        let mut scene = Scene::new(Camera::new(
+            look_from,
+            look_at,
+            vector(camera.up),
+            camera.vfov,
+            self.render.width as f32 / self.render.height as f32,
+            camera.aperture,
+            focus_distance,
+        ));
+        scene.background = self.background.build(base)?;
+        scene.fog = self.fog.as_ref().map(MediumDescription::build);
+
+        let mut textures = Textures {
+            descriptions: &self.textures,
+            base,
+            built: BTreeMap::new(),
+        };
+        let materials = self
+            .materials
+            .iter()
+            .map(|(name, material)| Ok((name.as_str(), material.build(&mut textures)?)))
+            .collect::<Result<BTreeMap<_, _>, SceneError>>()?;
+        let material = |name: &str| materials[name].clone();
+        let medium = |name: &str| self.media[name].build();
+
+        for entity in self.entities.iter() {
+            match entity {
+                EntityDescription::Sphere {
+                    center,
+                    radius,
+                    material: name,
+                    interior,
+                } => {
+                    let sphere = Sphere::new(vector(*center), *radius, material(name));
+                    let sphere = match interior {
+                        Some(interior) => sphere.with_interior(medium(interior)),
+                        None => sphere,
+                    };
+                    scene.add(Entity::Sphere(sphere));
+                }
+                EntityDescription::Triangle {
+                    vertices,
+                    material: name,
+                } => scene.add(Entity::Triangle(Triangle::new(
+                    [
+                        vector(vertices[0]),
+                        vector(vertices[1]),
+                        vector(vertices[2]),
+                    ],
+                    material(name),
+                ))),
+                EntityDescription::Mesh {
+                    file,
+                    material: name,
+                    interior,
+                } => {
+                    for mesh in obj::load(base.join(file))? {
+                        let mesh = match name {
+                            Some(name) => mesh.with_material(material(name)),
+                            None => mesh,
+                        };
+                        let mesh = match interior {
+                            Some(interior) => mesh.with_interior(medium(interior)),
+                            None => mesh,
+                        };
+                        scene.add(Entity::Mesh(mesh));
+                    }
+                }
+            }
+        }
+
+        for light in self.lights.iter() {
+            scene.add_light(light.build());
+        }
+
+        Ok(scene)
+    }
+
+    fn validate(&self) -> Result<(), SceneError> {
+        self.render.validate()?;
+
+        match &self.background {
+            BackgroundDescription::Gradient => {}
+            BackgroundDescription::Solid { color: c } => color("background", "color", c)?,
+            BackgroundDescription::Environment {
+                rotation,
+                intensity,
+                ..
+            } => {
+                if !rotation.is_finite() {
+                    return Err(invalid("background.rotation", "must be a number"));
+                }
+                if !(*intensity >= 0.0 && intensity.is_finite()) {
+                    return Err(invalid("background.intensity", "must not be negative"));
+                }
+            }
+            BackgroundDescription::Sky {
+                elevation,
+                azimuth,
+                turbidity,
+                intensity,
+            } => {
+                if !(0.0..=90.0).contains(elevation) {
+                    return Err(invalid(
+                        "background.elevation",
+                        "must be between 0 and 90 degrees",
+                    ));
+                }
+                if !azimuth.is_finite() {
+                    return Err(invalid("background.azimuth", "must be a number"));
+                }
+                if !(1.7..=10.0).contains(turbidity) {
+                    return Err(invalid(
+                        "background.turbidity",
+                        "must be between 1.7 and 10",
+                    ));
+                }
+                if !(*intensity >= 0.0 && intensity.is_finite()) {
+                    return Err(invalid("background.intensity", "must not be negative"));
+                }
+            }
+        }
+
+        let camera = &self.camera;
+        if !(camera.vfov > 0.0 && camera.vfov < 180.0) {
+            return Err(invalid("camera.vfov", "must be between 0 and 180 degrees"));
+        }
+        if camera.aperture < 0.0 {
+            return Err(invalid("camera.aperture", "must not be negative"));
+        }
+        if let Some(distance) = camera.focus_distance {
+            positive("camera.focus_distance", distance)?;
+        }
+        if camera.look_from == camera.look_at {
+            return Err(invalid("camera.look_at", "must differ from look_from"));
+        }
+        // The camera basis is built from the cross product of both.
+        let view = (vector(camera.look_at) - vector(camera.look_from)).normalized();
+        let up = vector(camera.up);
+        let sine = (view ^ up).length();
+        if !(sine.is_finite() && sine > 1e-4 * up.length()) {
+            return Err(invalid(
+                "camera.up",
+                "must not be zero or parallel to the view direction",
+            ));
+        }
+
+        for (name, texture) in self.textures.iter() {
+            let path = format!("textures.{}", name);
+            let references = match texture {
+                TextureDescription::Constant { color: c } => {
+                    color(&path, "color", c)?;
+                    vec![]
+                }
+                TextureDescription::Checker { even, odd, size } => {
+                    positive(&format!("{}.size", path), *size)?;
+                    vec![("even", even), ("odd", odd)]
+                }
+                TextureDescription::Image { .. } => vec![],
+                TextureDescription::Transform {
+                    texture,
+                    scale,
+                    offset,
+                } => {
+                    if !scale.iter().chain(offset.iter()).all(|x| x.is_finite()) {
+                        return Err(invalid(
+                            &format!("{}.scale", path),
+                            "scale and offset must be numbers",
+                        ));
+                    }
+                    vec![("texture", texture)]
+                }
+                TextureDescription::Noise {
+                    scale,
+                    octaves,
+                    low,
+                    high,
+                    ..
+                } => {
+                    positive(&format!("{}.scale", path), *scale)?;
+                    if !(1..=16).contains(octaves) {
+                        return Err(invalid(
+                            &format!("{}.octaves", path),
+                            "must be between 1 and 16",
+                        ));
+                    }
+                    vec![("low", low), ("high", high)]
+                }
+            };
+            for (key, reference) in references {
+                self.validate_texture_reference(&path, key, reference)?;
+            }
+            self.validate_texture_cycles(name, &mut Vec::new())?;
+        }
+
+        for (name, material) in self.materials.iter() {
+            let path = format!("materials.{}", name);
+            match material {
+                MaterialDescription::Lambertian { albedo } => {
+                    self.validate_texture_reference(&path, "albedo", albedo)?
+                }
+                MaterialDescription::Metal { albedo, fuzz } => {
+                    self.validate_texture_reference(&path, "albedo", albedo)?;
+                    self.validate_fraction(&path, "fuzz", fuzz)?;
+                }
+                MaterialDescription::Dielectric { ior, roughness } => {
+                    positive(&format!("{}.ior", path), *ior)?;
+                    if let Some(roughness) = roughness {
+                        self.validate_fraction(&path, "roughness", roughness)?;
+                    }
+                }
+                MaterialDescription::Conductor { ior, roughness } => {
+                    if let ConductorIor::Complex { eta, k } = ior {
+                        color(&path, "ior.eta", eta)?;
+                        color(&path, "ior.k", k)?;
+                    }
+                    self.validate_fraction(&path, "roughness", roughness)?;
+                }
+                MaterialDescription::Interface => {}
+                MaterialDescription::Principled(principled) => {
+                    for (key, reference) in principled.textures().iter() {
+                        self.validate_fraction(&path, key, reference)?;
+                    }
+                    positive(&format!("{}.ior", path), principled.ior)?;
+                }
+                MaterialDescription::DiffuseLight {
+                    color: c,
+                    intensity,
+                } => {
+                    color(&path, "color", c)?;
+                    if !(*intensity >= 0.0 && intensity.is_finite()) {
+                        return Err(invalid(
+                            &format!("{}.intensity", path),
+                            "must not be negative",
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(fog) = &self.fog {
+            fog.validate("fog")?;
+        }
+        for (name, medium) in self.media.iter() {
+            medium.validate(&format!("media.{}", name))?;
+        }
+
+        for (index, entity) in self.entities.iter().enumerate() {
+            let path = format!("entities[{}]", index);
+            let (material, interior) = match entity {
+                EntityDescription::Sphere {
+                    radius,
+                    material,
+                    interior,
+                    ..
+                } => {
+                    positive(&format!("{}.radius", path), *radius)?;
+                    (Some(material), interior.as_ref())
+                }
+                EntityDescription::Triangle { material, .. } => (Some(material), None),
+                EntityDescription::Mesh {
+                    material, interior, ..
+                } => (material.as_ref(), interior.as_ref()),
+            };
+            if let Some(interior) = interior {
+                if !self.media.contains_key(interior) {
+                    return Err(invalid(
+                        &format!("{}.interior", path),
+                        &format!("unknown medium `{}`", interior),
+                    ));
+                }
+            }
+            if let Some(material) = material {
+                if !self.materials.contains_key(material) {
+                    return Err(invalid(
+                        &format!("{}.material", path),
+                        &format!("unknown material `{}`", material),
+                    ));
+                }
+            }
+        }
+
+        for (index, light) in self.lights.iter().enumerate() {
+            light.validate(&format!("lights[{}]", index))?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_texture_reference(
+        &self,
+        path: &str,
+        key: &str,
+        reference: &TextureReference,
+    ) -> Result<(), SceneError> {
+        match reference {
+            TextureReference::Name(name) if !self.textures.contains_key(name) => Err(invalid(
+                &format!("{}.{}", path, key),
+                &format!("unknown texture `{}`", name),
+            )),
+            TextureReference::Name(_) => Ok(()),
+            constant => color(path, key, &constant.constant().unwrap()),
+        }
+    }
+
+    /// Like [`SceneDescription::validate_texture_reference`], but constants
+    /// must also be between 0 and 1.
+    fn validate_fraction(
+        &self,
+        path: &str,
+        key: &str,
+        reference: &TextureReference,
+    ) -> Result<(), SceneError> {
+        self.validate_texture_reference(path, key, reference)?;
+        let constant = reference.constant().unwrap_or([0.0; 3]);
+        if !constant.iter().all(|c| (0.0..=1.0).contains(c)) {
+            return Err(invalid(
+                &format!("{}.{}", path, key),
+                "must be between 0 and 1",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Follows the textures referenced by `name`, `visiting` holds the chain
+    /// of textures that led to it.
+    fn validate_texture_cycles<'a>(
+        &'a self,
+        name: &'a str,
+        visiting: &mut Vec<&'a str>,
+    ) -> Result<(), SceneError> {
+        if visiting.contains(&name) {
+            visiting.push(name);
+            return Err(invalid(
+                &format!("textures.{}", visiting[0]),
+                &format!("texture refers to itself: {}", visiting.join(" -> ")),
+            ));
+        }
+        let references = match &self.textures[name] {
+            TextureDescription::Checker { even, odd, .. } => vec![even, odd],
+            TextureDescription::Transform { texture, .. } => vec![texture],
+            TextureDescription::Noise { low, high, .. } => vec![low, high],
+            _ => vec![],
+        };
+        visiting.push(name);
+        for reference in references {
+            if let TextureReference::Name(next) = reference {
+                self.validate_texture_cycles(next, visiting)?;
+            }
+        }
+        visiting.pop();
+        Ok(())
+    }
+}
+
+/// Builds the named textures on first use, so that every material that
+/// refers to an image shares the same pixels.
+struct Textures<'a> {
+    descriptions: &'a BTreeMap<String, TextureDescription>,
+    base: &'a Path,
+    built: BTreeMap<&'a str, Texture>,
+}
+
+impl<'a> Textures<'a> {
+    fn get(&mut self, reference: &'a TextureReference) -> Result<Texture, SceneError> {
+        let name = match reference {
+            TextureReference::Name(name) => name.as_str(),
+            constant => return Ok(Texture::Constant(rgb(constant.constant().unwrap()))),
+        };
+        if let Some(texture) = self.built.get(name) {
+            return Ok(texture.clone());
+        }
+
+        let texture = match &self.descriptions[name] {
+            TextureDescription::Constant { color } => Texture::Constant(rgb(*color)),
+            TextureDescription::Checker { even, odd, size } => Texture::Checker {
+                even: Arc::new(self.get(even)?),
+                odd: Arc::new(self.get(odd)?),
+                size: *size,
+            },
+            TextureDescription::Image { file, wrap } => {
+                let path = self.base.join(file);
+                let image = load_image(&path).map_err(|error| {
+                    invalid(
+                        &format!("textures.{}.file", name),
+                        &format!("{}: {}", path.display(), error),
+                    )
+                })?;
+                Texture::Image(ImageTexture::new(Arc::new(image), *wrap))
+            }
+            TextureDescription::Transform {
+                texture,
+                scale,
+                offset,
+            } => Texture::Transform {
+                texture: Arc::new(self.get(texture)?),
+                scale: vector(*scale),
+                offset: vector(*offset),
+            },
+            TextureDescription::Noise {
+                pattern,
+                scale,
+                octaves,
+                seed,
+                low,
+                high,
+            } => Texture::Noise {
+                noise: Arc::new(Noise::new(*seed)),
+                pattern: *pattern,
+                scale: *scale,
+                octaves: *octaves,
+                low: Arc::new(self.get(low)?),
+                high: Arc::new(self.get(high)?),
+            },
+        };
+        self.built.insert(name, texture.clone());
+        Ok(texture)
+    }
+}
+
+impl LightDescription {
+    fn build(&self) -> Light {
+        match *self {
+            LightDescription::Point {
+                position,
+                color,
+                intensity,
+            } => Light::Point(PointLight::new(vector(position), intensity * rgb(color))),
+            LightDescription::Spot {
+                position,
+                direction,
+                cone_angle,
+                falloff,
+                color,
+                intensity,
+            } => Light::Spot(SpotLight::new(
+                vector(position),
+                vector(direction),
+                intensity * rgb(color),
+                cone_angle,
+                falloff,
+            )),
+            LightDescription::Directional {
+                direction,
+                angular_diameter,
+                color,
+                intensity,
+            } => Light::Directional(DirectionalLight::new(
+                vector(direction),
+                intensity * rgb(color),
+                angular_diameter,
+            )),
+            LightDescription::Rectangle {
+                corner,
+                edge_u,
+                edge_v,
+                color,
+                intensity,
+            } => area(
+                Shape::Rectangle {
+                    corner: vector(corner),
+                    edge_u: vector(edge_u),
+                    edge_v: vector(edge_v),
+                },
+                intensity * rgb(color),
+            ),
+            LightDescription::Disk {
+                center,
+                normal,
+                radius,
+                color,
+                intensity,
+            } => area(
+                Shape::Disk {
+                    center: vector(center),
+                    normal: vector(normal),
+                    radius,
+                },
+                intensity * rgb(color),
+            ),
+            LightDescription::Sphere {
+                center,
+                radius,
+                color,
+                intensity,
+            } => area(
+                Shape::Sphere {
+                    center: vector(center),
+                    radius,
+                },
+                intensity * rgb(color),
+            ),
+        }
+    }
+
+    fn validate(&self, path: &str) -> Result<(), SceneError> {
+        let key = |key: &str| format!("{}.{}", path, key);
+        let (c, intensity) = match self {
+            LightDescription::Point {
+                color, intensity, ..
+            } => (color, intensity),
+            LightDescription::Spot {
+                direction,
+                cone_angle,
+                falloff,
+                color,
+                intensity,
+                ..
+            } => {
+                nonzero(&key("direction"), direction)?;
+                if !(*cone_angle > 0.0 && *cone_angle <= 180.0) {
+                    return Err(invalid(
+                        &key("cone_angle"),
+                        "must be between 0 and 180 degrees",
+                    ));
+                }
+                if !(*falloff >= 0.0 && falloff <= cone_angle) {
+                    return Err(invalid(
+                        &key("falloff"),
+                        "must be between 0 and the cone angle",
+                    ));
+                }
+                (color, intensity)
+            }
+            LightDescription::Directional {
+                direction,
+                angular_diameter,
+                color,
+                intensity,
+            } => {
+                nonzero(&key("direction"), direction)?;
+                if !(*angular_diameter >= 0.0 && *angular_diameter < 180.0) {
+                    return Err(invalid(
+                        &key("angular_diameter"),
+                        "must be between 0 and 180 degrees",
+                    ));
+                }
+                (color, intensity)
+            }
+            LightDescription::Rectangle {
+                edge_u,
+                edge_v,
+                color,
+                intensity,
+                ..
+            } => {
+                if (vector(*edge_u) ^ vector(*edge_v)).length() <= 0.0 {
+                    return Err(invalid(&key("edge_v"), "must not be parallel to edge_u"));
+                }
+                (color, intensity)
+            }
+            LightDescription::Disk {
+                normal,
+                radius,
+                color,
+                intensity,
+                ..
+            } => {
+                nonzero(&key("normal"), normal)?;
+                positive(&key("radius"), *radius)?;
+                (color, intensity)
+            }
+            LightDescription::Sphere {
+                radius,
+                color,
+                intensity,
+                ..
+            } => {
+                positive(&key("radius"), *radius)?;
+                (color, intensity)
+            }
+        };
+        color(path, "color", c)?;
+        if !(*intensity >= 0.0 && intensity.is_finite()) {
+            return Err(invalid(&key("intensity"), "must not be negative"));
+        }
+        Ok(())
+    }
+}
+
+fn area(shape: Shape, radiance: Color) -> Light {
+    Light::Area(AreaLight::new(shape, radiance))
+}
+
+impl MaterialDescription {
+    fn build<'a>(&'a self, textures: &mut Textures<'a>) -> Result<Material, SceneError> {
+        Ok(match *self {
+            MaterialDescription::Lambertian { ref albedo } => {
+                Material::Lambertian(Lambertian::new(textures.get(albedo)?))
+            }
+            MaterialDescription::Metal {
+                ref albedo,
+                ref fuzz,
+            } => Material::Metal(Metal::new(textures.get(albedo)?, textures.get(fuzz)?)),
+            MaterialDescription::Dielectric {
+                ior,
+                roughness: None,
+            } => Material::Dielectric(Dielectric::new(ior)),
+            MaterialDescription::Dielectric {
+                ior,
+                roughness: Some(ref roughness),
+            } => Material::RoughDielectric(RoughDielectric::new(ior, textures.get(roughness)?)),
+            MaterialDescription::DiffuseLight { color, intensity } => {
+                Material::DiffuseLight(DiffuseLight::new(rgb(color), intensity))
+            }
+            MaterialDescription::Conductor {
+                ref ior,
+                ref roughness,
+            } => {
+                let (eta, k) = match *ior {
+                    ConductorIor::Preset(preset) => preset.ior(),
+                    ConductorIor::Complex { eta, k } => (rgb(eta), rgb(k)),
+                };
+                Material::Conductor(Conductor::new(eta, k, textures.get(roughness)?))
+            }
+            MaterialDescription::Interface => Material::Interface,
+            MaterialDescription::Principled(ref principled) => {
+                Material::Principled(Box::new(Principled {
+                    base_color: textures.get(&principled.base_color)?,
+                    metallic: textures.get(&principled.metallic)?,
+                    roughness: textures.get(&principled.roughness)?,
+                    specular: textures.get(&principled.specular)?,
+                    specular_tint: textures.get(&principled.specular_tint)?,
+                    sheen: textures.get(&principled.sheen)?,
+                    sheen_tint: textures.get(&principled.sheen_tint)?,
+                    clearcoat: textures.get(&principled.clearcoat)?,
+                    clearcoat_gloss: textures.get(&principled.clearcoat_gloss)?,
+                    anisotropic: textures.get(&principled.anisotropic)?,
+                    transmission: textures.get(&principled.transmission)?,
+                    ior: principled.ior,
+                }))
+            }
+        })
+    }
+}
+
+impl MediumDescription {
+    fn validate(&self, path: &str) -> Result<(), SceneError> {
+        for (key, value) in [
+            ("absorption", &self.absorption),
+            ("scattering", &self.scattering),
+        ] {
+            if !value.iter().all(|c| *c >= 0.0 && c.is_finite()) {
+                return Err(invalid(
+                    &format!("{}.{}", path, key),
+                    "must not be negative",
+                ));
+            }
+        }
+        if !(self.g > -1.0 && self.g < 1.0) {
+            return Err(invalid(&format!("{}.g", path), "must be between -1 and 1"));
+        }
+        Ok(())
+    }
+
+    fn build(&self) -> Medium {
+        Medium::new(rgb(self.absorption), rgb(self.scattering), self.g)
+    }
+}
+
+impl BackgroundDescription {
+    fn build(&self, base: &Path) -> Result<Background, SceneError> {
+        Ok(match *self {
+            BackgroundDescription::Gradient => Background::Gradient,
+            BackgroundDescription::Solid { color } => Background::Solid(rgb(color)),
+            BackgroundDescription::Environment {
+                ref file,
+                rotation,
+                intensity,
+            } => {
+                let path = base.join(file);
+                let image = hdr::load(&path).map_err(|error| {
+                    invalid("background.file", &format!("{}: {}", path.display(), error))
+                })?;
+                if image.pixels().is_empty() {
+                    return Err(invalid("background.file", "image is empty"));
+                }
+                Background::Environment(Arc::new(EnvironmentMap::new(&image, rotation, intensity)))
+            }
+            BackgroundDescription::Sky {
+                elevation,
+                azimuth,
+                turbidity,
+                intensity,
+            } => Background::Sky(Arc::new(Sky::new(elevation, azimuth, turbidity, intensity))),
+        })
+    }
+}
+
+fn vector(v: [f32; 3]) -> Vector3 {
+    Vector3::xyz(v[0], v[1], v[2])
+}
+
+fn rgb(c: [f32; 3]) -> Color {
+    Color::rgb(c[0], c[1], c[2])
+}
+
+fn invalid(path: &str, message: &str) -> SceneError {
+    SceneError::Invalid(format!("{}: {}", path, message))
+}
+
+fn positive(path: &str, value: f32) -> Result<(), SceneError> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(invalid(path, "must be positive"))
+    }
+}
+
+fn nonzero(path: &str, value: &[f32; 3]) -> Result<(), SceneError> {
+    if vector(*value).length() > 0.0 {
+        Ok(())
+    } else {
+        Err(invalid(path, "must not be zero"))
+    }
+}
+
+fn color(path: &str, key: &str, value: &[f32; 3]) -> Result<(), SceneError> {
+    if value.iter().all(|c| *c >= 0.0 && c.is_finite()) {
+        Ok(())
+    } else {
+        Err(invalid(
+            &format!("{}.{}", path, key),
+            "color components must not be negative",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MINIMAL: &str = "
+        [camera]
+        look_from = [0.0, 0.0, 1.0]
+        look_at = [0.0, 0.0, 0.0]
+        vfov = 90.0
+    ";
+
+    fn error(text: &str) -> String {
+        SceneDescription::parse(text).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn example_scene() {
+        let description = SceneDescription::parse(include_str!("../scenes/spheres.toml")).unwrap();
+
+        assert_eq!(description.render.width, 640);
+        assert_eq!(description.materials.len(), 4);
+        assert_eq!(description.entities.len(), 4);
+
+        let scene = description.build_scene(Path::new(".")).unwrap();
+        assert_eq!(scene.entities().len(), 4);
+        assert_eq!(description.render_options().samples, 10);
+    }
+
+    #[test]
+    fn cornell_box() {
+        let description = SceneDescription::parse(include_str!("../scenes/cornell.toml")).unwrap();
+        assert_eq!(
+            description.background,
+            BackgroundDescription::Solid {
+                color: [0.0, 0.0, 0.0]
+            }
+        );
+
+        let scene = description.build_scene(Path::new(".")).unwrap();
+        let lights = scene
+            .entities()
+            .iter()
+            .filter(|e| match e {
+                Entity::Triangle(triangle) => {
+                    matches!(triangle.material(), Material::DiffuseLight(_))
+                }
+                _ => false,
+            })
+            .count();
+        assert_eq!(lights, 2);
+    }
+
+    #[test]
+    fn lights() {
+        let description = SceneDescription::parse(&format!(
+            "{}
+            [[lights]]
+            type = \"point\"
+            position = [0.0, 2.0, 0.0]
+
+            [[lights]]
+            type = \"directional\"
+            direction = [0.0, -1.0, -1.0]
+            intensity = 3.0
+
+            [[lights]]
+            type = \"rectangle\"
+            corner = [0.0, 2.0, 0.0]
+            edge_u = [1.0, 0.0, 0.0]
+            edge_v = [0.0, 0.0, 1.0]
+            color = [1.0, 0.5, 0.5]
+            ",
+            MINIMAL
+        ))
+        .unwrap();
+        assert_eq!(
+            description.lights[1],
+            LightDescription::Directional {
+                direction: [0.0, -1.0, -1.0],
+                angular_diameter: 0.53,
+                color: [1.0, 1.0, 1.0],
+                intensity: 3.0,
+            }
+        );
+
+        let scene = description.build_scene(Path::new(".")).unwrap();
+        let lights = scene.lights().lights();
+        assert_eq!(lights.len(), 3);
+        assert!(lights[0].is_delta());
+        assert!(!lights[1].is_delta());
+        assert!(matches!(lights[2], Light::Area(_)));
+    }
+
+    #[test]
+    fn environment_background() {
+        let directory = std::env::temp_dir().join("raytracer-environment-test");
+        fs::create_dir_all(&directory).unwrap();
+        let mut image = crate::canvas::ImageCanvas::new(8, 4);
+        crate::canvas::Canvas::draw_point(&mut image, &Color::rgb(4.0, 2.0, 1.0), 3, 1);
+        let mut file = fs::File::create(directory.join("sky.hdr")).unwrap();
+        hdr::write(&mut file, &image, &Default::default()).unwrap();
+
+        let description = SceneDescription::parse(&format!(
+            "{}
+            [background]
+            type = \"environment\"
+            file = \"sky.hdr\"
+            rotation = 90.0
+            ",
+            MINIMAL
+        ))
+        .unwrap();
+        let scene = description.build_scene(&directory).unwrap();
+        assert!(scene.background.is_light());
+        assert!(matches!(scene.lights().lights()[0], Light::Environment(_)));
+
+        let message = description
+            .build_scene(Path::new("missing"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            message.starts_with("background.file: missing"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn textures() {
+        let directory = std::env::temp_dir().join("raytracer-texture-test");
+        fs::create_dir_all(&directory).unwrap();
+        let mut image = crate::canvas::ImageCanvas::new(2, 2);
+        crate::canvas::Canvas::draw_point(&mut image, &Color::rgb(0.0, 1.0, 0.0), 0, 0);
+        crate::output::save(directory.join("grass.png"), &image, &Default::default()).unwrap();
+
+        let description = SceneDescription::parse(&format!(
+            "{}
+            [textures.grass]
+            type = \"image\"
+            file = \"grass.png\"
+            wrap = \"mirror\"
+
+            [textures.tiles]
+            type = \"checker\"
+            even = \"grass\"
+            odd = [0.1, 0.1, 0.1]
+
+            [textures.small]
+            type = \"transform\"
+            texture = \"tiles\"
+            scale = [4.0, 4.0, 4.0]
+
+            [materials.ground]
+            type = \"lambertian\"
+            albedo = \"small\"
+
+            [textures.rough]
+            type = \"noise\"
+            pattern = \"granite\"
+            seed = 3
+            high = 0.5
+
+            [materials.plain]
+            type = \"metal\"
+            albedo = 0.8
+            fuzz = \"rough\"
+
+            [[entities]]
+            type = \"sphere\"
+            center = [0.0, 0.0, -2.0]
+            radius = 1.0
+            material = \"ground\"
+            ",
+            MINIMAL
+        ))
+        .unwrap();
+        assert_eq!(
+            description.textures["tiles"],
+            TextureDescription::Checker {
+                even: TextureReference::Name("grass".to_string()),
+                odd: TextureReference::Color([0.1, 0.1, 0.1]),
+                size: 1.0,
+            }
+        );
+        assert_eq!(
+            description.textures["rough"],
+            TextureDescription::Noise {
+                pattern: NoisePattern::Granite,
+                scale: 1.0,
+                octaves: 6,
+                seed: 3,
+                low: TextureReference::Value(0.0),
+                high: TextureReference::Value(0.5),
+            }
+        );
+
+        let scene = description.build_scene(&directory).unwrap();
+        match scene.entities()[0] {
+            Entity::Sphere(ref sphere) => match sphere.material() {
+                Material::Lambertian(lambertian) => {
+                    assert!(matches!(lambertian.albedo, Texture::Transform { .. }))
+                }
+                _ => panic!("expected a lambertian material"),
+            },
+            _ => panic!("expected a sphere"),
+        }
+
+        let message = description
+            .build_scene(Path::new("missing"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            message.starts_with("textures.grass.file: missing"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn microfacet_materials() {
+        let description = SceneDescription::parse(&format!(
+            "{}
+            [materials.gold]
+            type = \"conductor\"
+            ior = \"gold\"
+            roughness = 0.2
+
+            [materials.chrome]
+            type = \"conductor\"
+            ior = {{ eta = [3.2, 3.1, 2.3], k = [3.3, 3.3, 3.1] }}
+
+            [materials.frosted]
+            type = \"dielectric\"
+            ior = 1.5
+            roughness = 0.3
+
+            [[entities]]
+            type = \"sphere\"
+            center = [0.0, 0.0, -2.0]
+            radius = 1.0
+            material = \"frosted\"
+            ",
+            MINIMAL
+        ))
+        .unwrap();
+        assert_eq!(
+            description.materials["gold"],
+            MaterialDescription::Conductor {
+                ior: ConductorIor::Preset(ConductorPreset::Gold),
+                roughness: TextureReference::Value(0.2),
+            }
+        );
+        assert_eq!(
+            description.materials["chrome"],
+            MaterialDescription::Conductor {
+                ior: ConductorIor::Complex {
+                    eta: [3.2, 3.1, 2.3],
+                    k: [3.3, 3.3, 3.1],
+                },
+                roughness: TextureReference::Value(0.0),
+            }
+        );
+
+        let scene = description.build_scene(Path::new(".")).unwrap();
+        match scene.entities()[0] {
+            Entity::Sphere(ref sphere) => {
+                assert!(matches!(sphere.material(), Material::RoughDielectric(_)))
+            }
+            _ => panic!("expected a sphere"),
+        }
+    }
+
+    #[test]
+    fn principled_material() {
+        let description = SceneDescription::parse(&format!(
+            "{}
+            [materials.car_paint]
+            type = \"principled\"
+            base_color = [0.6, 0.05, 0.05]
+            metallic = 0.3
+            clearcoat = 1.0
+
+            [[entities]]
+            type = \"sphere\"
+            center = [0.0, 0.0, -2.0]
+            radius = 1.0
+            material = \"car_paint\"
+            ",
+            MINIMAL
+        ))
+        .unwrap();
+        assert_eq!(
+            description.materials["car_paint"],
+            MaterialDescription::Principled(Box::new(PrincipledDescription {
+                base_color: TextureReference::Color([0.6, 0.05, 0.05]),
+                metallic: TextureReference::Value(0.3),
+                clearcoat: TextureReference::Value(1.0),
+                ..PrincipledDescription::default()
+            }))
+        );
+
+        let scene = description.build_scene(Path::new(".")).unwrap();
+        match scene.entities()[0] {
+            Entity::Sphere(ref sphere) => {
+                assert!(matches!(sphere.material(), Material::Principled(_)))
+            }
+            _ => panic!("expected a sphere"),
+        }
+    }
+
+    #[test]
+    fn media() {
+        let description = SceneDescription::parse(&format!(
+            "{}
+            [fog]
+            scattering = [0.05, 0.05, 0.05]
+
+            [media.tea]
+            absorption = [0.2, 0.6, 1.4]
+
+            [materials.glass]
+            type = \"dielectric\"
+            ior = 1.33
+
+            [materials.boundary]
+            type = \"interface\"
+
+            [[entities]]
+            type = \"sphere\"
+            center = [0.0, 0.0, -2.0]
+            radius = 1.0
+            material = \"glass\"
+            interior = \"tea\"
+
+            [[entities]]
+            type = \"sphere\"
+            center = [0.0, 0.0, 2.0]
+            radius = 1.0
+            material = \"boundary\"
+            ",
+            MINIMAL
+        ))
+        .unwrap();
+        assert_eq!(
+            description.media["tea"],
+            MediumDescription {
+                absorption: [0.2, 0.6, 1.4],
+                ..MediumDescription::default()
+            }
+        );
+
+        let scene = description.build_scene(Path::new(".")).unwrap();
+        assert!(scene.fog.is_some());
+        match (&scene.entities()[0], &scene.entities()[1]) {
+            (Entity::Sphere(glass), Entity::Sphere(boundary)) => {
+                assert!(glass.interior().is_some());
+                assert!(boundary.interior().is_none());
+                assert!(matches!(boundary.material(), Material::Interface));
+            }
+            _ => panic!("expected spheres"),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let description = SceneDescription::parse(MINIMAL).unwrap();
+
+        assert_eq!(description.render, RenderDescription::default());
+        assert_eq!(description.camera.up, [0.0, 1.0, 0.0]);
+        assert_eq!(description.camera.aperture, 0.0);
+        assert_eq!(description.camera.focus_distance, None);
+        assert!(description.entities.is_empty());
+        assert_eq!(description.background, BackgroundDescription::Gradient);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut description =
+            SceneDescription::parse(include_str!("../scenes/spheres.toml")).unwrap();
+        description.camera.focus_distance = Some(5.0);
+        description.entities.push(EntityDescription::Triangle {
+            vertices: [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            material: "glass".to_string(),
+        });
+        description.entities.push(EntityDescription::Mesh {
+            file: PathBuf::from("models/bunny.obj"),
+            material: None,
+            interior: Some("smoke".to_string()),
+        });
+        description.entities.push(EntityDescription::Sphere {
+            center: [0.0, 1.0, 0.0],
+            radius: 1.0,
+            material: "boundary".to_string(),
+            interior: Some("smoke".to_string()),
+        });
+        description.fog = Some(MediumDescription {
+            absorption: [0.0, 0.0, 0.0],
+            scattering: [0.02, 0.02, 0.02],
+            g: 0.0,
+        });
+        description.media.insert(
+            "smoke".to_string(),
+            MediumDescription {
+                absorption: [0.1, 0.1, 0.1],
+                scattering: [2.0, 2.0, 2.0],
+                g: 0.3,
+            },
+        );
+        description
+            .materials
+            .insert("boundary".to_string(), MaterialDescription::Interface);
+        description.background = BackgroundDescription::Sky {
+            elevation: 35.0,
+            azimuth: 120.0,
+            turbidity: 2.5,
+            intensity: 1.0,
+        };
+        description.lights.push(LightDescription::Spot {
+            position: [0.0, 4.0, 0.0],
+            direction: [0.0, -1.0, 0.0],
+            cone_angle: 30.0,
+            falloff: 5.0,
+            color: [1.0, 1.0, 0.9],
+            intensity: 20.0,
+        });
+        description.lights.push(LightDescription::Disk {
+            center: [0.0, 3.0, 0.0],
+            normal: [0.0, -1.0, 0.0],
+            radius: 0.5,
+            color: [1.0, 1.0, 1.0],
+            intensity: 4.0,
+        });
+        description.textures.insert(
+            "marble".to_string(),
+            TextureDescription::Image {
+                file: PathBuf::from("textures/marble.jpg"),
+                wrap: WrapMode::Clamp,
+            },
+        );
+        description.textures.insert(
+            "veins".to_string(),
+            TextureDescription::Noise {
+                pattern: NoisePattern::Marble,
+                scale: 4.0,
+                octaves: 5,
+                seed: 42,
+                low: TextureReference::Color([0.9, 0.9, 0.85]),
+                high: TextureReference::Name("marble".to_string()),
+            },
+        );
+        description.textures.insert(
+            "floor".to_string(),
+            TextureDescription::Transform {
+                texture: TextureReference::Name("marble".to_string()),
+                scale: [2.0, 2.0, 1.0],
+                offset: [0.0, 0.0, 0.0],
+            },
+        );
+        description.materials.insert(
+            "tiles".to_string(),
+            MaterialDescription::Lambertian {
+                albedo: TextureReference::Name("floor".to_string()),
+            },
+        );
+        description.materials.insert(
+            "copper".to_string(),
+            MaterialDescription::Conductor {
+                ior: ConductorIor::Preset(ConductorPreset::Copper),
+                roughness: TextureReference::Name("veins".to_string()),
+            },
+        );
+        description.materials.insert(
+            "frosted".to_string(),
+            MaterialDescription::Dielectric {
+                ior: 1.33,
+                roughness: Some(TextureReference::Value(0.4)),
+            },
+        );
+        description.materials.insert(
+            "velvet".to_string(),
+            MaterialDescription::Principled(Box::new(PrincipledDescription {
+                base_color: TextureReference::Name("veins".to_string()),
+                roughness: TextureReference::Value(0.9),
+                sheen: TextureReference::Value(1.0),
+                ior: 1.5,
+                ..PrincipledDescription::default()
+            })),
+        );
+        description.materials.insert(
+            "lamp".to_string(),
+            MaterialDescription::DiffuseLight {
+                color: [1.0, 0.9, 0.8],
+                intensity: 15.0,
+            },
+        );
+
+        let text = description.to_toml().unwrap();
+        assert_eq!(SceneDescription::parse(&text).unwrap(), description);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let message = error(&format!("{}\nfov = 60.0", MINIMAL));
+        assert!(message.contains("unknown field `fov`"), "{}", message);
+        assert!(message.contains("for key `camera`"), "{}", message);
+
+        let message = error(&format!(
+            "{}\n[materials.a]\ntype = \"lambertian\"\nalbedo = [1.0, 1.0, 1.0]\nfuzz = 0.5",
+            MINIMAL
+        ));
+        assert!(message.contains("unknown field `fuzz`"), "{}", message);
+
+        let message = error(&format!(
+            "{}\n[materials.a]\ntype = \"principled\"\nsubsurface = 0.5",
+            MINIMAL
+        ));
+        assert!(
+            message.contains("unknown field `subsurface`"),
+            "{}",
+            message
+        );
+
+        let message = error(&format!("{}\n[[entities]]\ntype = \"cube\"", MINIMAL));
+        assert!(message.contains("unknown variant `cube`"), "{}", message);
+
+        let message = error(&format!("{}\n[output]\nfile = \"a.png\"", MINIMAL));
+        assert!(message.contains("unknown field `output`"), "{}", message);
+    }
+
+    #[test]
+    fn bad_values() {
+        let message = error(&format!("{}\n[render]\nsamples = -1", MINIMAL));
+        assert!(message.contains("invalid value"), "{}", message);
+        assert!(message.contains("line 8"), "{}", message);
+
+        let message = error(&MINIMAL.replace("90.0", "\"wide\""));
+        assert!(message.contains("invalid type"), "{}", message);
+
+        assert_eq!(
+            error(&MINIMAL.replace("90.0", "180.0")),
+            "camera.vfov: must be between 0 and 180 degrees"
+        );
+        assert_eq!(
+            error(&format!("{}up = [0.0, 0.0, -2.0]", MINIMAL)),
+            "camera.up: must not be zero or parallel to the view direction"
+        );
+        assert_eq!(
+            error(&format!("{}up = [0.0, 0.0, 0.0]", MINIMAL)),
+            "camera.up: must not be zero or parallel to the view direction"
+        );
+        assert_eq!(
+            error(&format!("{}\n[render]\nwidth = 0", MINIMAL)),
+            "render.width: must be positive"
+        );
+        assert_eq!(
+            error(&format!("{}\n[render]\nmax_scatter = 0", MINIMAL)),
+            "render.max_scatter: must be positive"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[materials.a]\ntype = \"metal\"\nalbedo = [1.0, 1.0, 1.0]\nfuzz = 2.0",
+                MINIMAL
+            )),
+            "materials.a.fuzz: must be between 0 and 1"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[materials.a]\ntype = \"principled\"\nsheen = 1.5",
+                MINIMAL
+            )),
+            "materials.a.sheen: must be between 0 and 1"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[materials.a]\ntype = \"principled\"\nior = 0.0",
+                MINIMAL
+            )),
+            "materials.a.ior: must be positive"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[[entities]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = inf\nmaterial = \"a\"",
+                MINIMAL
+            )),
+            "entities[0].radius: must be positive"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[materials.a]\ntype = \"diffuse_light\"\ncolor = [1.0, 1.0, 1.0]\nintensity = -2.0",
+                MINIMAL
+            )),
+            "materials.a.intensity: must not be negative"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[[entities]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"missing\"",
+                MINIMAL
+            )),
+            "entities[0].material: unknown material `missing`"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[materials.a]\ntype = \"interface\"\n[[entities]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"a\"\ninterior = \"smoke\"",
+                MINIMAL
+            )),
+            "entities[0].interior: unknown medium `smoke`"
+        );
+        assert_eq!(
+            error(&format!("{}\n[media.a]\ng = 1.0", MINIMAL)),
+            "media.a.g: must be between -1 and 1"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[fog]\nabsorption = [0.1, -0.1, 0.1]",
+                MINIMAL
+            )),
+            "fog.absorption: must not be negative"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[[lights]]\ntype = \"spot\"\nposition = [0.0, 0.0, 0.0]\ndirection = [0.0, 0.0, 0.0]\ncone_angle = 30.0",
+                MINIMAL
+            )),
+            "lights[0].direction: must not be zero"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[[lights]]\ntype = \"disk\"\ncenter = [0.0, 0.0, 0.0]\nnormal = [0.0, 1.0, 0.0]\nradius = 1.0\nintensity = -1.0",
+                MINIMAL
+            )),
+            "lights[0].intensity: must not be negative"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[background]\ntype = \"sky\"\nelevation = 30.0\nturbidity = 20.0",
+                MINIMAL
+            )),
+            "background.turbidity: must be between 1.7 and 10"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[materials.a]\ntype = \"lambertian\"\nalbedo = \"wood\"",
+                MINIMAL
+            )),
+            "materials.a.albedo: unknown texture `wood`"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[textures.a]\ntype = \"checker\"\neven = \"b\"\nodd = [0.0, 0.0, 0.0]\n[textures.b]\ntype = \"transform\"\ntexture = \"a\"",
+                MINIMAL
+            )),
+            "textures.a: texture refers to itself: a -> b -> a"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[textures.a]\ntype = \"checker\"\neven = [1.0, 1.0, 1.0]\nodd = [0.0, 0.0, 0.0]\nsize = 0.0",
+                MINIMAL
+            )),
+            "textures.a.size: must be positive"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[textures.a]\ntype = \"noise\"\noctaves = 0",
+                MINIMAL
+            )),
+            "textures.a.octaves: must be between 1 and 16"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[materials.a]\ntype = \"dielectric\"\nior = 1.5\nroughness = 1.5",
+                MINIMAL
+            )),
+            "materials.a.roughness: must be between 0 and 1"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[materials.a]\ntype = \"conductor\"\nior = {{ eta = [1.0, 1.0, 1.0], k = [-1.0, 0.0, 0.0] }}",
+                MINIMAL
+            )),
+            "materials.a.ior.k: color components must not be negative"
+        );
+    }
+}
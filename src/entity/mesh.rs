@@ -0,0 +1,236 @@
+use crate::{
+    aabb::Aabb,
+    bvh::{Bvh, BvhStats},
+    hit::{HitRecord, Hittable},
+    material::Material,
+    medium::Medium,
+    ray::Ray,
+    vector::Vector3,
+};
+
+use super::triangle;
+
+/// Indices of a single triangle into the buffers of its mesh.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MeshFace {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+}
+
+impl MeshFace {
+    pub fn new(positions: [usize; 3]) -> Self {
+        Self {
+            positions,
+            ..Self::default()
+        }
+    }
+}
+
+/// Indexed triangle mesh with shared vertex buffers and its own BVH.
+pub struct Mesh {
+    positions: Vec<Vector3>,
+    normals: Vec<Vector3>,
+    uvs: Vec<(f32, f32)>,
+    faces: Vec<MeshFace>,
+    material: Material,
+    interior: Option<Medium>,
+    bvh: Bvh,
+}
+
+impl Mesh {
+    /// # Panics
+    ///
+    /// Panics if a face refers to an element outside of the buffers.
+    pub fn new(
+        positions: Vec<Vector3>,
+        normals: Vec<Vector3>,
+        uvs: Vec<(f32, f32)>,
+        faces: Vec<MeshFace>,
+        material: Material,
+    ) -> Self {
+        for face in faces.iter() {
+            check_indices("position", &face.positions, positions.len());
+            if let Some(ref indices) = face.normals {
+                check_indices("normal", indices, normals.len());
+            }
+            if let Some(ref indices) = face.uvs {
+                check_indices("uv", indices, uvs.len());
+            }
+        }
+
+        let bounds = faces
+            .iter()
+            .map(|face| triangle::bounds(&gather(&positions, &face.positions)))
+            .collect::<Vec<_>>();
+        let bvh = Bvh::build(&bounds);
+
+        Self {
+            positions,
+            normals,
+            uvs,
+            faces,
+            material,
+            interior: None,
+            bvh,
+        }
+    }
+
+    pub fn positions(&self) -> &[Vector3] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> &[Vector3] {
+        &self.normals
+    }
+
+    pub fn uvs(&self) -> &[(f32, f32)] {
+        &self.uvs
+    }
+
+    pub fn faces(&self) -> &[MeshFace] {
+        &self.faces
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Fills the mesh with a medium, which only makes sense for closed
+    /// meshes.
+    pub fn with_interior(mut self, medium: Medium) -> Self {
+        self.interior = Some(medium);
+        self
+    }
+
+    pub fn interior(&self) -> Option<&Medium> {
+        self.interior.as_ref()
+    }
+
+    pub fn bvh_stats(&self) -> &BvhStats {
+        self.bvh.stats()
+    }
+
+    fn hit_face(&self, index: usize, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let face = &self.faces[index];
+        let vertices = gather(&self.positions, &face.positions);
+        let (t, b1, b2) =
+            triangle::intersect(vertices[0], vertices[1], vertices[2], ray, t_min, t_max)?;
+        let normals = face.normals.map(|indices| gather(&self.normals, &indices));
+        let uvs = face
+            .uvs
+            .map(|[a, b, c]| [self.uvs[a], self.uvs[b], self.uvs[c]]);
+
+        let mut record = triangle::record(
+            ray,
+            t,
+            (b1, b2),
+            &vertices,
+            normals.as_ref(),
+            uvs.as_ref(),
+            &self.material,
+        );
+        record.interior = self.interior.as_ref();
+        record.primitive = index;
+        Some(record)
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.bvh.hit(ray, t_min, t_max, |index, ray, t_min, t_max| {
+            self.hit_face(index, ray, t_min, t_max)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+}
+
+fn gather(buffer: &[Vector3], indices: &[usize; 3]) -> [Vector3; 3] {
+    [buffer[indices[0]], buffer[indices[1]], buffer[indices[2]]]
+}
+
+fn check_indices(kind: &str, indices: &[usize; 3], len: usize) {
+    for &index in indices.iter() {
+        assert!(
+            index < len,
+            "mesh {} index {} is out of range 0..{}",
+            kind,
+            index,
+            len
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{color::Color, material::Lambertian};
+
+    fn material() -> Material {
+        Material::Lambertian(Lambertian::new(Color::rgb(0.5, 0.5, 0.5)))
+    }
+
+    /// Unit quad in the z = 0 plane made of two triangles.
+    fn quad(normals: Vec<Vector3>) -> Mesh {
+        let positions = vec![
+            Vector3::xyz(0.0, 0.0, 0.0),
+            Vector3::xyz(1.0, 0.0, 0.0),
+            Vector3::xyz(1.0, 1.0, 0.0),
+            Vector3::xyz(0.0, 1.0, 0.0),
+        ];
+        let mut faces = vec![MeshFace::new([0, 1, 2]), MeshFace::new([0, 2, 3])];
+        if !normals.is_empty() {
+            faces[0].normals = Some([0, 1, 2]);
+            faces[1].normals = Some([0, 2, 3]);
+        }
+        Mesh::new(positions, normals, Vec::new(), faces, material())
+    }
+
+    #[test]
+    fn hit() {
+        let mesh = quad(Vec::new());
+        let ray = Ray::new(Vector3::xyz(0.25, 0.75, 1.0), Vector3::xyz(0.0, 0.0, -1.0));
+        let hit = mesh.hit(&ray, 0.001, f32::INFINITY).unwrap();
+
+        assert!((hit.t - 1.0).abs() < 1e-6);
+        assert_eq!(hit.normal, Vector3::xyz(0.0, 0.0, 1.0));
+        assert!(hit.barycentric.is_some());
+
+        let miss = Ray::new(Vector3::xyz(1.5, 0.5, 1.0), Vector3::xyz(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&miss, 0.001, f32::INFINITY).is_none());
+        assert_eq!(mesh.bvh_stats().primitive_count, 2);
+    }
+
+    #[test]
+    fn smooth_normals() {
+        let up = Vector3::xyz(0.0, 0.0, 1.0);
+        let right = Vector3::xyz(1.0, 0.0, 1.0).normalized();
+        let mesh = quad(vec![up, right, right, up]);
+
+        let ray = Ray::new(Vector3::xyz(0.5, 0.25, 1.0), Vector3::xyz(0.0, 0.0, -1.0));
+        let hit = mesh.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        let expected = (0.5 * up + 0.5 * right).normalized();
+
+        assert!((hit.normal - expected).length() < 1e-5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_index() {
+        Mesh::new(
+            vec![Vector3::new(); 3],
+            Vec::new(),
+            Vec::new(),
+            vec![MeshFace::new([0, 1, 3])],
+            material(),
+        );
+    }
+}
@@ -1,17 +1,31 @@
-use crate::{hit::Hittable, ray::Ray};
+use crate::{aabb::Aabb, hit::Hittable, ray::Ray};
 
-use self::sphere::Sphere;
+use self::{mesh::Mesh, sphere::Sphere, triangle::Triangle};
 
+pub mod mesh;
 pub mod sphere;
+pub mod triangle;
 
 pub enum Entity {
     Sphere(Sphere),
+    Triangle(Triangle),
+    Mesh(Mesh),
 }
 
 impl Hittable for Entity {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<crate::hit::HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<crate::hit::HitRecord<'_>> {
         match *self {
             Entity::Sphere(ref inner) => inner.hit(ray, t_min, t_max),
+            Entity::Triangle(ref inner) => inner.hit(ray, t_min, t_max),
+            Entity::Mesh(ref inner) => inner.hit(ray, t_min, t_max),
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match *self {
+            Entity::Sphere(ref inner) => inner.bounding_box(),
+            Entity::Triangle(ref inner) => inner.bounding_box(),
+            Entity::Mesh(ref inner) => inner.bounding_box(),
         }
     }
 }
@@ -1,9 +1,15 @@
-use crate::{hit::HitRecord, hit::Hittable, material::Material, ray::Ray, vector::Vector3};
+use std::f32::consts::PI;
+
+use crate::{
+    aabb::Aabb, hit::HitRecord, hit::Hittable, material::Material, medium::Medium, ray::Ray,
+    vector::Vector3,
+};
 
 pub struct Sphere {
     center: Vector3,
     radius: f32,
     material: Material,
+    interior: Option<Medium>,
 }
 
 impl Sphere {
@@ -12,12 +18,35 @@ impl Sphere {
             center,
             radius,
             material,
+            interior: None,
         }
     }
+
+    /// Fills the sphere with a medium.
+    pub fn with_interior(mut self, medium: Medium) -> Self {
+        self.interior = Some(medium);
+        self
+    }
+
+    pub fn center(&self) -> Vector3 {
+        self.center
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn interior(&self) -> Option<&Medium> {
+        self.interior.as_ref()
+    }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let oc: Vector3 = ray.origin() - self.center;
         let a = ray.direction().squared_length();
         let half_b = oc * ray.direction();
@@ -50,11 +79,53 @@ impl Hittable for Sphere {
         let record = HitRecord {
             t,
             point: hit_point,
-            material: self.material,
+            material: &self.material,
             normal,
             face,
+            uv: sphere_uv(outward_normal),
+            barycentric: None,
+            interior: self.interior.as_ref(),
+            entity: 0,
+            primitive: 0,
         };
 
         Some(record)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vector3::xyz(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
+
+/// Longitude and latitude of a point on the unit sphere, both in [0, 1]. The
+/// seam is on the negative x axis and `v` grows from the bottom to the top.
+fn sphere_uv(p: Vector3) -> (f32, f32) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{color::Color, material::Lambertian};
+
+    #[test]
+    fn uv() {
+        let sphere = Sphere::new(
+            Vector3::xyz(0.0, 0.0, -3.0),
+            2.0,
+            Material::Lambertian(Lambertian::new(Color::rgb(0.5, 0.5, 0.5))),
+        );
+        let ray = Ray::new(Vector3::xyz(0.0, 0.0, 0.0), Vector3::xyz(0.0, 0.0, -1.0));
+        let (u, v) = sphere.hit(&ray, 0.001, f32::INFINITY).unwrap().uv;
+        assert!((u - 0.25).abs() < 1e-6);
+        assert!((v - 0.5).abs() < 1e-6);
+
+        let (_, v) = sphere_uv(Vector3::xyz(0.0, 1.0, 0.0));
+        assert_eq!(v, 1.0);
+        let (u, _) = sphere_uv(Vector3::xyz(1.0, 0.0, 0.0));
+        assert_eq!(u, 0.5);
+    }
 }
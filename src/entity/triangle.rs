@@ -0,0 +1,292 @@
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vector::Vector3,
+};
+
+pub struct Triangle {
+    vertices: [Vector3; 3],
+    normals: Option<[Vector3; 3]>,
+    material: Material,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Vector3; 3], material: Material) -> Self {
+        Self {
+            vertices,
+            normals: None,
+            material,
+        }
+    }
+
+    /// Triangle with per-vertex normals which are interpolated across the face.
+    pub fn with_normals(vertices: [Vector3; 3], normals: [Vector3; 3], material: Material) -> Self {
+        Self {
+            vertices,
+            normals: Some(normals),
+            material,
+        }
+    }
+
+    pub fn vertices(&self) -> &[Vector3; 3] {
+        &self.vertices
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let [p0, p1, p2] = self.vertices;
+        let (t, b1, b2) = intersect(p0, p1, p2, ray, t_min, t_max)?;
+        Some(record(
+            ray,
+            t,
+            (b1, b2),
+            &self.vertices,
+            self.normals.as_ref(),
+            None,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        bounds(&self.vertices)
+    }
+}
+
+pub(crate) fn bounds(vertices: &[Vector3; 3]) -> Aabb {
+    Aabb::empty()
+        .grow(vertices[0])
+        .grow(vertices[1])
+        .grow(vertices[2])
+}
+
+/// Hit record at the given barycentric coordinates. Without texture
+/// coordinates the vertices map to (0, 0), (1, 0) and (0, 1).
+pub(crate) fn record<'a>(
+    ray: &Ray,
+    t: f32,
+    barycentric: (f32, f32),
+    vertices: &[Vector3; 3],
+    normals: Option<&[Vector3; 3]>,
+    uvs: Option<&[(f32, f32); 3]>,
+    material: &'a Material,
+) -> HitRecord<'a> {
+    let [p0, p1, p2] = *vertices;
+    let (b1, b2) = barycentric;
+    let geometric_normal = ((p1 - p0) ^ (p2 - p0)).normalized();
+    let (face, normal) = HitRecord::get_face_normal(ray, geometric_normal);
+
+    let normal = match normals {
+        Some([n0, n1, n2]) => {
+            let shading = ((1.0 - b1 - b2) * *n0 + b1 * *n1 + b2 * *n2).normalized();
+            // Keep the shading normal on the side the ray came from.
+            if shading * normal < 0.0 {
+                -shading
+            } else {
+                shading
+            }
+        }
+        None => normal,
+    };
+
+    let uv = match uvs {
+        Some([uv0, uv1, uv2]) => {
+            let b0 = 1.0 - b1 - b2;
+            (
+                b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+            )
+        }
+        None => barycentric,
+    };
+
+    HitRecord {
+        t,
+        point: ray.at(t),
+        material,
+        normal,
+        face,
+        uv,
+        barycentric: Some(barycentric),
+        interior: None,
+        entity: 0,
+        primitive: 0,
+    }
+}
+
+/// Watertight ray/triangle intersection (Woop, Benthin and Wald, 2013).
+///
+/// Returns the distance along the ray and the barycentric coordinates of the
+/// hit point with respect to the second and the third vertex.
+pub(crate) fn intersect(
+    p0: Vector3,
+    p1: Vector3,
+    p2: Vector3,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, f32, f32)> {
+    let origin = ray.origin();
+    let direction = ray.direction();
+
+    let kz = max_axis(direction);
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if direction[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    let sx = direction[kx] / direction[kz];
+    let sy = direction[ky] / direction[kz];
+    let sz = 1.0 / direction[kz];
+
+    let a = p0 - origin;
+    let b = p1 - origin;
+    let c = p2 - origin;
+
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    let mut u = cx * by - cy * bx;
+    let mut v = ax * cy - ay * cx;
+    let mut w = bx * ay - by * ax;
+
+    // Fall back to double precision on the edges to stay watertight.
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let t = (u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz]) / det;
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+
+    Some((t, v / det, w / det))
+}
+
+fn max_axis(v: Vector3) -> usize {
+    let (x, y, z) = (v.x.abs(), v.y.abs(), v.z.abs());
+    if x > y && x > z {
+        0
+    } else if y > z {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{color::Color, material::Lambertian};
+
+    fn material() -> Material {
+        Material::Lambertian(Lambertian::new(Color::rgb(0.5, 0.5, 0.5)))
+    }
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            [
+                Vector3::xyz(0.0, 0.0, 0.0),
+                Vector3::xyz(1.0, 0.0, 0.0),
+                Vector3::xyz(0.0, 1.0, 0.0),
+            ],
+            material(),
+        )
+    }
+
+    #[test]
+    fn hit() {
+        let ray = Ray::new(Vector3::xyz(0.25, 0.5, 2.0), Vector3::xyz(0.0, 0.0, -1.0));
+        let triangle = triangle();
+        let hit = triangle.hit(&ray, 0.001, f32::INFINITY).unwrap();
+
+        assert!((hit.t - 2.0).abs() < 1e-6);
+        assert_eq!(hit.normal, Vector3::xyz(0.0, 0.0, 1.0));
+        let (b1, b2) = hit.barycentric.unwrap();
+        assert!((b1 - 0.25).abs() < 1e-6);
+        assert!((b2 - 0.5).abs() < 1e-6);
+        assert_eq!(hit.uv, (b1, b2));
+    }
+
+    #[test]
+    fn miss() {
+        let outside = Ray::new(Vector3::xyz(0.75, 0.5, 2.0), Vector3::xyz(0.0, 0.0, -1.0));
+        let parallel = Ray::new(Vector3::xyz(0.0, 0.0, 1.0), Vector3::xyz(1.0, 0.0, 0.0));
+        let behind = Ray::new(Vector3::xyz(0.25, 0.25, 2.0), Vector3::xyz(0.0, 0.0, 1.0));
+
+        assert!(triangle().hit(&outside, 0.001, f32::INFINITY).is_none());
+        assert!(triangle().hit(&parallel, 0.001, f32::INFINITY).is_none());
+        assert!(triangle().hit(&behind, 0.001, f32::INFINITY).is_none());
+        let ray = Ray::new(Vector3::xyz(0.25, 0.25, 2.0), Vector3::xyz(0.0, 0.0, -1.0));
+        assert!(triangle().hit(&ray, 0.001, 1.0).is_none());
+    }
+
+    #[test]
+    fn back_face() {
+        let ray = Ray::new(Vector3::xyz(0.25, 0.25, -2.0), Vector3::xyz(0.0, 0.0, 1.0));
+        let triangle = triangle();
+        let hit = triangle.hit(&ray, 0.001, f32::INFINITY).unwrap();
+
+        assert!(matches!(hit.face, crate::hit::Face::Back));
+        assert_eq!(hit.normal, Vector3::xyz(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn shared_edge_is_watertight() {
+        let a = Vector3::xyz(0.0, 0.0, 0.0);
+        let b = Vector3::xyz(1.0, 1.0, 0.0);
+        let first = Triangle::new([a, Vector3::xyz(1.0, 0.0, 0.0), b], material());
+        let second = Triangle::new([a, b, Vector3::xyz(0.0, 1.0, 0.0)], material());
+
+        for i in 1..100 {
+            let s = i as f32 / 100.0;
+            let ray = Ray::new(Vector3::xyz(s, s, 1.0), Vector3::xyz(0.0, 0.0, -1.0));
+            let hits = first.hit(&ray, 0.0, f32::INFINITY).is_some() as u32
+                + second.hit(&ray, 0.0, f32::INFINITY).is_some() as u32;
+            assert!(hits >= 1);
+        }
+    }
+
+    #[test]
+    fn interpolated_normal() {
+        let up = Vector3::xyz(0.0, 0.0, 1.0);
+        let tilted = Vector3::xyz(1.0, 0.0, 1.0).normalized();
+        let triangle = Triangle::with_normals(
+            [
+                Vector3::xyz(0.0, 0.0, 0.0),
+                Vector3::xyz(1.0, 0.0, 0.0),
+                Vector3::xyz(0.0, 1.0, 0.0),
+            ],
+            [up, tilted, up],
+            material(),
+        );
+
+        let ray = Ray::new(Vector3::xyz(0.5, 0.0, 2.0), Vector3::xyz(0.0, 0.0, -1.0));
+        let hit = triangle.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        let expected = (0.5 * up + 0.5 * tilted).normalized();
+
+        assert!((hit.normal - expected).length() < 1e-5);
+    }
+}
@@ -1,26 +1,32 @@
-use crate::{material::Material, ray::Ray, vector::Vector3};
+use crate::{aabb::Aabb, material::Material, medium::Medium, ray::Ray, vector::Vector3};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum Face {
+    #[default]
     Front,
     Back,
 }
 
-impl Default for Face {
-    fn default() -> Self {
-        Face::Front
-    }
-}
-
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub point: Vector3,
     pub normal: Vector3,
-    pub material: Material,
+    pub material: &'a Material,
     pub t: f32,
     pub face: Face,
+    /// Surface coordinates used to look up textures.
+    pub uv: (f32, f32),
+    /// Barycentric coordinates of the hit with respect to the second and
+    /// the third vertex, for primitives made of triangles.
+    pub barycentric: Option<(f32, f32)>,
+    /// Medium inside the entity, for closed entities that hold one.
+    pub interior: Option<&'a Medium>,
+    /// Index of the entity in the scene, set by the scene.
+    pub entity: usize,
+    /// Index of the triangle in a mesh, 0 for other entities.
+    pub primitive: usize,
 }
 
-impl HitRecord {
+impl<'a> HitRecord<'a> {
     pub fn get_face_normal(ray: &Ray, outward_normal: Vector3) -> (Face, Vector3) {
         let is_frontface = ray.direction() * outward_normal < 0.0;
         let normal = if is_frontface {
@@ -37,8 +43,32 @@ impl HitRecord {
 
         (face, normal)
     }
+
+    /// Medium that a ray leaving the hit point along `direction` travels
+    /// through: the interior of the entity after entering through the front
+    /// face, `outside` after leaving through the back face and `current`
+    /// otherwise. Entities without an interior medium never change it.
+    pub fn medium_towards<'m>(
+        &self,
+        direction: Vector3,
+        current: Option<&'m Medium>,
+        outside: Option<&'m Medium>,
+    ) -> Option<&'m Medium>
+    where
+        'a: 'm,
+    {
+        let interior = match self.interior {
+            Some(interior) if direction * self.normal < 0.0 => interior,
+            _ => return current,
+        };
+        match self.face {
+            Face::Front => Some(interior),
+            Face::Back => outside,
+        }
+    }
 }
 
 pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>;
+    fn bounding_box(&self) -> Aabb;
 }
@@ -1,122 +1,973 @@
 use std::time::SystemTime;
 
+use crate::tile::{split_surface, Tile, TileConfig};
+use adaptive::{AdaptiveOptions, PixelStats, SampleCounts};
 use canvas::Canvas;
 use color::Color;
 use hit::Hittable;
-use material::Scatterable;
+use material::{Material, Scatterable};
+use medium::Medium;
+use progressive::{Accumulator, Pass, ProgressiveOptions};
+use rand::Rng;
 use ray::Ray;
+use sampler::{Sampler, SamplerKind};
 use scene::Scene;
-use util::Random;
-use vector::Vector3;
-use threadpool::ThreadPool;
+use std::ops::Range;
 use std::sync::mpsc::channel;
-use std::sync::Arc;
-use crate::tile::{split_surface, TileConfig};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use vector::Vector3;
 
+pub mod aabb;
+pub mod adaptive;
+pub mod bvh;
 pub mod camera;
 pub mod canvas;
 pub mod color;
+pub mod description;
 pub mod entity;
 pub mod hit;
+pub mod light;
 pub mod material;
+pub mod medium;
+pub mod obj;
+pub mod output;
+pub mod progressive;
 pub mod ray;
+pub mod sampler;
 pub mod scene;
+pub mod texture;
+pub mod tile;
 pub mod util;
 pub mod vector;
-pub mod tile;
 
 pub struct RenderOptions {
     pub samples: u32,
     pub max_scatter: u32,
     pub tile_config: TileConfig,
+    /// Number of worker threads, defaults to the available parallelism.
+    pub threads: usize,
+    /// Global seed; together with the pixel and sample index it fully
+    /// determines the random numbers used for a camera sample.
+    pub seed: u64,
+    /// Generator of the pixel and lens positions.
+    pub sampler: SamplerKind,
+    /// Stops sampling converged pixels, `samples` is then the maximum.
+    pub adaptive: Option<AdaptiveOptions>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            samples: 10,
+            max_scatter: 10,
+            tile_config: TileConfig::new(128, 72),
+            threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            seed: 0,
+            sampler: SamplerKind::default(),
+            adaptive: None,
+        }
+    }
 }
 
 pub struct Raytracer<'a, T: Canvas> {
     canvas: &'a mut T,
     options: RenderOptions,
+    sample_counts: SampleCounts,
+}
+
+/// Work shared by all the tiles of a pass.
+struct PassSetup<'p> {
+    options: &'p RenderOptions,
+    width: u32,
+    height: u32,
+    /// Indices of the samples to take in every pixel.
+    samples: Range<u32>,
+    /// Row by row flags of the pixels to sample, all of them if `None`.
+    active: Option<&'p [bool]>,
 }
 
 impl<'a, T: Canvas> Raytracer<'a, T> {
-    pub fn new(canvas: &'a mut T, options: RenderOptions) -> Raytracer<T> {
-        Raytracer { canvas, options }
+    pub fn new(canvas: &'a mut T, options: RenderOptions) -> Raytracer<'a, T> {
+        let sample_counts = SampleCounts::new(canvas.width(), canvas.height());
+        Raytracer {
+            canvas,
+            options,
+            sample_counts,
+        }
     }
 
     pub fn render(&mut self, scene: Arc<Scene>) {
         self.draw_scene(scene);
     }
 
-    fn draw_scene(&mut self, scene: Arc<Scene>) {
+    /// Samples taken in every pixel by the last render.
+    pub fn sample_counts(&self) -> &SampleCounts {
+        &self.sample_counts
+    }
+
+    /// Renders the frame in passes of `progressive.pass_samples` samples per
+    /// pixel and redraws the whole canvas after each of them. `keep_going`
+    /// is called after every pass. Stops at `RenderOptions::samples`, when
+    /// the time budget is spent, when `keep_going` returns false or when
+    /// adaptive sampling finds every pixel converged. Returns the largest
+    /// number of samples per pixel in the final image.
+    pub fn render_progressive<F>(
+        &mut self,
+        scene: Arc<Scene>,
+        progressive: &ProgressiveOptions,
+        mut keep_going: F,
+    ) -> u32
+    where
+        F: FnMut(&Pass) -> bool,
+    {
         let width = self.canvas.width();
         let height = self.canvas.height();
+        let target = self.options.samples;
+        let pass_samples = progressive.pass_samples.max(1);
+        let mut accumulator = Accumulator::new(width, height);
 
-        let samples = self.options.samples;
-        let max_scatter = self.options.max_scatter;
+        let now = SystemTime::now();
+        let mut samples = 0;
+        let mut index = 0;
+        while samples < target {
+            let active = self
+                .options
+                .adaptive
+                .as_ref()
+                .map(|adaptive| accumulator.unconverged(adaptive));
+            if let Some(ref active) = active {
+                if !active.contains(&true) {
+                    break;
+                }
+            }
 
-        let pool = ThreadPool::new(4);
+            let count = pass_samples.min(target - samples);
+            let setup = PassSetup {
+                options: &self.options,
+                width,
+                height,
+                samples: samples..samples + count,
+                active: active.as_deref(),
+            };
+            Raytracer::<T>::render_pass(&setup, &scene, |tile| accumulator.add_tile(&tile));
+            samples += count;
+            accumulator.draw(self.canvas);
 
-        let (tx, rx) = channel();
+            let pass = Pass {
+                index,
+                samples,
+                elapsed: now.elapsed().unwrap_or_default(),
+            };
+            index += 1;
 
-        let tile_width = self.options.tile_config.width;
-        let tile_height = self.options.tile_config.height;
-        let mut tiles = split_surface(width, height, tile_width, tile_height);
-        let total_tiles = tiles.len();
+            let over_budget = progressive
+                .time_budget
+                .is_some_and(|budget| pass.elapsed >= budget);
+            if !keep_going(&pass) || over_budget {
+                break;
+            }
+        }
+        self.sample_counts = accumulator.sample_counts();
+        samples
+    }
 
+    fn draw_scene(&mut self, scene: Arc<Scene>) {
+        let width = self.canvas.width();
+        let height = self.canvas.height();
+        let total_tiles = split_surface(width, height, &self.options.tile_config).len();
+
+        let canvas = &mut *self.canvas;
+        let sample_counts = &mut self.sample_counts;
+        *sample_counts = SampleCounts::new(width, height);
+        let setup = PassSetup {
+            options: &self.options,
+            width,
+            height,
+            samples: 0..self.options.samples,
+            active: None,
+        };
+        let mut current_progress = 0;
         let now = SystemTime::now();
-        for mut tile in tiles.drain(..) {
-            let tx = tx.clone();
-            let scene = scene.clone();
-            pool.execute(move || {
-                for j in 0..tile.height {
-                    for i in 0..tile.width {
-                        let mut color = Color::new();
-                        for _ in 0..samples {
-                            let x = i + tile.x;
-                            let y = height - (j + tile.y + 1);
-                            let dx = f32::random();
-                            let dy = f32::random();
-                            let u = (x as f32 + dx) / width as f32;
-                            let v = (y as f32 + dy) / height as f32;
-                            let ray = scene.camera.ray(u, v);
-                            color += Raytracer::<T>::ray_color(&ray, &scene, max_scatter);
+        Raytracer::<T>::render_pass(&setup, &scene, |tile| {
+            canvas.draw_tile(&tile);
+            for x in 0..tile.width {
+                for y in 0..tile.height {
+                    let samples = tile.stats[(x * tile.height + y) as usize].samples();
+                    sample_counts.set(x + tile.x, y + tile.y, samples);
+                }
+            }
+            current_progress += 1;
+            println!("Tile rendered [{}/{}]", current_progress, total_tiles);
+        });
+        println!("Done: {} ms", now.elapsed().unwrap().as_millis());
+    }
+
+    /// Renders every tile of the frame and hands it to `on_tile` on the
+    /// calling thread.
+    fn render_pass<F: FnMut(Tile)>(setup: &PassSetup, scene: &Scene, mut on_tile: F) {
+        let options = setup.options;
+        let tiles = split_surface(setup.width, setup.height, &options.tile_config);
+        let threads = options.threads.max(1).min(tiles.len().max(1));
+
+        // Workers pull the next tile from the shared queue as soon as they
+        // are done with the previous one, so expensive tiles don't leave
+        // the rest of the threads idle.
+        let queue = Mutex::new(tiles.into_iter());
+        let (tx, rx) = channel();
+
+        thread::scope(|s| {
+            for _ in 0..threads {
+                let tx = tx.clone();
+                let queue = &queue;
+                s.spawn(move || {
+                    let mut sampler = options.sampler.create(options.seed, options.samples);
+                    loop {
+                        let next = queue.lock().unwrap().next();
+                        let mut tile = match next {
+                            Some(tile) => tile,
+                            None => break,
+                        };
+                        Raytracer::<T>::render_tile(&mut tile, scene, setup, sampler.as_mut());
+                        if tx.send(tile).is_err() {
+                            break;
                         }
+                    }
+                });
+            }
+            drop(tx);
+
+            for tile in rx.iter() {
+                on_tile(tile);
+            }
+        });
+    }
 
-                        color.r = (color.r / samples as f32).sqrt();
-                        color.g = (color.g / samples as f32).sqrt();
-                        color.b = (color.b / samples as f32).sqrt();
-                        let index = (i * tile.height + j) as usize;
-                        tile.data[index] = color;
+    /// Collects the samples of the pass for every pixel of the tile. With
+    /// adaptive sampling and no mask of active pixels, a pixel stops as soon
+    /// as it converges.
+    fn render_tile(tile: &mut Tile, scene: &Scene, setup: &PassSetup, sampler: &mut dyn Sampler) {
+        let options = setup.options;
+        let (width, height) = (setup.width, setup.height);
+        let adaptive = match setup.active {
+            None => options.adaptive.as_ref(),
+            Some(_) => None,
+        };
+
+        for j in 0..tile.height {
+            for i in 0..tile.width {
+                let index = (i * tile.height + j) as usize;
+                let x = i + tile.x;
+                if let Some(active) = setup.active {
+                    if !active[((j + tile.y) * width + x) as usize] {
+                        continue;
+                    }
+                }
+
+                let mut stats = PixelStats::default();
+                let y = height - (j + tile.y + 1);
+                for sample in setup.samples.clone() {
+                    sampler.start_pixel_sample(x, y, sample);
+                    let (dx, dy) = sampler.next_2d();
+                    let u = (x as f32 + dx) / width as f32;
+                    let v = (y as f32 + dy) / height as f32;
+                    let ray = scene.camera.ray(u, v, sampler.next_2d());
+                    let mut rng = util::sample_rng(options.seed, x, y, sample);
+                    stats.add(Raytracer::<T>::ray_color(
+                        &ray,
+                        scene,
+                        options.max_scatter,
+                        &mut rng,
+                    ));
+                    if adaptive.is_some_and(|adaptive| stats.is_converged(adaptive)) {
+                        break;
+                    }
+                }
+
+                tile.data[index] = stats.mean();
+                tile.stats[index] = stats;
+            }
+        }
+    }
+
+    /// Path traced radiance along `ray`. At every bounce off a material that
+    /// isn't specular and at every scattering event in a medium one light is
+    /// sampled directly and the result is combined with hitting lights by
+    /// chance through multiple importance sampling with the power heuristic.
+    /// The distance to the next scattering event in a medium is sampled
+    /// proportionally to the transmittance.
+    fn ray_color<R: Rng + ?Sized>(
+        ray: &Ray,
+        scene: &Scene,
+        max_scatter: u32,
+        rng: &mut R,
+    ) -> Color {
+        let lights = scene.lights();
+        let fog = scene.fog.as_ref();
+        let mut radiance = Color::rgba(0.0, 0.0, 0.0, 0.0);
+        let mut throughput = Color::rgb(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        // Density of the direction of `ray`, `None` when it can't be
+        // sampled by the lights (camera rays and specular bounces).
+        let mut scatter_pdf: Option<f32> = None;
+        // Medium around the origin of `ray` and the start of the part of the
+        // ray that is left after passing through interfaces between media.
+        let mut medium = fog;
+        let mut t_min = 0.001;
+
+        // Emission found by following `ray`, weighted against sampling the
+        // lights directly.
+        let weighted = |emitted: Color, light_pdf: f32, scatter_pdf: Option<f32>| {
+            let weight = match scatter_pdf {
+                Some(pdf) => power_heuristic(pdf, light_pdf),
+                None => 1.0,
+            };
+            emitted * weight
+        };
+
+        let mut scattered = 0;
+        while scattered < max_scatter {
+            let hit = scene.hit(&ray, t_min, f32::INFINITY);
+            let t_max = hit.as_ref().map_or(f32::INFINITY, |hit| hit.t);
+            let light = scene.hit_light(&ray, t_min, t_max);
+            let t_max = light.map_or(t_max, |(_, t, _)| t);
+
+            if let Some(medium) = medium {
+                let (t, weight) = medium.sample(&ray, t_min, t_max, rng);
+                throughput = throughput * weight;
+                if throughput.luminance() <= 0.0 {
+                    break;
+                }
+                if let Some(t) = t {
+                    let point = ray.at(t);
+                    let direction = ray.direction().normalized();
+                    let phase = medium.phase();
+                    radiance += throughput
+                        * Raytracer::<T>::sample_light(point, scene, rng, |wi| {
+                            let p = phase.eval(direction, wi);
+                            (Color::rgb(p, p, p), p, Some(medium))
+                        });
+
+                    let wi = phase.sample(direction, (rng.gen(), rng.gen()));
+                    scatter_pdf = Some(phase.eval(direction, wi));
+                    ray = Ray::new(point, wi);
+                    t_min = 0.0;
+                    scattered += 1;
+                    continue;
+                }
+            }
+
+            if let Some((index, t, emitted)) = light {
+                radiance += throughput * weighted(emitted, lights.pdf(index, &ray, t), scatter_pdf);
+                break;
+            }
+            let hit = match hit {
+                Some(hit) => hit,
+                None => {
+                    if !scene.background.is_light() {
+                        radiance += throughput * scene.background.radiance(&ray);
+                    }
+                    let escaped = lights.escaped(&ray);
+                    if escaped.luminance() > 0.0 {
+                        radiance +=
+                            throughput * weighted(escaped, lights.escaped_pdf(&ray), scatter_pdf);
                     }
+                    break;
                 }
-                tx.send(tile).unwrap();
+            };
+
+            // Interfaces between media don't count as bounces, and the ray
+            // keeps its origin so that lights hit beyond them are weighted
+            // correctly.
+            if let Material::Interface = hit.material {
+                medium = hit.medium_towards(ray.direction(), medium, fog);
+                t_min = hit.t + 0.001;
+                continue;
+            }
+
+            let emitted = hit.material.emitted(&ray, &hit);
+            if emitted.luminance() > 0.0 {
+                let light_pdf = lights
+                    .entity_light(hit.entity, hit.primitive)
+                    .map_or(0.0, |index| lights.pdf(index, &ray, hit.t));
+                radiance += throughput * weighted(emitted, light_pdf, scatter_pdf);
+            }
+
+            let scatter = match hit.material.scatter(&ray, &hit, rng) {
+                Some(scatter) => scatter,
+                None => break,
+            };
+            if scatter.pdf.is_some() {
+                radiance += throughput
+                    * Raytracer::<T>::sample_light(hit.point, scene, rng, |wi| {
+                        (
+                            hit.material.eval(&ray, &hit, wi),
+                            hit.material.pdf(&ray, &hit, wi),
+                            hit.medium_towards(wi, medium, fog),
+                        )
+                    });
+            }
+
+            throughput = throughput * scatter.attenuation;
+            scatter_pdf = scatter.pdf;
+            medium = hit.medium_towards(scatter.ray.direction(), medium, fog);
+            ray = scatter.ray;
+            t_min = 0.001;
+            scattered += 1;
+        }
+
+        Color::rgba(radiance.r, radiance.g, radiance.b, 1.0)
+    }
+
+    /// Light arriving directly at `point` from a randomly chosen light and
+    /// scattered towards the path, weighted for multiple importance
+    /// sampling. `scattering` gives the scattered fraction and its density
+    /// for a direction towards the light and the medium in that direction.
+    fn sample_light<'m, R, F>(point: Vector3, scene: &Scene, rng: &mut R, scattering: F) -> Color
+    where
+        R: Rng + ?Sized,
+        F: Fn(Vector3) -> (Color, f32, Option<&'m Medium>),
+    {
+        let black = Color::rgba(0.0, 0.0, 0.0, 0.0);
+        let (light, probability) = match scene.lights().choose(rng.gen()) {
+            Some(choice) => choice,
+            None => return black,
+        };
+        let sample = match light.sample(point, (rng.gen(), rng.gen())) {
+            Some(sample) if sample.pdf > 0.0 => sample,
+            _ => return black,
+        };
+
+        let (f, scatter_pdf, medium) = scattering(sample.direction);
+        if f.luminance() <= 0.0 {
+            return black;
+        }
+        let shadow = Ray::new(point, sample.direction);
+        let transmittance = scene.transmittance(&shadow, sample.distance * (1.0 - 1e-4), medium);
+        if transmittance.luminance() <= 0.0 {
+            return black;
+        }
+
+        let pdf = probability * sample.pdf;
+        let weight = if light.is_delta() {
+            1.0
+        } else {
+            power_heuristic(pdf, scatter_pdf)
+        };
+        f * transmittance * sample.radiance * (weight / pdf)
+    }
+}
+
+/// Weight of a sample from the strategy with density `f` when the other
+/// strategy has density `g` (Veach, power heuristic with exponent 2).
+fn power_heuristic(f: f32, g: f32) -> f32 {
+    let (f2, g2) = (f * f, g * g);
+    if f2 + g2 == 0.0 {
+        return 0.0;
+    }
+    f2 / (f2 + g2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::PI;
+
+    use crate::{
+        camera::Camera,
+        canvas::ImageCanvas,
+        entity::{
+            mesh::{Mesh, MeshFace},
+            sphere::Sphere,
+            triangle::Triangle,
+            Entity,
+        },
+        light::{AreaLight, DirectionalLight, EnvironmentMap, Light, PointLight, Shape, Sky},
+        material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+        medium::Medium,
+        scene::Background,
+        tile::TileOrder,
+        vector::Vector3,
+    };
+
+    fn scene() -> Arc<Scene> {
+        let camera = Camera::new(
+            Vector3::xyz(0.0, 1.0, 4.0),
+            Vector3::xyz(0.0, 0.0, 0.0),
+            Vector3::xyz(0.0, 1.0, 0.0),
+            50.0,
+            1.5,
+            0.1,
+            4.0,
+        );
+        let mut scene = Scene::new(camera);
+        scene.add(Entity::Sphere(Sphere::new(
+            Vector3::xyz(0.0, -100.5, 0.0),
+            100.0,
+            Material::Lambertian(Lambertian::new(Color::rgb(0.5, 0.5, 0.5))),
+        )));
+        scene.add(Entity::Sphere(Sphere::new(
+            Vector3::xyz(-1.0, 0.0, 0.0),
+            0.5,
+            Material::Metal(Metal::new(Color::rgb(0.8, 0.6, 0.2), 0.3)),
+        )));
+        scene.add(Entity::Sphere(Sphere::new(
+            Vector3::xyz(1.0, 0.0, 0.0),
+            0.5,
+            Material::Dielectric(Dielectric::new(1.5)),
+        )));
+        Arc::new(scene)
+    }
+
+    fn render(options: RenderOptions) -> ImageCanvas {
+        let mut canvas = ImageCanvas::new(30, 20);
+        Raytracer::new(&mut canvas, options).render(scene());
+        canvas
+    }
+
+    fn options(threads: usize, order: TileOrder, seed: u64) -> RenderOptions {
+        RenderOptions {
+            samples: 4,
+            max_scatter: 5,
+            tile_config: TileConfig::new(7, 6).with_order(order),
+            threads,
+            seed,
+            sampler: SamplerKind::default(),
+            adaptive: None,
+        }
+    }
+
+    fn bits(canvas: &ImageCanvas) -> Vec<u32> {
+        canvas
+            .pixels()
+            .iter()
+            .flat_map(|c| [c.r, c.g, c.b, c.a])
+            .map(f32::to_bits)
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_is_bit_identical() {
+        let reference = bits(&render(options(1, TileOrder::Scanline, 7)));
+        for (threads, order) in [
+            (1, TileOrder::Scanline),
+            (3, TileOrder::Scanline),
+            (4, TileOrder::Hilbert),
+            (2, TileOrder::Spiral),
+        ] {
+            assert_eq!(bits(&render(options(threads, order, 7))), reference);
+        }
+    }
+
+    #[test]
+    fn different_seeds_differ() {
+        let a = bits(&render(options(2, TileOrder::Scanline, 1)));
+        let b = bits(&render(options(2, TileOrder::Scanline, 2)));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn progressive_converges_to_full_render() {
+        let full = render(options(2, TileOrder::Scanline, 5));
+        let mut canvas = ImageCanvas::new(30, 20);
+        let samples = Raytracer::new(&mut canvas, options(3, TileOrder::Hilbert, 5))
+            .render_progressive(
+                scene(),
+                &ProgressiveOptions {
+                    pass_samples: 3,
+                    time_budget: None,
+                },
+                |_| true,
+            );
+
+        assert_eq!(samples, 4);
+        for (a, b) in full.pixels().iter().zip(canvas.pixels()) {
+            assert!((a.r - b.r).abs() < 1e-5);
+            assert!((a.g - b.g).abs() < 1e-5);
+            assert!((a.b - b.b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn progressive_stops_when_asked() {
+        let mut canvas = ImageCanvas::new(30, 20);
+        let mut passes = Vec::new();
+        let samples = Raytracer::new(&mut canvas, options(1, TileOrder::Scanline, 5))
+            .render_progressive(scene(), &ProgressiveOptions::default(), |pass| {
+                passes.push(pass.samples);
+                pass.index < 1
             });
+
+        assert_eq!(samples, 2);
+        assert_eq!(passes, [1, 2]);
+    }
+
+    fn adaptive(threads: usize, order: TileOrder) -> RenderOptions {
+        RenderOptions {
+            samples: 32,
+            adaptive: Some(AdaptiveOptions {
+                min_samples: 4,
+                threshold: 0.05,
+            }),
+            ..options(threads, order, 3)
         }
+    }
 
-        drop(tx);
+    #[test]
+    fn adaptive_sampling_spends_samples_on_noisy_pixels() {
+        let mut canvas = ImageCanvas::new(30, 20);
+        let mut raytracer = Raytracer::new(&mut canvas, adaptive(2, TileOrder::Scanline));
+        raytracer.render(scene());
+        let counts = raytracer.sample_counts();
 
-        let mut current_progress = 0;
-        for tile in rx.iter() {
-            self.canvas.draw_tile(&tile);
-            current_progress += 1;
-            println!("Tile rendered [{}/{}]", current_progress, total_tiles);
+        // The top row only sees the smooth sky.
+        assert_eq!(counts.get(15, 0), 4);
+        assert_eq!(counts.max(), 32);
+        assert!(counts.mean() > 4.0 && counts.mean() < 32.0);
+        for y in 0..20 {
+            for x in 0..30 {
+                assert!((4..=32).contains(&counts.get(x, y)));
+            }
         }
-        println!("Done: {} ms", now.elapsed().unwrap().as_millis());
+
+        let reference = bits(&canvas);
+        let mut canvas = ImageCanvas::new(30, 20);
+        Raytracer::new(&mut canvas, adaptive(3, TileOrder::Spiral)).render(scene());
+        assert_eq!(bits(&canvas), reference);
+    }
+
+    #[test]
+    fn progressive_adaptive_stops_when_converged() {
+        let mut canvas = ImageCanvas::new(30, 20);
+        let mut raytracer = Raytracer::new(
+            &mut canvas,
+            RenderOptions {
+                samples: 10_000,
+                ..adaptive(2, TileOrder::Scanline)
+            },
+        );
+        let progressive = ProgressiveOptions {
+            pass_samples: 64,
+            time_budget: None,
+        };
+        let samples = raytracer.render_progressive(scene(), &progressive, |_| true);
+        let counts = raytracer.sample_counts();
+
+        assert!(samples < 10_000);
+        assert_eq!(counts.get(15, 0), 64);
+        assert!(counts.max() > 64);
+    }
+
+    /// Plane lit by a light straight above the point the camera looks at.
+    fn lit_plane(light: Entity) -> Arc<Scene> {
+        let mut scene = plane();
+        scene.add(light);
+        Arc::new(scene)
+    }
+
+    fn plane_with_light(light: Light) -> Arc<Scene> {
+        let mut scene = plane();
+        scene.add_light(light);
+        Arc::new(scene)
+    }
+
+    fn plane() -> Scene {
+        let camera = Camera::new(
+            Vector3::xyz(0.0, 1.0, 0.0),
+            Vector3::xyz(0.0, 0.0, 0.0),
+            Vector3::xyz(0.0, 0.0, 1.0),
+            1.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+        let mut scene = Scene::new(camera);
+        scene.background = Background::Solid(Color::rgb(0.0, 0.0, 0.0));
+        let floor = Material::Lambertian(Lambertian::new(Color::rgb(0.5, 0.5, 0.5)));
+        let (a, b, c, d) = (
+            Vector3::xyz(-100.0, 0.0, -100.0),
+            Vector3::xyz(100.0, 0.0, -100.0),
+            Vector3::xyz(100.0, 0.0, 100.0),
+            Vector3::xyz(-100.0, 0.0, 100.0),
+        );
+        scene.add(Entity::Triangle(Triangle::new([a, c, b], floor.clone())));
+        scene.add(Entity::Triangle(Triangle::new([a, d, c], floor)));
+        scene
+    }
+
+    fn mean_radiance(scene: Arc<Scene>) -> f32 {
+        let mut canvas = ImageCanvas::new(8, 8);
+        let options = RenderOptions {
+            samples: 64,
+            max_scatter: 2,
+            ..options(1, TileOrder::Scanline, 9)
+        };
+        Raytracer::new(&mut canvas, options).render(scene);
+        canvas.pixels().iter().map(|c| c.g).sum::<f32>() / 64.0
+    }
+
+    #[test]
+    fn direct_light_from_sphere() {
+        let light = Material::DiffuseLight(DiffuseLight::new(Color::rgb(1.0, 1.0, 1.0), 4.0));
+        let scene = lit_plane(Entity::Sphere(Sphere::new(
+            Vector3::xyz(0.0, 2.0, 0.0),
+            0.5,
+            light,
+        )));
+        assert_eq!(scene.lights().lights().len(), 1);
+
+        // Irradiance pi L sin^2, reflected with albedo / pi.
+        let expected = 0.5 * 4.0 * (0.5f32 / 2.0).powi(2);
+        let radiance = mean_radiance(scene);
+        assert!(
+            (radiance - expected).abs() < 0.02 * expected,
+            "{} vs {}",
+            radiance,
+            expected
+        );
     }
 
-    fn ray_color(ray: &Ray, scene: &Scene, scatters_count: u32) -> Color {
-        if scatters_count == 0 {
-            return Color::new();
+    #[test]
+    fn direct_light_from_triangles() {
+        // A downward facing square covering the whole upper hemisphere
+        // reflects the albedo times its radiance.
+        let light = Material::DiffuseLight(DiffuseLight::new(Color::rgb(1.0, 1.0, 1.0), 2.0));
+        let (a, b, c) = (
+            Vector3::xyz(-1000.0, 2.0, -1000.0),
+            Vector3::xyz(1000.0, 2.0, -1000.0),
+            Vector3::xyz(0.0, 2.0, 2000.0),
+        );
+        let scene = lit_plane(Entity::Triangle(Triangle::new([a, b, c], light)));
+
+        let radiance = mean_radiance(scene);
+        assert!((radiance - 1.0).abs() < 0.02, "{}", radiance);
+    }
+
+    #[test]
+    fn direct_light_from_mesh() {
+        // A small square light made of two triangles, whose lights are found
+        // through the entity and the face that was hit, lights the floor
+        // like the same rectangle added as a light.
+        let light = Material::DiffuseLight(DiffuseLight::new(Color::rgb(1.0, 1.0, 1.0), 2.0));
+        let positions = vec![
+            Vector3::xyz(-0.25, 2.0, -0.25),
+            Vector3::xyz(0.25, 2.0, -0.25),
+            Vector3::xyz(0.25, 2.0, 0.25),
+            Vector3::xyz(-0.25, 2.0, 0.25),
+        ];
+        let faces = vec![MeshFace::new([0, 1, 2]), MeshFace::new([0, 2, 3])];
+        let mesh = Mesh::new(positions, Vec::new(), Vec::new(), faces, light);
+        let scene = lit_plane(Entity::Mesh(mesh));
+        assert_eq!(scene.lights().entity_light(2, 1), Some(1));
+
+        let rectangle = plane_with_light(Light::Area(AreaLight::new(
+            Shape::Rectangle {
+                corner: Vector3::xyz(-0.25, 2.0, -0.25),
+                edge_u: Vector3::xyz(0.5, 0.0, 0.0),
+                edge_v: Vector3::xyz(0.0, 0.0, 0.5),
+            },
+            Color::rgb(2.0, 2.0, 2.0),
+        )));
+        let expected = mean_radiance(rectangle);
+        let radiance = mean_radiance(scene);
+        assert!(
+            (radiance - expected).abs() < 0.02 * expected,
+            "{} vs {}",
+            radiance,
+            expected
+        );
+    }
+
+    #[test]
+    fn direct_light_from_point() {
+        let scene = plane_with_light(Light::Point(PointLight::new(
+            Vector3::xyz(0.0, 2.0, 0.0),
+            Color::rgb(4.0, 4.0, 4.0),
+        )));
+
+        // Irradiance I / d^2, reflected with albedo / pi.
+        let expected = 0.5 / PI;
+        let radiance = mean_radiance(scene);
+        assert!((radiance - expected).abs() < 1e-3, "{}", radiance);
+    }
+
+    #[test]
+    fn direct_light_from_sun() {
+        for diameter in [0.0, 0.53].iter() {
+            let scene = plane_with_light(Light::Directional(DirectionalLight::new(
+                Vector3::xyz(0.0, -1.0, 0.0),
+                Color::rgb(2.0, 2.0, 2.0),
+                *diameter,
+            )));
+            let expected = 0.5 * 2.0 / PI;
+            let radiance = mean_radiance(scene);
+            assert!((radiance - expected).abs() < 1e-3, "{}", radiance);
         }
+    }
+
+    #[test]
+    fn direct_light_from_rectangle() {
+        // Same as the large triangle, but the light isn't an entity.
+        let scene = plane_with_light(Light::Area(AreaLight::new(
+            Shape::Rectangle {
+                corner: Vector3::xyz(-1000.0, 2.0, -1000.0),
+                edge_u: Vector3::xyz(2000.0, 0.0, 0.0),
+                edge_v: Vector3::xyz(0.0, 0.0, 2000.0),
+            },
+            Color::rgb(2.0, 2.0, 2.0),
+        )));
+        assert!(scene.entities().len() == 2);
+
+        let radiance = mean_radiance(scene);
+        assert!((radiance - 1.0).abs() < 0.02, "{}", radiance);
+    }
 
-        if let Some(hit) = scene.hit(ray, 0.001, f32::INFINITY) {
-            if let Some(scatter) = hit.material.scatter(ray, &hit) {
-                return scatter.attenuation * Raytracer::<T>::ray_color(&scatter.ray, scene, scatters_count - 1);
+    #[test]
+    fn direct_light_from_environment() {
+        // A uniform sky reflects the albedo times its radiance, with a sun
+        // pixel below the horizon that must not leak through the floor.
+        let mut image = ImageCanvas::new(16, 8);
+        for x in 0..16 {
+            for y in 0..8 {
+                image.draw_point(&Color::rgb(0.5, 0.5, 0.5), x, y);
             }
-            return Color::new();
         }
+        image.draw_point(&Color::rgb(1e4, 1e4, 1e4), 4, 6);
+        let mut scene = plane();
+        scene.background = Background::Environment(Arc::new(EnvironmentMap::new(&image, 0.0, 2.0)));
+
+        let radiance = mean_radiance(Arc::new(scene));
+        assert!((radiance - 0.5).abs() < 0.02, "{}", radiance);
+    }
+
+    #[test]
+    fn direct_light_from_sky() {
+        let sky = Arc::new(Sky::new(60.0, 45.0, 3.0, 1.0));
+
+        // Irradiance from the sky on the floor, by midpoint integration over
+        // the upper hemisphere, and from the sun.
+        let n = 256;
+        let mut irradiance = 0.0;
+        for i in 0..n {
+            let theta = (i as f32 + 0.5) / n as f32 * PI / 2.0;
+            for j in 0..n {
+                let phi = (j as f32 + 0.5) / n as f32 * 2.0 * PI;
+                let direction = Vector3::xyz(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                let radiance = sky.radiance(direction).g;
+                irradiance += radiance * theta.cos() * theta.sin();
+            }
+        }
+        irradiance *= (PI / 2.0) * (2.0 * PI) / (n * n) as f32;
+        let sun = sky.sun().sample((0.5, 0.5)).unwrap();
+        irradiance += sun.radiance.g / sun.pdf * 60f32.to_radians().sin();
+
+        let mut scene = plane();
+        scene.background = Background::Sky(sky);
+        assert_eq!(scene.lights().lights().len(), 2);
+        let expected = 0.5 / PI * irradiance;
+        let radiance = mean_radiance(Arc::new(scene));
+        assert!(
+            (radiance - expected).abs() < 0.02 * expected,
+            "{} vs {}",
+            radiance,
+            expected
+        );
+    }
+
+    /// A sphere of radius 1 at the origin that only bounds `medium`.
+    fn cloud(medium: Medium) -> Entity {
+        Entity::Sphere(
+            Sphere::new(Vector3::xyz(0.0, 0.0, 0.0), 1.0, Material::Interface)
+                .with_interior(medium),
+        )
+    }
+
+    fn mean_ray_color(scene: &Scene, ray: &Ray, max_scatter: u32) -> Color {
+        let n = 20_000;
+        let mut rng = util::sample_rng(3, 0, 0, 0);
+        let mut sum = Color::rgba(0.0, 0.0, 0.0, 0.0);
+        for _ in 0..n {
+            sum += Raytracer::<ImageCanvas>::ray_color(ray, scene, max_scatter, &mut rng);
+        }
+        sum / n as f32
+    }
+
+    #[test]
+    fn absorbing_medium_follows_beer_lambert() {
+        let mut scene = Scene::new(plane().camera);
+        scene.background = Background::Solid(Color::rgb(1.0, 1.0, 1.0));
+        scene.add(cloud(Medium::new(
+            Color::rgb(0.25, 0.5, 1.0),
+            Color::rgb(0.0, 0.0, 0.0),
+            0.0,
+        )));
+
+        let ray = Ray::new(Vector3::xyz(0.0, 0.0, -5.0), Vector3::xyz(0.0, 0.0, 1.0));
+        let color = mean_ray_color(&scene, &ray, 5);
+        for (value, sigma) in [(color.r, 0.25), (color.g, 0.5), (color.b, 1.0)] {
+            let expected = (-2.0 * sigma as f32).exp();
+            assert!((value - expected).abs() < 0.02, "{} {}", value, expected);
+        }
+    }
+
+    #[test]
+    fn scattering_medium_conserves_energy() {
+        // A medium that doesn't absorb looks exactly like the uniform sky
+        // around it, whether the light is found by sampling the sky or by
+        // following the phase function.
+        let mut image = ImageCanvas::new(16, 8);
+        for x in 0..16 {
+            for y in 0..8 {
+                image.draw_point(&Color::rgb(0.5, 0.5, 0.5), x, y);
+            }
+        }
+        let mut scene = Scene::new(plane().camera);
+        scene.background = Background::Environment(Arc::new(EnvironmentMap::new(&image, 0.0, 2.0)));
+        scene.add(cloud(Medium::new(
+            Color::rgb(0.0, 0.0, 0.0),
+            Color::rgb(2.0, 2.0, 2.0),
+            0.6,
+        )));
+
+        let ray = Ray::new(Vector3::xyz(0.2, 0.1, -5.0), Vector3::xyz(0.0, 0.0, 1.0));
+        let color = mean_ray_color(&scene, &ray, 200);
+        assert!((color.g - 1.0).abs() < 0.03, "{}", color.g);
+    }
+
+    #[test]
+    fn shadow_rays_pass_through_media() {
+        let mut scene = Scene::new(plane().camera);
+        scene.fog = Some(Medium::new(
+            Color::rgb(0.1, 0.1, 0.1),
+            Color::rgb(0.0, 0.0, 0.0),
+            0.0,
+        ));
+        scene.add(cloud(Medium::new(
+            Color::rgb(0.5, 1.0, 0.5),
+            Color::rgb(0.5, 0.0, 0.0),
+            0.0,
+        )));
+
+        let ray = Ray::new(Vector3::xyz(0.0, 0.0, -5.0), Vector3::xyz(0.0, 0.0, 1.0));
+        let fog = scene.fog.as_ref();
+        let transmittance = scene.transmittance(&ray, 10.0, fog);
+        let expected = (-0.1f32 * 8.0 - 2.0).exp();
+        assert!((transmittance.g - expected).abs() < 1e-4);
+        assert!((transmittance.r - expected).abs() < 1e-4);
+        assert!(transmittance.b > transmittance.g);
 
-        let dir = ray.direction().normalized();
-        let t = 0.5 * (dir.y + 1.0);
-        let v = (1.0 - t) * Vector3::xyz(1.0, 1.0, 1.0) + t * Vector3::xyz(0.5, 0.7, 1.0);
-        Color::rgb(v.x, v.y, v.z)
+        let mut scene = Scene::new(plane().camera);
+        scene.add(Entity::Sphere(Sphere::new(
+            Vector3::xyz(0.0, 0.0, 0.0),
+            1.0,
+            Material::Lambertian(Lambertian::new(Color::rgb(0.5, 0.5, 0.5))),
+        )));
+        assert_eq!(scene.transmittance(&ray, 10.0, None).g, 0.0);
     }
 }
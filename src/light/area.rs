@@ -0,0 +1,399 @@
+use std::f32::consts::PI;
+
+use crate::{color::Color, entity::triangle, ray::Ray, sampler::concentric_disk, vector::Vector3};
+
+use super::{cone_pdf, LightSample};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Shape {
+    /// Emits from the side the counter-clockwise winding faces.
+    Triangle([Vector3; 3]),
+    /// Parallelogram spanned by two edges from a corner, emits towards
+    /// `edge_u ^ edge_v`.
+    Rectangle {
+        corner: Vector3,
+        edge_u: Vector3,
+        edge_v: Vector3,
+    },
+    /// Emits towards `normal`.
+    Disk {
+        center: Vector3,
+        normal: Vector3,
+        radius: f32,
+    },
+    /// Emits outwards.
+    Sphere { center: Vector3, radius: f32 },
+}
+
+impl Shape {
+    pub fn area(&self) -> f32 {
+        match *self {
+            Shape::Triangle([p0, p1, p2]) => ((p1 - p0) ^ (p2 - p0)).length() / 2.0,
+            Shape::Rectangle { edge_u, edge_v, .. } => (edge_u ^ edge_v).length(),
+            Shape::Disk { radius, .. } => PI * radius * radius,
+            Shape::Sphere { radius, .. } => 4.0 * PI * radius * radius,
+        }
+    }
+
+    /// Uniformly distributed point on the surface with the normal of its
+    /// emitting side.
+    fn sample_area(&self, (u, v): (f32, f32)) -> (Vector3, Vector3) {
+        match *self {
+            Shape::Triangle([p0, p1, p2]) => {
+                // Shirley and Chiu.
+                let s = u.sqrt();
+                let (b0, b1) = (1.0 - s, v * s);
+                let point = b0 * p0 + b1 * p1 + (1.0 - b0 - b1) * p2;
+                (point, ((p1 - p0) ^ (p2 - p0)).normalized())
+            }
+            Shape::Rectangle {
+                corner,
+                edge_u,
+                edge_v,
+            } => (
+                corner + u * edge_u + v * edge_v,
+                (edge_u ^ edge_v).normalized(),
+            ),
+            Shape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let normal = normal.normalized();
+                let (a, b) = normal.basis();
+                let (x, y) = concentric_disk((u, v));
+                (center + radius * (x * a + y * b), normal)
+            }
+            Shape::Sphere { center, radius } => {
+                let z = 1.0 - 2.0 * u;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let phi = 2.0 * PI * v;
+                let normal = Vector3::xyz(r * phi.cos(), r * phi.sin(), z);
+                (center + radius * normal, normal)
+            }
+        }
+    }
+
+    /// Closest intersection with a unit direction ray inside the interval,
+    /// together with the normal of the emitting side.
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, Vector3)> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let plane = |point: Vector3, normal: Vector3| {
+            let denominator = normal * direction;
+            if denominator == 0.0 {
+                return None;
+            }
+            let t = (normal * (point - origin)) / denominator;
+            (t > t_min && t < t_max).then_some(t)
+        };
+
+        match *self {
+            Shape::Triangle([p0, p1, p2]) => {
+                let (t, _, _) = triangle::intersect(p0, p1, p2, ray, t_min, t_max)?;
+                Some((t, ((p1 - p0) ^ (p2 - p0)).normalized()))
+            }
+            Shape::Rectangle {
+                corner,
+                edge_u,
+                edge_v,
+            } => {
+                let cross = edge_u ^ edge_v;
+                let normal = cross.normalized();
+                let t = plane(corner, normal)?;
+                // Coordinates of the hit along the edges.
+                let offset = ray.at(t) - corner;
+                let u = ((offset ^ edge_v) * cross) / cross.squared_length();
+                let v = ((edge_u ^ offset) * cross) / cross.squared_length();
+                ((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v)).then_some((t, normal))
+            }
+            Shape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let normal = normal.normalized();
+                let t = plane(center, normal)?;
+                ((ray.at(t) - center).squared_length() <= radius * radius).then_some((t, normal))
+            }
+            Shape::Sphere { center, radius } => {
+                let oc = origin - center;
+                let half_b = oc * direction;
+                let c = oc.squared_length() - radius * radius;
+                let discriminant = half_b * half_b - c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let root = discriminant.sqrt();
+                [-half_b - root, -half_b + root]
+                    .iter()
+                    .copied()
+                    .find(|t| *t > t_min && *t < t_max)
+                    .map(|t| (t, (ray.at(t) - center) / radius))
+            }
+        }
+    }
+}
+
+/// Surface emitting the same radiance in every direction from its front.
+#[derive(Debug, Clone, Copy)]
+pub struct AreaLight {
+    shape: Shape,
+    radiance: Color,
+}
+
+impl AreaLight {
+    pub fn new(shape: Shape, radiance: Color) -> Self {
+        Self { shape, radiance }
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    pub fn radiance(&self) -> Color {
+        self.radiance
+    }
+
+    pub fn area(&self) -> f32 {
+        self.shape.area()
+    }
+
+    pub fn power(&self) -> f32 {
+        PI * self.area() * self.radiance.luminance()
+    }
+
+    pub fn sample(&self, point: Vector3, u: (f32, f32)) -> Option<LightSample> {
+        if let Shape::Sphere { center, radius } = self.shape {
+            // Uniform direction inside the cone subtended by the sphere.
+            let to_center = center - point;
+            let d2 = to_center.squared_length();
+            if d2 <= radius * radius {
+                return None;
+            }
+            let d = d2.sqrt();
+            let w = to_center / d;
+            let cos_max = (1.0 - radius * radius / d2).max(0.0).sqrt();
+            let direction = sample_cone(w, cos_max, u);
+            let cos_theta = direction * w;
+            let distance = d * cos_theta
+                - (radius * radius - d2 * (1.0 - cos_theta * cos_theta))
+                    .max(0.0)
+                    .sqrt();
+            return Some(LightSample {
+                direction,
+                distance,
+                radiance: self.radiance,
+                pdf: cone_pdf(cos_max),
+            });
+        }
+
+        let area = self.area();
+        let (target, normal) = self.shape.sample_area(u);
+        let to_light = target - point;
+        let distance = to_light.length();
+        if area == 0.0 || distance == 0.0 {
+            return None;
+        }
+        let direction = to_light / distance;
+        let cos_light = -(normal * direction);
+        if cos_light <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.radiance,
+            pdf: distance * distance / (cos_light * area),
+        })
+    }
+
+    pub fn pdf(&self, ray: &Ray, t: f32) -> f32 {
+        if !t.is_finite() {
+            return 0.0;
+        }
+        let length = ray.direction().length();
+        let unit = Ray::new(ray.origin(), ray.direction() / length);
+        // The light only counts when it is the surface that was hit.
+        let (distance, normal) = match self.shape.intersect(&unit, 0.0, f32::INFINITY) {
+            Some((hit, normal)) if (hit - t * length).abs() <= 1e-3 * (t * length).max(1.0) => {
+                (hit, normal)
+            }
+            _ => return 0.0,
+        };
+        let cos_light = -(normal * unit.direction());
+        if cos_light <= 0.0 {
+            return 0.0;
+        }
+
+        match self.shape {
+            Shape::Sphere { center, radius } => {
+                let d2 = (center - ray.origin()).squared_length();
+                if d2 <= radius * radius {
+                    return 0.0;
+                }
+                cone_pdf((1.0 - radius * radius / d2).max(0.0).sqrt())
+            }
+            _ => distance * distance / (cos_light * self.area()),
+        }
+    }
+
+    /// Closest intersection of the light surface with `ray` inside the
+    /// interval and the radiance it emits back along the ray.
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, Color)> {
+        let length = ray.direction().length();
+        let unit = Ray::new(ray.origin(), ray.direction() / length);
+        let (t, normal) = self
+            .shape
+            .intersect(&unit, t_min * length, t_max * length)?;
+        let radiance = if normal * unit.direction() < 0.0 {
+            self.radiance
+        } else {
+            Color::rgba(0.0, 0.0, 0.0, 0.0)
+        };
+        Some((t / length, radiance))
+    }
+}
+
+/// Uniformly distributed unit vector within `cos_max` of the axis `w`.
+pub(super) fn sample_cone(w: Vector3, cos_max: f32, (u, v): (f32, f32)) -> Vector3 {
+    let cos_theta = 1.0 - u * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    let (a, b) = w.basis();
+    (sin_theta * phi.cos()) * a + (sin_theta * phi.sin()) * b + cos_theta * w
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn quad_light() -> AreaLight {
+        // Facing down, towards the origin.
+        AreaLight::new(
+            Shape::Triangle([
+                Vector3::xyz(-1.0, 2.0, -1.0),
+                Vector3::xyz(1.0, 2.0, -1.0),
+                Vector3::xyz(0.0, 2.0, 1.0),
+            ]),
+            Color::rgb(2.0, 2.0, 2.0),
+        )
+    }
+
+    fn check_pdf(light: &AreaLight, point: Vector3) {
+        for i in 0..8 {
+            for j in 0..8 {
+                let u = ((i as f32 + 0.5) / 8.0, (j as f32 + 0.5) / 8.0);
+                let sample = light.sample(point, u).unwrap();
+                assert!((sample.direction.length() - 1.0).abs() < 1e-4);
+                let ray = Ray::new(point, sample.direction * 3.0);
+                let pdf = light.pdf(&ray, sample.distance / 3.0);
+                assert!(
+                    (pdf - sample.pdf).abs() < 1e-3 * sample.pdf,
+                    "{} vs {}",
+                    pdf,
+                    sample.pdf
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn triangle_sample_and_pdf_agree() {
+        let light = quad_light();
+        assert_eq!(light.area(), 2.0);
+        check_pdf(&light, Vector3::xyz(0.1, 0.0, 0.2));
+
+        // Seen from above only the back is visible.
+        assert!(light
+            .sample(Vector3::xyz(0.0, 3.0, 0.0), (0.5, 0.5))
+            .is_none());
+        let ray = Ray::new(Vector3::xyz(0.0, 3.0, 0.0), Vector3::xyz(0.0, -1.0, 0.0));
+        assert_eq!(light.pdf(&ray, 1.0), 0.0);
+    }
+
+    #[test]
+    fn sphere_sample_and_pdf_agree() {
+        let light = AreaLight::new(
+            Shape::Sphere {
+                center: Vector3::xyz(0.0, 0.0, -4.0),
+                radius: 1.0,
+            },
+            Color::rgb(1.0, 1.0, 1.0),
+        );
+        check_pdf(&light, Vector3::xyz(0.5, 0.0, 0.0));
+
+        // Inside the sphere it can't be sampled.
+        assert!(light
+            .sample(Vector3::xyz(0.0, 0.0, -4.0), (0.5, 0.5))
+            .is_none());
+    }
+
+    #[test]
+    fn rectangle_and_disk_sample_and_pdf_agree() {
+        let rectangle = AreaLight::new(
+            Shape::Rectangle {
+                corner: Vector3::xyz(-1.0, 2.0, -1.0),
+                edge_u: Vector3::xyz(3.0, 0.0, 0.0),
+                edge_v: Vector3::xyz(0.0, 0.0, 2.0),
+            },
+            Color::rgb(1.0, 1.0, 1.0),
+        );
+        assert_eq!(rectangle.area(), 6.0);
+        check_pdf(&rectangle, Vector3::xyz(0.3, 0.0, -0.2));
+
+        let disk = AreaLight::new(
+            Shape::Disk {
+                center: Vector3::xyz(0.0, 2.0, 0.0),
+                normal: Vector3::xyz(0.0, -1.0, 0.0),
+                radius: 0.5,
+            },
+            Color::rgb(1.0, 1.0, 1.0),
+        );
+        check_pdf(&disk, Vector3::xyz(0.3, 0.0, -0.2));
+        assert!(disk
+            .sample(Vector3::xyz(0.0, 3.0, 0.0), (0.5, 0.5))
+            .is_none());
+    }
+
+    #[test]
+    fn hits_the_emitting_side() {
+        let light = quad_light();
+        let down = Ray::new(Vector3::xyz(0.0, 4.0, 0.0), Vector3::xyz(0.0, -2.0, 0.0));
+        let (t, radiance) = light.hit(&down, 0.0, f32::INFINITY).unwrap();
+        assert!((t - 1.0).abs() < 1e-6);
+        assert_eq!(radiance.r, 0.0);
+
+        let up = Ray::new(Vector3::xyz(0.0, 0.0, 0.0), Vector3::xyz(0.0, 1.0, 0.0));
+        assert_eq!(light.hit(&up, 0.0, f32::INFINITY).unwrap().1.r, 2.0);
+        assert!(light.hit(&up, 0.0, 1.5).is_none());
+    }
+
+    #[test]
+    fn triangle_pdf_integrates_to_one() {
+        // Summing 1 / pdf over stratified samples estimates the solid angle,
+        // which must match the solid angle from the pdf of the hit itself.
+        let light = quad_light();
+        let point = Vector3::xyz(0.0, 0.0, 0.0);
+        let n = 64;
+        let mut solid_angle = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let u = ((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                solid_angle += 1.0 / light.sample(point, u).unwrap().pdf;
+            }
+        }
+        solid_angle /= (n * n) as f32;
+
+        // Solid angle of the triangle (Van Oosterom and Strackee).
+        let [a, b, c] = match light.shape() {
+            Shape::Triangle(vertices) => *vertices,
+            _ => unreachable!(),
+        };
+        let (la, lb, lc) = (a.length(), b.length(), c.length());
+        let numerator = a * (b ^ c);
+        let denominator = la * lb * lc + (a * b) * lc + (a * c) * lb + (b * c) * la;
+        let expected = 2.0 * numerator.abs().atan2(denominator);
+        assert!((solid_angle - expected).abs() < 1e-3 * expected);
+    }
+}
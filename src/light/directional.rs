@@ -0,0 +1,123 @@
+use std::f32::consts::PI;
+
+use crate::{color::Color, ray::Ray, util::deg_to_rad, vector::Vector3};
+
+use super::{area::sample_cone, cone_pdf, LightSample};
+
+/// Light arriving from infinitely far away, like the sun. With an angular
+/// diameter of zero every ray arrives from the same direction, otherwise
+/// from a small disk in the sky that rays can also escape into.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+    /// Unit vector towards the light.
+    to_light: Vector3,
+    irradiance: Color,
+    cos_max: f32,
+}
+
+impl DirectionalLight {
+    /// `direction` is the direction the light travels in, `irradiance` the
+    /// light arriving on a surface facing it and `angular_diameter` the
+    /// apparent size of the source in degrees.
+    pub fn new(direction: Vector3, irradiance: Color, angular_diameter: f32) -> Self {
+        Self {
+            to_light: -direction.normalized(),
+            irradiance,
+            cos_max: deg_to_rad(angular_diameter.max(0.0) / 2.0).cos(),
+        }
+    }
+
+    pub fn direction(&self) -> Vector3 {
+        -self.to_light
+    }
+
+    pub fn is_delta(&self) -> bool {
+        self.cos_max >= 1.0
+    }
+
+    /// Radiance of the disk, spreading the irradiance over its solid angle.
+    fn radiance(&self) -> Color {
+        cone_pdf(self.cos_max) * self.irradiance
+    }
+
+    /// Power falling on a disk as large as the scene.
+    pub fn power(&self, scene_radius: f32) -> f32 {
+        PI * scene_radius * scene_radius * self.irradiance.luminance()
+    }
+
+    pub fn sample(&self, u: (f32, f32)) -> Option<LightSample> {
+        if self.is_delta() {
+            return Some(LightSample {
+                direction: self.to_light,
+                distance: f32::INFINITY,
+                radiance: self.irradiance,
+                pdf: 1.0,
+            });
+        }
+        Some(LightSample {
+            direction: sample_cone(self.to_light, self.cos_max, u),
+            distance: f32::INFINITY,
+            radiance: self.radiance(),
+            pdf: cone_pdf(self.cos_max),
+        })
+    }
+
+    fn contains(&self, ray: &Ray) -> bool {
+        !self.is_delta() && ray.direction().normalized() * self.to_light >= self.cos_max
+    }
+
+    pub fn pdf(&self, ray: &Ray, t: f32) -> f32 {
+        if t.is_finite() || !self.contains(ray) {
+            return 0.0;
+        }
+        cone_pdf(self.cos_max)
+    }
+
+    /// Radiance arriving along a ray that left the scene.
+    pub fn escaped(&self, ray: &Ray) -> Color {
+        if self.contains(ray) {
+            self.radiance()
+        } else {
+            Color::rgba(0.0, 0.0, 0.0, 0.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sun_disk() {
+        let sun = DirectionalLight::new(
+            Vector3::xyz(0.0, -1.0, 0.0),
+            Color::rgb(2.0, 2.0, 2.0),
+            10.0,
+        );
+        assert!(!sun.is_delta());
+
+        let sample = sun.sample((0.3, 0.7)).unwrap();
+        assert!(sample.direction.y >= deg_to_rad(5.0).cos() - 1e-6);
+        let ray = Ray::new(Vector3::xyz(0.0, 0.0, 0.0), 2.0 * sample.direction);
+        assert_eq!(sun.pdf(&ray, f32::INFINITY), sample.pdf);
+        assert_eq!(sun.pdf(&ray, 100.0), 0.0);
+
+        // Integrating the radiance over the disk gives back the irradiance.
+        let irradiance = sun.escaped(&ray).r / sample.pdf;
+        assert!((irradiance - 2.0).abs() < 1e-4);
+        let away = Ray::new(Vector3::xyz(0.0, 0.0, 0.0), Vector3::xyz(1.0, 0.0, 0.0));
+        assert_eq!(sun.escaped(&away).r, 0.0);
+    }
+
+    #[test]
+    fn delta_sun() {
+        let sun =
+            DirectionalLight::new(Vector3::xyz(1.0, -1.0, 0.0), Color::rgb(1.0, 1.0, 1.0), 0.0);
+        assert!(sun.is_delta());
+        let sample = sun.sample((0.5, 0.5)).unwrap();
+        assert!((sample.direction.x + 0.5f32.sqrt()).abs() < 1e-6);
+        assert_eq!(sample.distance, f32::INFINITY);
+        let ray = Ray::new(Vector3::xyz(0.0, 0.0, 0.0), sample.direction);
+        assert_eq!(sun.escaped(&ray).r, 0.0);
+    }
+}
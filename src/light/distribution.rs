@@ -0,0 +1,160 @@
+//! Piecewise constant distributions for importance sampling tabulated
+//! functions (Pharr et al., Physically Based Rendering, 13.6).
+
+/// Distribution over [0, 1) proportional to a step function with equally
+/// wide steps.
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    function: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    /// Negative values are treated as zero. When the function is zero
+    /// everywhere the distribution is uniform.
+    pub fn new(function: Vec<f32>) -> Self {
+        let n = function.len() as f32;
+        let function = function.into_iter().map(|f| f.max(0.0)).collect::<Vec<_>>();
+        let mut cdf = Vec::with_capacity(function.len() + 1);
+        cdf.push(0.0);
+        for f in function.iter() {
+            cdf.push(cdf[cdf.len() - 1] + f / n);
+        }
+        let integral = cdf[cdf.len() - 1];
+        if integral > 0.0 {
+            cdf.iter_mut().for_each(|c| *c /= integral);
+        } else {
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, c)| *c = i as f32 / n);
+        }
+        Self {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.function.len()
+    }
+
+    /// Integral of the function over [0, 1).
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    /// Maps `u` to a value in [0, 1) and returns it with its density and
+    /// the index of the step it falls in.
+    pub fn sample(&self, u: f32) -> (f32, f32, usize) {
+        let index = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .saturating_sub(1)
+            .min(self.len() - 1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 {
+            ((u - self.cdf[index]) / width).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let x = ((index as f32 + offset) / self.len() as f32).min(1.0 - f32::EPSILON);
+        (x, self.pdf(index), index)
+    }
+
+    /// Density inside the step at `index`.
+    pub fn pdf(&self, index: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.function[index] / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    fn index(&self, x: f32) -> usize {
+        ((x * self.len() as f32) as usize).min(self.len() - 1)
+    }
+}
+
+/// Distribution over [0, 1)² proportional to a function tabulated row by
+/// row, sampling a row first and then a column within it.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(function: &[f32], width: usize) -> Self {
+        let rows = function
+            .chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect::<Vec<_>>();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+        Self { rows, marginal }
+    }
+
+    /// Integral of the function over [0, 1)².
+    pub fn integral(&self) -> f32 {
+        self.marginal.integral()
+    }
+
+    /// Maps `u` to a point `(x, y)` and returns it with its density.
+    pub fn sample(&self, (u, v): (f32, f32)) -> ((f32, f32), f32) {
+        let (y, pdf_y, row) = self.marginal.sample(v);
+        let (x, pdf_x, _) = self.rows[row].sample(u);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, (x, y): (f32, f32)) -> f32 {
+        let row = self.marginal.index(y);
+        self.rows[row].pdf(self.rows[row].index(x)) * self.marginal.pdf(row)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn samples_proportionally() {
+        let distribution = Distribution1D::new(vec![1.0, 0.0, 3.0, 0.0]);
+        assert_eq!(distribution.integral(), 1.0);
+
+        let (x, pdf, index) = distribution.sample(0.1);
+        assert_eq!(index, 0);
+        assert!((x - 0.1).abs() < 1e-6);
+        assert_eq!(pdf, 1.0);
+        let (x, pdf, index) = distribution.sample(0.625);
+        assert_eq!(index, 2);
+        assert!((x - 0.625).abs() < 1e-6);
+        assert_eq!(pdf, 3.0);
+        assert!(distribution.sample(0.999_999).0 < 0.75);
+    }
+
+    #[test]
+    fn zero_function_is_uniform() {
+        let distribution = Distribution1D::new(vec![0.0; 4]);
+        let (x, pdf, index) = distribution.sample(0.6);
+        assert_eq!(index, 2);
+        assert!((x - 0.6).abs() < 1e-6);
+        assert_eq!(pdf, 1.0);
+    }
+
+    #[test]
+    fn sample_and_pdf_agree_2d() {
+        let function = [0.0, 1.0, 2.0, 3.0, 0.5, 0.5];
+        let distribution = Distribution2D::new(&function, 2);
+        assert!((distribution.integral() - 7.0 / 6.0).abs() < 1e-6);
+        for u in [0.1, 0.4, 0.7, 0.95].iter() {
+            for v in [0.05, 0.5, 0.9].iter() {
+                let (point, pdf) = distribution.sample((*u, *v));
+                assert!((distribution.pdf(point) - pdf).abs() < 1e-5);
+                let (x, y) = point;
+                let value = function[(y * 3.0) as usize * 2 + (x * 2.0) as usize];
+                assert!((pdf - value / distribution.integral()).abs() < 1e-5);
+            }
+        }
+    }
+}
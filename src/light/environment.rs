@@ -0,0 +1,220 @@
+use std::{f32::consts::PI, fmt};
+
+use crate::{
+    canvas::{Canvas, ImageCanvas},
+    color::Color,
+    util::deg_to_rad,
+    vector::Vector3,
+};
+
+use super::{distribution::Distribution2D, LightSample};
+
+/// Equirectangular image of the radiance arriving from every direction.
+/// The top row is straight up and the center of the image looks down the
+/// negative z axis before the rotation around the y axis is applied.
+///
+/// Directions are sampled proportionally to the luminance of the pixels, so
+/// small bright regions like the sun are found by the light samples instead
+/// of by chance.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    rotation: f32,
+    intensity: f32,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// `rotation` is in degrees, counter-clockwise around the y axis seen
+    /// from above, and `intensity` scales the pixel values.
+    pub fn new(image: &ImageCanvas, rotation: f32, intensity: f32) -> Self {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+        // The rows near the poles cover a smaller solid angle.
+        let function = image
+            .pixels()
+            .iter()
+            .enumerate()
+            .map(|(index, pixel)| {
+                let theta = PI * ((index / width) as f32 + 0.5) / height as f32;
+                pixel.luminance() * theta.sin()
+            })
+            .collect::<Vec<_>>();
+        Self {
+            width,
+            height,
+            pixels: image.pixels().to_vec(),
+            rotation: deg_to_rad(rotation),
+            intensity,
+            distribution: Distribution2D::new(&function, width),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Radiance arriving from `direction`, which doesn't need to be a unit
+    /// vector.
+    pub fn radiance(&self, direction: Vector3) -> Color {
+        let (u, v) = self.image_coordinates(direction.normalized());
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.intensity * self.pixels[y * self.width + x]
+    }
+
+    pub fn sample(&self, u: (f32, f32)) -> Option<LightSample> {
+        let ((x, y), pdf) = self.distribution.sample(u);
+        let direction = self.direction(x, y);
+        let pdf = image_to_solid_angle(pdf, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance: f32::INFINITY,
+            radiance: self.radiance(direction),
+            pdf,
+        })
+    }
+
+    /// Solid angle density of sampling `direction`.
+    pub fn pdf(&self, direction: Vector3) -> f32 {
+        let direction = direction.normalized();
+        let pdf = self.distribution.pdf(self.image_coordinates(direction));
+        image_to_solid_angle(pdf, direction)
+    }
+
+    /// Power falling on a disk as large as the scene from the average
+    /// radiance over the sphere.
+    pub fn power(&self, scene_radius: f32) -> f32 {
+        // The integral over the image is taken in (u, v) with the sin(theta)
+        // weight, the sphere covers 2 pi^2 of it and has a solid angle of
+        // 4 pi.
+        let average = self.distribution.integral() * PI / 2.0;
+        PI * scene_radius * scene_radius * average * self.intensity
+    }
+
+    /// Image coordinates in [0, 1)² of a unit direction.
+    fn image_coordinates(&self, direction: Vector3) -> (f32, f32) {
+        let (sin, cos) = self.rotation.sin_cos();
+        // Rotate back into the frame of the image.
+        let x = cos * direction.x - sin * direction.z;
+        let z = sin * direction.x + cos * direction.z;
+        let u = 0.5 + x.atan2(-z) / (2.0 * PI);
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        (u.rem_euclid(1.0), v)
+    }
+
+    fn direction(&self, u: f32, v: f32) -> Vector3 {
+        let phi = 2.0 * PI * (u - 0.5);
+        let theta = PI * v;
+        let (x, z) = (theta.sin() * phi.sin(), -theta.sin() * phi.cos());
+        let (sin, cos) = self.rotation.sin_cos();
+        Vector3::xyz(cos * x + sin * z, theta.cos(), -sin * x + cos * z)
+    }
+}
+
+/// Converts a density over the image to one over directions, the image
+/// covers 2 pi by pi radians and a pixel shrinks by sin(theta) towards the
+/// poles.
+fn image_to_solid_angle(pdf: f32, direction: Vector3) -> f32 {
+    let sin_theta = (1.0 - direction.y * direction.y).max(0.0).sqrt();
+    if sin_theta == 0.0 {
+        return 0.0;
+    }
+    pdf / (2.0 * PI * PI * sin_theta)
+}
+
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("rotation", &self.rotation)
+            .field("intensity", &self.intensity)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Dark sky with a bright pixel a bit above the horizon, towards -z
+    /// before rotating.
+    fn sky(rotation: f32) -> EnvironmentMap {
+        let mut image = ImageCanvas::new(16, 8);
+        for x in 0..16 {
+            for y in 0..8 {
+                image.draw_point(&Color::rgb(0.1, 0.1, 0.1), x, y);
+            }
+        }
+        image.draw_point(&Color::rgb(1000.0, 1000.0, 1000.0), 8, 3);
+        EnvironmentMap::new(&image, rotation, 2.0)
+    }
+
+    #[test]
+    fn directions_map_to_pixels() {
+        let map = sky(0.0);
+        assert_eq!(map.radiance(Vector3::xyz(0.0, 0.1, -1.0)).r, 2000.0);
+        assert_eq!(map.radiance(Vector3::xyz(0.0, 0.1, 1.0)).r, 0.2);
+        assert_eq!(map.radiance(Vector3::xyz(0.0, 1.0, 0.0)).r, 0.2);
+
+        // Rotating by 90 degrees moves the bright pixel to -x.
+        let map = sky(90.0);
+        assert_eq!(map.radiance(Vector3::xyz(-1.0, 0.1, 0.0)).r, 2000.0);
+        assert_eq!(map.radiance(Vector3::xyz(0.0, 0.1, -1.0)).r, 0.2);
+
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)].iter() {
+            let direction = map.direction(*u, *v);
+            let (x, y) = map.image_coordinates(direction);
+            assert!((x - u).abs() < 1e-5 && (y - v).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn samples_the_bright_pixel() {
+        let map = sky(30.0);
+        let n = 32;
+        let mut bright = 0;
+        let mut solid_angle = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let u = ((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                let sample = map.sample(u).unwrap();
+                let pdf = map.pdf(sample.direction);
+                assert!(
+                    (pdf - sample.pdf).abs() < 1e-3 * pdf,
+                    "{} {}",
+                    pdf,
+                    sample.pdf
+                );
+                if sample.radiance.r > 1.0 {
+                    bright += 1;
+                }
+                solid_angle += sample.radiance.luminance() / sample.pdf;
+            }
+        }
+        assert!(bright > n * n * 9 / 10);
+
+        // Estimate of the integral of the luminance over the sphere.
+        let solid_angle = solid_angle / (n * n) as f32;
+        let pixel = |row: f32| {
+            let (top, bottom) = (PI * row / 8.0, PI * (row + 1.0) / 8.0);
+            2.0 * PI / 16.0 * (top.cos() - bottom.cos())
+        };
+        let expected = 2.0 * (0.1 * (4.0 * PI - pixel(3.0)) + 1000.0 * pixel(3.0));
+        assert!(
+            (solid_angle - expected).abs() < 0.02 * expected,
+            "{} vs {}",
+            solid_angle,
+            expected
+        );
+    }
+}
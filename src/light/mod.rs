@@ -0,0 +1,292 @@
+//! Light sources that can be sampled directly by the integrator.
+//!
+//! Scenes hold point, spot and directional lights and area lights of
+//! their own, every surface with an emissive material also becomes an
+//! [`AreaLight`] and [`EnvironmentMap`] and [`Sky`] backgrounds are sampled
+//! like any other light. The lights of a scene are chosen proportionally
+//! to their power.
+
+use std::{f32::consts::PI, sync::Arc};
+
+use crate::{color::Color, ray::Ray, vector::Vector3};
+
+mod area;
+mod directional;
+mod distribution;
+mod environment;
+mod point;
+mod sky;
+
+pub use area::{AreaLight, Shape};
+pub use directional::DirectionalLight;
+pub use environment::EnvironmentMap;
+pub use point::{PointLight, SpotLight};
+pub use sky::Sky;
+
+/// Incoming light at a point, as returned by [`Light::sample`].
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// Unit vector from the shaded point towards the light.
+    pub direction: Vector3,
+    /// Distance to the sampled point on the light, used to limit the
+    /// shadow ray. Infinite for directional lights.
+    pub distance: f32,
+    /// Radiance arriving from the light, or the irradiance for lights
+    /// described by a delta distribution.
+    pub radiance: Color,
+    /// Solid angle density of `direction`, 1 for delta lights.
+    pub pdf: f32,
+}
+
+#[derive(Debug, Clone)]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+    Area(AreaLight),
+    Environment(Arc<EnvironmentMap>),
+    /// The sky without the sun, which is a separate directional light.
+    Sky(Arc<Sky>),
+}
+
+impl Light {
+    /// Whether the light can only be reached by sampling it, not by rays
+    /// scattered in a random direction.
+    pub fn is_delta(&self) -> bool {
+        match self {
+            Light::Point(_) | Light::Spot(_) => true,
+            Light::Directional(light) => light.is_delta(),
+            Light::Area(_) | Light::Environment(_) | Light::Sky(_) => false,
+        }
+    }
+
+    /// Samples a direction towards the light as seen from `point`, `None` if
+    /// no light can arrive from the sampled direction.
+    pub fn sample(&self, point: Vector3, u: (f32, f32)) -> Option<LightSample> {
+        match self {
+            Light::Point(light) => light.sample(point),
+            Light::Spot(light) => light.sample(point),
+            Light::Directional(light) => light.sample(u),
+            Light::Area(light) => light.sample(point, u),
+            Light::Environment(map) => map.sample(u),
+            Light::Sky(sky) => sky.sample(u),
+        }
+    }
+
+    /// Solid angle density of sampling `ray`'s direction from its origin,
+    /// given that the first hit along the ray is at distance `t`, infinite
+    /// for rays that leave the scene.
+    pub fn pdf(&self, ray: &Ray, t: f32) -> f32 {
+        match self {
+            Light::Point(_) | Light::Spot(_) => 0.0,
+            Light::Directional(light) => light.pdf(ray, t),
+            Light::Area(light) => light.pdf(ray, t),
+            Light::Environment(_) | Light::Sky(_) if t.is_finite() => 0.0,
+            Light::Environment(map) => map.pdf(ray.direction()),
+            Light::Sky(sky) => sky.pdf(ray.direction()),
+        }
+    }
+
+    /// Radiance arriving along a ray that left the scene.
+    pub fn escaped(&self, ray: &Ray) -> Color {
+        match self {
+            Light::Directional(light) => light.escaped(ray),
+            Light::Environment(map) => map.radiance(ray.direction()),
+            Light::Sky(sky) => sky.radiance(ray.direction()),
+            _ => Color::rgba(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Closest intersection with the surface of an area light inside the
+    /// interval and the radiance it emits back along `ray`.
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, Color)> {
+        match self {
+            Light::Area(light) => light.hit(ray, t_min, t_max),
+            _ => None,
+        }
+    }
+
+    /// Whether rays that leave the scene can reach the light.
+    fn is_infinite(&self) -> bool {
+        matches!(
+            self,
+            Light::Directional(_) | Light::Environment(_) | Light::Sky(_)
+        )
+    }
+
+    /// Total emitted power, up to a constant factor shared by all lights.
+    /// Directional lights, environment maps and the sky illuminate a disk
+    /// of `scene_radius`.
+    pub fn power(&self, scene_radius: f32) -> f32 {
+        match self {
+            Light::Point(light) => light.power(),
+            Light::Spot(light) => light.power(),
+            Light::Directional(light) => light.power(scene_radius),
+            Light::Area(light) => light.power(),
+            Light::Environment(map) => map.power(scene_radius),
+            Light::Sky(sky) => sky.power(scene_radius),
+        }
+    }
+}
+
+/// Lights of a scene with the distribution used to pick one of them.
+#[derive(Default)]
+pub struct LightSet {
+    lights: Vec<Light>,
+    cdf: Vec<f32>,
+    /// Indices of the lights that rays leaving the scene can reach.
+    infinite: Vec<usize>,
+    /// Index of the first area light of every entity of the scene, the
+    /// lights of the other triangles of a mesh follow it in order.
+    entities: Vec<Option<usize>>,
+}
+
+impl LightSet {
+    /// `scene_radius` bounds the geometry lit by directional lights.
+    pub fn new(lights: Vec<Light>, scene_radius: f32) -> Self {
+        let mut total = 0.0;
+        let mut cdf = lights
+            .iter()
+            .map(|light| {
+                total += light.power(scene_radius).max(0.0);
+                total
+            })
+            .collect::<Vec<_>>();
+        if total > 0.0 {
+            cdf.iter_mut().for_each(|c| *c /= total);
+        } else {
+            // Nothing emits, fall back to choosing uniformly.
+            let count = cdf.len() as f32;
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, c)| *c = (i + 1) as f32 / count);
+        }
+        let infinite = lights
+            .iter()
+            .enumerate()
+            .filter(|(_, light)| light.is_infinite())
+            .map(|(index, _)| index)
+            .collect();
+        Self {
+            lights,
+            cdf,
+            infinite,
+            entities: Vec::new(),
+        }
+    }
+
+    /// Records which area lights stand for the entities of the scene, see
+    /// [`LightSet::entity_light`].
+    pub fn with_entities(mut self, entities: Vec<Option<usize>>) -> Self {
+        self.entities = entities;
+        self
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Picks a light with `u` in [0, 1) and returns it with the
+    /// probability of picking it.
+    pub fn choose(&self, u: f32) -> Option<(&Light, f32)> {
+        let index = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .min(self.lights.len().checked_sub(1)?);
+        Some((&self.lights[index], self.probability(index)))
+    }
+
+    /// Index of the area light of the triangle `primitive` of an emissive
+    /// entity, or of the entity itself when it isn't a mesh.
+    pub fn entity_light(&self, entity: usize, primitive: usize) -> Option<usize> {
+        let first = self.entities.get(entity).copied().flatten()?;
+        Some(first + primitive)
+    }
+
+    /// Solid angle density of sampling `ray`'s direction with
+    /// [`LightSet::choose`] followed by [`Light::sample`], for a ray that
+    /// hits the light `index` first at distance `t`. Other area lights
+    /// can't produce the same direction without being blocked by it.
+    pub fn pdf(&self, index: usize, ray: &Ray, t: f32) -> f32 {
+        self.probability(index) * self.lights[index].pdf(ray, t)
+    }
+
+    /// Like [`LightSet::pdf`], for a ray that left the scene.
+    pub fn escaped_pdf(&self, ray: &Ray) -> f32 {
+        self.infinite
+            .iter()
+            .map(|&index| self.pdf(index, ray, f32::INFINITY))
+            .sum()
+    }
+
+    /// Radiance of all lights along a ray that left the scene.
+    pub fn escaped(&self, ray: &Ray) -> Color {
+        self.infinite
+            .iter()
+            .fold(Color::rgba(0.0, 0.0, 0.0, 0.0), |sum, &index| {
+                sum + self.lights[index].escaped(ray)
+            })
+    }
+
+    fn probability(&self, index: usize) -> f32 {
+        let previous = if index == 0 { 0.0 } else { self.cdf[index - 1] };
+        self.cdf[index] - previous
+    }
+}
+
+/// Density of uniformly sampling a cone of directions.
+fn cone_pdf(cos_max: f32) -> f32 {
+    1.0 / (2.0 * PI * (1.0 - cos_max))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn light(radiance: f32, size: f32) -> Light {
+        Light::Area(AreaLight::new(
+            Shape::Triangle([
+                Vector3::xyz(0.0, 0.0, 0.0),
+                Vector3::xyz(size, 0.0, 0.0),
+                Vector3::xyz(0.0, size, 0.0),
+            ]),
+            Color::rgb(radiance, radiance, radiance),
+        ))
+    }
+
+    #[test]
+    fn chooses_by_power() {
+        let set = LightSet::new(vec![light(1.0, 1.0), light(3.0, 1.0), light(0.0, 1.0)], 1.0);
+
+        let (_, p) = set.choose(0.1).unwrap();
+        assert!((p - 0.25).abs() < 1e-6);
+        let (chosen, p) = set.choose(0.5).unwrap();
+        assert!((p - 0.75).abs() < 1e-6);
+        assert!((chosen.power(1.0) - 3.0 * light(1.0, 1.0).power(1.0)).abs() < 1e-4);
+        let (_, p) = set.choose(0.999_999).unwrap();
+        assert!((p - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn entity_lights() {
+        let set = LightSet::new(vec![light(1.0, 1.0), light(1.0, 1.0), light(1.0, 1.0)], 1.0)
+            .with_entities(vec![None, Some(1), None]);
+
+        assert_eq!(set.entity_light(0, 0), None);
+        assert_eq!(set.entity_light(1, 0), Some(1));
+        assert_eq!(set.entity_light(1, 1), Some(2));
+        assert_eq!(set.entity_light(3, 0), None);
+        assert!(set.infinite.is_empty());
+    }
+
+    #[test]
+    fn empty_set() {
+        let set = LightSet::new(Vec::new(), 1.0);
+        assert!(set.is_empty());
+        assert!(set.choose(0.5).is_none());
+    }
+}
@@ -0,0 +1,149 @@
+use std::f32::consts::PI;
+
+use crate::{color::Color, util::deg_to_rad, vector::Vector3};
+
+use super::LightSample;
+
+/// Infinitely small light emitting the same intensity in every direction.
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    position: Vector3,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Vector3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+
+    pub fn position(&self) -> Vector3 {
+        self.position
+    }
+
+    pub fn power(&self) -> f32 {
+        4.0 * PI * self.intensity.luminance()
+    }
+
+    pub fn sample(&self, point: Vector3) -> Option<LightSample> {
+        delta_sample(point, self.position, self.intensity)
+    }
+}
+
+/// Point light restricted to a cone. The intensity fades out smoothly over
+/// the outer `falloff` degrees of the cone.
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+    position: Vector3,
+    direction: Vector3,
+    intensity: Color,
+    cos_outer: f32,
+    cos_inner: f32,
+}
+
+impl SpotLight {
+    /// `cone_angle` is the angle in degrees between the axis of the cone
+    /// and its edge.
+    pub fn new(
+        position: Vector3,
+        direction: Vector3,
+        intensity: Color,
+        cone_angle: f32,
+        falloff: f32,
+    ) -> Self {
+        let falloff = falloff.clamp(0.0, cone_angle);
+        Self {
+            position,
+            direction: direction.normalized(),
+            intensity,
+            cos_outer: deg_to_rad(cone_angle).cos(),
+            cos_inner: deg_to_rad(cone_angle - falloff).cos(),
+        }
+    }
+
+    pub fn position(&self) -> Vector3 {
+        self.position
+    }
+
+    pub fn direction(&self) -> Vector3 {
+        self.direction
+    }
+
+    /// Fraction of the intensity emitted at an angle with cosine `cos_theta`
+    /// from the axis.
+    fn falloff(&self, cos_theta: f32) -> f32 {
+        if cos_theta <= self.cos_outer {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_inner {
+            return 1.0;
+        }
+        let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    pub fn power(&self) -> f32 {
+        // The falloff region counts for half of its solid angle.
+        2.0 * PI * (1.0 - 0.5 * (self.cos_inner + self.cos_outer)) * self.intensity.luminance()
+    }
+
+    pub fn sample(&self, point: Vector3) -> Option<LightSample> {
+        let cos_theta = (point - self.position).normalized() * self.direction;
+        let falloff = self.falloff(cos_theta);
+        if falloff <= 0.0 {
+            return None;
+        }
+        delta_sample(point, self.position, falloff * self.intensity)
+    }
+}
+
+fn delta_sample(point: Vector3, position: Vector3, intensity: Color) -> Option<LightSample> {
+    let to_light = position - point;
+    let d2 = to_light.squared_length();
+    if d2 == 0.0 {
+        return None;
+    }
+    let distance = d2.sqrt();
+    Some(LightSample {
+        direction: to_light / distance,
+        distance,
+        radiance: (1.0 / d2) * intensity,
+        pdf: 1.0,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn point_light_falls_off_with_distance() {
+        let light = PointLight::new(Vector3::xyz(0.0, 4.0, 0.0), Color::rgb(8.0, 8.0, 8.0));
+        let sample = light.sample(Vector3::xyz(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(sample.distance, 4.0);
+        assert_eq!(sample.direction.y, 1.0);
+        assert_eq!(sample.radiance.r, 0.5);
+        assert_eq!(sample.pdf, 1.0);
+    }
+
+    #[test]
+    fn spot_light_cone() {
+        let light = SpotLight::new(
+            Vector3::xyz(0.0, 1.0, 0.0),
+            Vector3::xyz(0.0, -2.0, 0.0),
+            Color::rgb(1.0, 1.0, 1.0),
+            45.0,
+            15.0,
+        );
+        let at = |x: f32| light.sample(Vector3::xyz(x, 0.0, 0.0));
+
+        // Full intensity inside the inner cone, none outside the outer one.
+        assert_eq!(at(0.0).unwrap().radiance.r, 1.0);
+        assert_eq!(at(0.5).unwrap().radiance.r, 1.0 / 1.25);
+        assert!(at(1.1).is_none());
+        let edge = at(0.85).unwrap().radiance.r * (1.0 + 0.85 * 0.85);
+        assert!(edge > 0.0 && edge < 1.0);
+    }
+}
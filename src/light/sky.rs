@@ -0,0 +1,306 @@
+use std::f32::consts::PI;
+
+use crate::{
+    canvas::{Canvas, ImageCanvas},
+    color::Color,
+    util::deg_to_rad,
+    vector::Vector3,
+};
+
+use super::{DirectionalLight, EnvironmentMap, LightSample};
+
+/// Sky luminance is computed in kcd/m² and the sun illuminance in klx, this
+/// maps a white surface in full sun to about 1.
+const SCALE: f32 = 0.05;
+/// Illuminance of the sun outside the atmosphere in klx.
+const SOLAR_ILLUMINANCE: f32 = 128.0;
+/// Wavelengths in micrometers at which the sunlight is attenuated for the
+/// red, green and blue channels.
+const WAVELENGTHS: [f32; 3] = [0.65, 0.57, 0.475];
+/// Resolution of the table used to importance sample the sky.
+const TABLE_WIDTH: u32 = 128;
+const TABLE_HEIGHT: u32 = 64;
+
+/// Daylight sky of Preetham et al., "A Practical Analytic Model for
+/// Daylight", 1999, together with the sun as a directional light with its
+/// real angular diameter. The sky is black below the horizon, scenes are
+/// expected to have a ground.
+#[derive(Debug)]
+pub struct Sky {
+    model: Model,
+    turbidity: f32,
+    sun: DirectionalLight,
+    table: EnvironmentMap,
+}
+
+impl Sky {
+    /// `elevation` is the angle of the sun above the horizon and `azimuth`
+    /// its angle clockwise from the negative z axis seen from above, both in
+    /// degrees. `turbidity` goes from 2 for a clear to 10 for a hazy sky.
+    pub fn new(elevation: f32, azimuth: f32, turbidity: f32, intensity: f32) -> Self {
+        // The model breaks down when the sun sets.
+        let elevation = deg_to_rad(elevation).clamp(0.0, PI / 2.0);
+        let azimuth = deg_to_rad(azimuth);
+        let to_sun = Vector3::xyz(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+        let theta_sun = PI / 2.0 - elevation;
+
+        let t = turbidity;
+        let perez = [
+            Perez::new([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez::new([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez::new([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[f32; 4]; 3]| {
+            let theta = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let row = |r: [f32; 4]| r.iter().zip(theta.iter()).map(|(a, b)| a * b).sum::<f32>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        let zenith = [luminance, x, y];
+        let zenith_scale = [0, 1, 2].map(|i| zenith[i] / perez[i].eval(1.0, theta_sun.cos()));
+
+        let sun = DirectionalLight::new(
+            -to_sun,
+            intensity * SCALE * SOLAR_ILLUMINANCE * sun_transmittance(theta_sun, turbidity),
+            0.53,
+        );
+        let model = Model {
+            to_sun,
+            intensity,
+            perez,
+            zenith: zenith_scale,
+        };
+        Self {
+            table: model.tabulate(),
+            model,
+            turbidity,
+            sun,
+        }
+    }
+
+    /// Unit vector towards the sun.
+    pub fn sun_direction(&self) -> Vector3 {
+        self.model.to_sun
+    }
+
+    pub fn turbidity(&self) -> f32 {
+        self.turbidity
+    }
+
+    /// The sun disk, sampled as a light of its own.
+    pub fn sun(&self) -> DirectionalLight {
+        self.sun
+    }
+
+    /// Radiance of the sky without the sun from `direction`, which doesn't
+    /// need to be a unit vector.
+    pub fn radiance(&self, direction: Vector3) -> Color {
+        self.model.radiance(direction)
+    }
+
+    pub fn sample(&self, u: (f32, f32)) -> Option<LightSample> {
+        let sample = self.table.sample(u)?;
+        Some(LightSample {
+            radiance: self.radiance(sample.direction),
+            ..sample
+        })
+    }
+
+    /// Solid angle density of sampling `direction`.
+    pub fn pdf(&self, direction: Vector3) -> f32 {
+        self.table.pdf(direction)
+    }
+
+    pub fn power(&self, scene_radius: f32) -> f32 {
+        self.table.power(scene_radius)
+    }
+}
+
+/// The analytic part of the sky.
+#[derive(Debug)]
+struct Model {
+    to_sun: Vector3,
+    intensity: f32,
+    /// Perez distributions of the luminance Y and the chromaticity x and y.
+    perez: [Perez; 3],
+    /// Values at the zenith divided by the distribution at the zenith.
+    zenith: [f32; 3],
+}
+
+impl Model {
+    fn radiance(&self, direction: Vector3) -> Color {
+        let direction = direction.normalized();
+        let cos_theta = direction.y;
+        if cos_theta <= 0.0 {
+            return Color::rgba(0.0, 0.0, 0.0, 0.0);
+        }
+        let cos_gamma = (direction * self.to_sun).clamp(-1.0, 1.0);
+        let [luminance, x, y] =
+            [0, 1, 2].map(|i| self.zenith[i] * self.perez[i].eval(cos_theta, cos_gamma));
+        (self.intensity * SCALE) * xyy_to_rgb(x, y, luminance)
+    }
+
+    /// Equirectangular image of the sky used to importance sample it, in the
+    /// layout of [`EnvironmentMap`].
+    fn tabulate(&self) -> EnvironmentMap {
+        let mut image = ImageCanvas::new(TABLE_WIDTH, TABLE_HEIGHT);
+        for y in 0..TABLE_HEIGHT {
+            let theta = PI * (y as f32 + 0.5) / TABLE_HEIGHT as f32;
+            for x in 0..TABLE_WIDTH {
+                let phi = 2.0 * PI * ((x as f32 + 0.5) / TABLE_WIDTH as f32 - 0.5);
+                let direction = Vector3::xyz(
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                    -theta.sin() * phi.cos(),
+                );
+                image.draw_point(&self.radiance(direction), x, y);
+            }
+        }
+        EnvironmentMap::new(&image, 0.0, 1.0)
+    }
+}
+
+/// Perez et al. all-weather sky luminance distribution.
+#[derive(Debug, Clone, Copy)]
+struct Perez {
+    coefficients: [f32; 5],
+}
+
+impl Perez {
+    fn new(coefficients: [f32; 5]) -> Self {
+        Self { coefficients }
+    }
+
+    /// Distribution at an angle `theta` from the zenith and `gamma` from the
+    /// sun, given as cosines.
+    fn eval(&self, cos_theta: f32, cos_gamma: f32) -> f32 {
+        let [a, b, c, d, e] = self.coefficients;
+        let gamma = cos_gamma.acos();
+        (1.0 + a * (b / cos_theta.max(0.01)).exp())
+            * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+}
+
+/// Converts CIE xyY to linear sRGB, clamping colors outside of the gamut.
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Color {
+    if y <= 0.0 {
+        return Color::rgba(0.0, 0.0, 0.0, 0.0);
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color::rgb(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+/// Fraction of the sunlight that crosses the atmosphere at a zenith angle of
+/// `theta`, from Rayleigh and aerosol scattering as in the appendix of
+/// Preetham et al.
+fn sun_transmittance(theta: f32, turbidity: f32) -> Color {
+    // Relative optical mass of Kasten and Young.
+    let degrees = theta.to_degrees().min(93.0);
+    let mass = 1.0 / (theta.cos() + 0.15 * (93.885 - degrees).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let [r, g, b] = WAVELENGTHS.map(|lambda| {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * mass).exp();
+        let aerosol = (-beta * lambda.powf(-1.3) * mass).exp();
+        rayleigh * aerosol
+    });
+    Color::rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zenith_luminance() {
+        let sky = Sky::new(45.0, 0.0, 3.0, 1.0);
+        let zenith = sky.radiance(Vector3::xyz(0.0, 1.0, 0.0));
+        // Zenith luminance from the model, in kcd/m².
+        let chi = (4.0 / 9.0 - 3.0 / 120.0) * (PI / 2.0);
+        let expected = (4.0453 * 3.0 - 4.9710) * chi.tan() - 0.2155 * 3.0 + 2.4192;
+        assert!(
+            (zenith.luminance() - SCALE * expected).abs() < 0.02 * SCALE * expected,
+            "{} vs {}",
+            zenith.luminance(),
+            SCALE * expected
+        );
+        // Clear skies are blue.
+        assert!(zenith.b > zenith.r);
+    }
+
+    #[test]
+    fn brightest_around_the_sun() {
+        let sky = Sky::new(30.0, 90.0, 3.0, 1.0);
+        let to_sun = sky.sun_direction();
+        assert!((to_sun.x - 30f32.to_radians().cos()).abs() < 1e-5);
+        assert!(to_sun.z.abs() < 1e-5);
+
+        let near = sky.radiance(to_sun + Vector3::xyz(0.0, 0.1, 0.0));
+        let opposite = sky.radiance(Vector3::xyz(-to_sun.x, to_sun.y, 0.0));
+        assert!(near.luminance() > 3.0 * opposite.luminance());
+        assert_eq!(sky.radiance(Vector3::xyz(0.0, -0.1, 1.0)).luminance(), 0.0);
+    }
+
+    #[test]
+    fn sun_reddens_towards_the_horizon() {
+        let noon = Sky::new(90.0, 0.0, 3.0, 1.0).sun();
+        let evening = Sky::new(5.0, 0.0, 3.0, 1.0).sun();
+        let ratio = |sun: DirectionalLight| {
+            let sample = sun.sample((0.5, 0.5)).unwrap();
+            sample.radiance.b / sample.radiance.r
+        };
+        assert!(ratio(evening) < 0.5 * ratio(noon));
+        assert!(!evening.is_delta());
+    }
+
+    #[test]
+    fn sample_and_pdf_agree() {
+        let sky = Sky::new(20.0, 200.0, 4.0, 1.0);
+        for i in 0..8 {
+            for j in 0..8 {
+                let u = ((i as f32 + 0.5) / 8.0, (j as f32 + 0.5) / 8.0);
+                let sample = sky.sample(u).unwrap();
+                assert!(sample.direction.y > 0.0);
+                assert!((sky.pdf(sample.direction) - sample.pdf).abs() < 1e-3 * sample.pdf);
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{color::Color, hit::HitRecord, ray::Ray, texture::Texture, vector::Vector3};
+
+use super::{
+    microfacet::{fresnel_conductor, reflect, Frame, Ggx},
+    ScatterRecord, Scatterable,
+};
+
+/// Metals with a measured complex index of refraction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConductorPreset {
+    Gold,
+    Copper,
+    Aluminium,
+}
+
+impl ConductorPreset {
+    /// Real and imaginary parts of the index of refraction at red, green and
+    /// blue wavelengths.
+    pub fn ior(&self) -> (Color, Color) {
+        match *self {
+            ConductorPreset::Gold => (
+                Color::rgb(0.143, 0.375, 1.442),
+                Color::rgb(3.983, 2.386, 1.603),
+            ),
+            ConductorPreset::Copper => (
+                Color::rgb(0.200, 0.924, 1.102),
+                Color::rgb(3.913, 2.453, 2.142),
+            ),
+            ConductorPreset::Aluminium => (
+                Color::rgb(1.657, 0.880, 0.521),
+                Color::rgb(9.224, 6.270, 4.837),
+            ),
+        }
+    }
+}
+
+/// Rough metal with a GGX distribution of mirror microfacets. The
+/// reflectance follows from the complex index of refraction `eta + i k`.
+#[derive(Clone)]
+pub struct Conductor {
+    eta: Color,
+    k: Color,
+    roughness: Texture,
+}
+
+impl Conductor {
+    pub fn new<T: Into<Texture>>(eta: Color, k: Color, roughness: T) -> Self {
+        Self {
+            eta,
+            k,
+            roughness: roughness.into(),
+        }
+    }
+
+    pub fn preset<T: Into<Texture>>(preset: ConductorPreset, roughness: T) -> Self {
+        let (eta, k) = preset.ior();
+        Self::new(eta, k, roughness)
+    }
+
+    /// Shading frame, distribution and the direction towards the origin of
+    /// the ray in the frame, `None` when the ray arrives from below the
+    /// shading normal.
+    fn setup(&self, ray: &Ray, hit: &HitRecord) -> Option<(Frame, Ggx, Vector3)> {
+        let frame = Frame::new(hit.normal);
+        let wo = frame.local(-ray.direction().normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let ggx = Ggx::isotropic(self.roughness.scalar(hit.uv, hit.point));
+        Some((frame, ggx, wo))
+    }
+
+    fn fresnel(&self, cos: f32) -> Color {
+        Color::rgb(
+            fresnel_conductor(cos, self.eta.r, self.k.r),
+            fresnel_conductor(cos, self.eta.g, self.k.g),
+            fresnel_conductor(cos, self.eta.b, self.k.b),
+        )
+    }
+}
+
+impl Scatterable for Conductor {
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut R,
+    ) -> Option<ScatterRecord> {
+        let (frame, ggx, wo) = self.setup(ray, hit)?;
+        let m = ggx.sample(wo, (rng.gen(), rng.gen()));
+        let wi = reflect(wo, m);
+        // Light that would leave below the surface is lost, there is no
+        // multiple scattering between the microfacets.
+        if wi.z <= 0.0 || wo * m <= 0.0 {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, frame.world(wi)),
+            attenuation: self.fresnel(wo * m) * (ggx.g(wo, wi) / ggx.g1(wo)),
+            pdf: Some(ggx.pdf(wo, m) / (4.0 * (wo * m))),
+        })
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> Color {
+        let black = Color::rgba(0.0, 0.0, 0.0, 0.0);
+        let (frame, ggx, wo) = match self.setup(ray, hit) {
+            Some(setup) => setup,
+            None => return black,
+        };
+        let wi = frame.local(direction.normalized());
+        if wi.z <= 0.0 {
+            return black;
+        }
+        let m = (wo + wi).normalized();
+        self.fresnel(wo * m) * (ggx.d(m) * ggx.g(wo, wi) / (4.0 * wo.z))
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> f32 {
+        let (frame, ggx, wo) = match self.setup(ray, hit) {
+            Some(setup) => setup,
+            None => return 0.0,
+        };
+        let wi = frame.local(direction.normalized());
+        if wi.z <= 0.0 {
+            return 0.0;
+        }
+        let m = (wo + wi).normalized();
+        ggx.pdf(wo, m) / (4.0 * (wo * m))
+    }
+}
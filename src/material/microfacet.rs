@@ -0,0 +1,332 @@
+//! Pieces shared by the microfacet materials: the GGX distribution of
+//! normals, the Fresnel equations and the shading frame they are evaluated
+//! in. Local directions point away from the surface with the shading normal
+//! as the z axis.
+
+use std::f32::consts::PI;
+
+use crate::vector::Vector3;
+
+/// Orthonormal basis around a shading normal.
+pub(crate) struct Frame {
+    tangent: Vector3,
+    bitangent: Vector3,
+    normal: Vector3,
+}
+
+impl Frame {
+    pub fn new(normal: Vector3) -> Self {
+        let (tangent, bitangent) = normal.basis();
+        Self {
+            tangent,
+            bitangent,
+            normal,
+        }
+    }
+
+    /// Frame whose tangent runs along circles around `axis`, or any frame
+    /// when the normal is parallel to the axis.
+    pub fn around(normal: Vector3, axis: Vector3) -> Self {
+        let tangent = axis ^ normal;
+        if tangent.length() < 1e-4 {
+            return Self::new(normal);
+        }
+        let tangent = tangent.normalized();
+        Self {
+            tangent,
+            bitangent: normal ^ tangent,
+            normal,
+        }
+    }
+
+    pub fn local(&self, v: Vector3) -> Vector3 {
+        Vector3::xyz(v * self.tangent, v * self.bitangent, v * self.normal)
+    }
+
+    pub fn world(&self, v: Vector3) -> Vector3 {
+        v.x * self.tangent + v.y * self.bitangent + v.z * self.normal
+    }
+}
+
+/// GGX (Trowbridge-Reitz) distribution of microfacet normals with the
+/// height-correlated Smith masking function.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ggx {
+    alpha_x: f32,
+    alpha_y: f32,
+}
+
+impl Ggx {
+    /// Below this the distribution is so narrow that the densities overflow.
+    const MIN_ALPHA: f32 = 1e-3;
+
+    pub fn new(alpha_x: f32, alpha_y: f32) -> Self {
+        Self {
+            alpha_x: alpha_x.max(Self::MIN_ALPHA),
+            alpha_y: alpha_y.max(Self::MIN_ALPHA),
+        }
+    }
+
+    /// Isotropic distribution for a perceptual roughness in [0, 1].
+    pub fn isotropic(roughness: f32) -> Self {
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        Self::new(alpha, alpha)
+    }
+
+    /// Density of microfacet normals per unit area of the macro surface.
+    pub fn d(&self, m: Vector3) -> f32 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let e = (m.x / self.alpha_x).powi(2) + (m.y / self.alpha_y).powi(2) + m.z * m.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    fn lambda(&self, w: Vector3) -> f32 {
+        let tan2 = ((self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2)) / (w.z * w.z);
+        0.5 * ((1.0 + tan2).sqrt() - 1.0)
+    }
+
+    /// Fraction of the microfacets visible from `w`.
+    pub fn g1(&self, w: Vector3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of the microfacets visible from both `wo` and `wi`.
+    pub fn g(&self, wo: Vector3, wi: Vector3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Samples a microfacet normal visible from `wo` (Heitz, "Sampling the
+    /// GGX Distribution of Visible Normals", 2018).
+    pub fn sample(&self, wo: Vector3, (u1, u2): (f32, f32)) -> Vector3 {
+        // Stretch the view direction to sample the hemisphere configuration.
+        let vh = Vector3::xyz(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).normalized();
+        let length2 = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length2 > 0.0 {
+            Vector3::xyz(-vh.y, vh.x, 0.0) / length2.sqrt()
+        } else {
+            Vector3::xyz(1.0, 0.0, 0.0)
+        };
+        let t2 = vh ^ t1;
+
+        // Point on the projected disk, squashed onto the visible half.
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        Vector3::xyz(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(0.0)).normalized()
+    }
+
+    /// Solid angle density of `sample` choosing `m`.
+    pub fn pdf(&self, wo: Vector3, m: Vector3) -> f32 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * (wo * m).max(0.0) * self.d(m) / wo.z
+    }
+}
+
+/// `v` mirrored about `m`, both pointing away from the surface.
+pub(crate) fn reflect(v: Vector3, m: Vector3) -> Vector3 {
+    2.0 * (v * m) * m - v
+}
+
+/// `v` refracted through a facet with normal `m` on the side of `v`, `eta`
+/// is the index on the other side relative to the side of `v`. `None` on
+/// total internal reflection.
+pub(crate) fn refract(v: Vector3, m: Vector3, eta: f32) -> Option<Vector3> {
+    let cos_i = v * m;
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-v / eta + (cos_i / eta - cos_t) * m)
+}
+
+/// BSDF times the cosine and the density of choosing `wi` for a rough
+/// dielectric interface (Walter et al., "Microfacet Models for Refraction
+/// through Rough Surfaces", 2007). `eta` is the index of refraction below
+/// the surface relative to the one above it.
+pub(crate) fn dielectric(ggx: &Ggx, wo: Vector3, wi: Vector3, eta: f32) -> (f32, f32) {
+    if wi.z > 0.0 {
+        let m = (wo + wi).normalized();
+        if wo * m <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let fresnel = fresnel_dielectric(wo * m, eta);
+        let f = fresnel * ggx.d(m) * ggx.g(wo, wi) / (4.0 * wo.z);
+        let pdf = fresnel * ggx.pdf(wo, m) / (4.0 * (wo * m));
+        (f, pdf)
+    } else if wi.z < 0.0 {
+        // Generalized half vector, on the side of `wo`.
+        let m = (eta * wi + wo).normalized();
+        let m = if m.z < 0.0 { -m } else { m };
+        if wo * m <= 0.0 || wi * m >= 0.0 {
+            return (0.0, 0.0);
+        }
+        let transmitted = 1.0 - fresnel_dielectric(wo * m, eta);
+        let denominator = (wi * m + (wo * m) / eta).powi(2);
+        // Radiance is compressed into the smaller solid angle on the side of
+        // the higher index.
+        let f = transmitted * ggx.d(m) * ggx.g(wo, wi) * (wi * m).abs() * (wo * m)
+            / (wo.z * denominator * eta * eta);
+        let pdf = transmitted * ggx.pdf(wo, m) * (wi * m).abs() / denominator;
+        (f, pdf)
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+/// Reflects or refracts `wo` on a visible microfacet, with the probability
+/// of reflection given by the Fresnel term. `None` when the sampled
+/// direction ends up on the wrong side of the surface.
+pub(crate) fn sample_dielectric(
+    ggx: &Ggx,
+    wo: Vector3,
+    eta: f32,
+    (u1, u2, u3): (f32, f32, f32),
+) -> Option<Vector3> {
+    let m = ggx.sample(wo, (u1, u2));
+    if wo * m <= 0.0 {
+        return None;
+    }
+    let wi = if u3 < fresnel_dielectric(wo * m, eta) {
+        reflect(wo, m)
+    } else {
+        refract(wo, m, eta)?
+    };
+    if (wi.z > 0.0) != (wi * m > 0.0) || wi.z == 0.0 {
+        return None;
+    }
+    Some(wi)
+}
+
+/// Unpolarized reflectance of a dielectric interface for light arriving at
+/// `cos_i` from the side with the lower index when `eta` is above 1, 1 on
+/// total internal reflection.
+pub(crate) fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
+/// Unpolarized reflectance of a conductor with the complex index of
+/// refraction `eta + i k`.
+pub(crate) fn fresnel_conductor(cos_i: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let (eta2, k2) = (eta * eta, k * k);
+
+    let t0 = eta2 - k2 - sin2;
+    let a2b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let a = (0.5 * (a2b2 + t0)).max(0.0).sqrt();
+    let t1 = a2b2 + cos2;
+    let t2 = 2.0 * cos2.sqrt() * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rp + rs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+    use rand_pcg::Pcg32;
+
+    fn uniform_hemisphere(u: (f32, f32)) -> Vector3 {
+        let z = u.0;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        Vector3::xyz(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    #[test]
+    fn projected_normals_cover_the_surface() {
+        let ggx = Ggx::new(0.3, 0.6);
+        let mut rng = Pcg32::new(1, 0);
+        let n = 200_000;
+        let sum: f32 = (0..n)
+            .map(|_| {
+                let m = uniform_hemisphere((rng.gen(), rng.gen()));
+                ggx.d(m) * m.z * 2.0 * PI
+            })
+            .sum();
+        assert!((sum / n as f32 - 1.0).abs() < 0.03, "{}", sum / n as f32);
+    }
+
+    #[test]
+    fn visible_normals_match_pdf() {
+        let ggx = Ggx::new(0.5, 0.2);
+        let wo = Vector3::xyz(0.6, 0.3, 0.5).normalized();
+        let mut rng = Pcg32::new(2, 0);
+        let n = 200_000;
+
+        // Integral of the density and of its mean height, estimated from
+        // uniform directions and from sampled normals.
+        let (mut integral, mut uniform_mean) = (0.0, 0.0);
+        for _ in 0..n {
+            let m = uniform_hemisphere((rng.gen(), rng.gen()));
+            let pdf = ggx.pdf(wo, m) * 2.0 * PI;
+            integral += pdf;
+            uniform_mean += pdf * m.z;
+        }
+        let sampled_mean = (0..n)
+            .map(|_| {
+                let m = ggx.sample(wo, (rng.gen(), rng.gen()));
+                assert!(wo * m >= 0.0);
+                m.z
+            })
+            .sum::<f32>();
+
+        assert!(
+            (integral / n as f32 - 1.0).abs() < 0.02,
+            "{}",
+            integral / n as f32
+        );
+        let (uniform_mean, sampled_mean) = (uniform_mean / n as f32, sampled_mean / n as f32);
+        assert!(
+            (uniform_mean - sampled_mean).abs() < 0.01,
+            "{} {}",
+            uniform_mean,
+            sampled_mean
+        );
+    }
+
+    #[test]
+    fn fresnel() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-6);
+        assert_eq!(fresnel_dielectric(0.1, 1.0 / 1.5), 1.0);
+        assert!((fresnel_dielectric(0.0, 1.5) - 1.0).abs() < 1e-6);
+
+        let (eta, k) = (0.2f32, 3.9f32);
+        let normal = ((eta - 1.0).powi(2) + k * k) / ((eta + 1.0).powi(2) + k * k);
+        assert!((fresnel_conductor(1.0, eta, k) - normal).abs() < 1e-5);
+        assert!((fresnel_conductor(0.0, eta, k) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn refraction_obeys_snell() {
+        let m = Vector3::xyz(0.0, 0.0, 1.0);
+        let v = Vector3::xyz(0.6, 0.0, 0.8);
+        let t = refract(v, m, 1.5).unwrap();
+
+        assert!((t.length() - 1.0).abs() < 1e-6);
+        assert!((t.x + 0.4).abs() < 1e-6);
+        assert!(t.z < 0.0);
+        assert!(refract(t, -m, 1.0 / 1.5).is_some());
+        assert!(refract(Vector3::xyz(0.8, 0.0, 0.6), m, 1.0 / 1.5).is_none());
+    }
+}
@@ -1,114 +1,226 @@
+use rand::Rng;
+
 use crate::{
     color::Color,
     hit::Face,
     hit::HitRecord,
     ray::Ray,
+    texture::Texture,
     util::{Random, RandomRange},
     vector::Vector3,
 };
 
+mod conductor;
+mod microfacet;
+mod principled;
+mod rough_dielectric;
+
+pub use self::conductor::{Conductor, ConductorPreset};
+pub use self::principled::Principled;
+pub use self::rough_dielectric::RoughDielectric;
+
 pub struct ScatterRecord {
     pub ray: Ray,
+    /// BSDF times the cosine of the scattered direction, divided by `pdf`.
     pub attenuation: Color,
+    /// Solid angle density of the scattered direction, `None` for specular
+    /// scattering that can't be evaluated for other directions.
+    pub pdf: Option<f32>,
 }
 
 pub trait Scatterable {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<ScatterRecord>;
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut R,
+    ) -> Option<ScatterRecord>;
+
+    /// BSDF times the cosine between `direction` and the normal, black for
+    /// specular materials.
+    fn eval(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vector3) -> Color {
+        Color::rgba(0.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Solid angle density of `scatter` choosing `direction`, zero for
+    /// specular materials.
+    fn pdf(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vector3) -> f32 {
+        0.0
+    }
+
+    /// Radiance emitted towards the origin of `ray`, black for materials
+    /// that don't emit light. The alpha of emitted light is always zero, so
+    /// it doesn't change the coverage of the pixel.
+    fn emitted(&self, _ray: &Ray, _hit: &HitRecord) -> Color {
+        Color::rgba(0.0, 0.0, 0.0, 0.0)
+    }
 
     fn reflect(v: Vector3, n: Vector3) -> Vector3 {
         v - 2.0 * (v * n) * n
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum Material {
     Lambertian(Lambertian),
     Metal(Metal),
     Dielectric(Dielectric),
+    DiffuseLight(DiffuseLight),
+    Conductor(Conductor),
+    RoughDielectric(RoughDielectric),
+    Principled(Box<Principled>),
+    /// Invisible boundary of a medium, rays pass through it unchanged.
+    Interface,
 }
 
 impl Scatterable for Material {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut R,
+    ) -> Option<ScatterRecord> {
+        match *self {
+            Material::Lambertian(ref inner) => inner.scatter(ray, hit, rng),
+            Material::Metal(ref inner) => inner.scatter(ray, hit, rng),
+            Material::Dielectric(ref inner) => inner.scatter(ray, hit, rng),
+            Material::DiffuseLight(ref inner) => inner.scatter(ray, hit, rng),
+            Material::Conductor(ref inner) => inner.scatter(ray, hit, rng),
+            Material::RoughDielectric(ref inner) => inner.scatter(ray, hit, rng),
+            Material::Principled(ref inner) => inner.scatter(ray, hit, rng),
+            Material::Interface => Some(ScatterRecord {
+                ray: Ray::new(hit.point, ray.direction()),
+                attenuation: Color::rgb(1.0, 1.0, 1.0),
+                pdf: None,
+            }),
+        }
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> Color {
+        match *self {
+            Material::Lambertian(ref inner) => inner.eval(ray, hit, direction),
+            Material::Conductor(ref inner) => inner.eval(ray, hit, direction),
+            Material::RoughDielectric(ref inner) => inner.eval(ray, hit, direction),
+            Material::Principled(ref inner) => inner.eval(ray, hit, direction),
+            _ => Color::rgba(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> f32 {
+        match *self {
+            Material::Lambertian(ref inner) => inner.pdf(ray, hit, direction),
+            Material::Conductor(ref inner) => inner.pdf(ray, hit, direction),
+            Material::RoughDielectric(ref inner) => inner.pdf(ray, hit, direction),
+            Material::Principled(ref inner) => inner.pdf(ray, hit, direction),
+            _ => 0.0,
+        }
+    }
+
+    fn emitted(&self, ray: &Ray, hit: &HitRecord) -> Color {
         match *self {
-            Material::Lambertian(ref inner) => inner.scatter(ray, hit),
-            Material::Metal(ref inner) => inner.scatter(ray, hit),
-            Material::Dielectric(ref inner) => inner.scatter(ray, hit),
+            Material::DiffuseLight(ref inner) => inner.emitted(ray, hit),
+            _ => Color::rgba(0.0, 0.0, 0.0, 0.0),
         }
     }
 }
 
 impl Random for Material {
-    fn random() -> Self {
-        let r = f32::random_range(0.0, 3.0);
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let r = f32::random_range(rng, 0.0, 3.0);
         match r {
-            r if r < 1.2 => Material::Lambertian(Lambertian::random()),
-            r if r < 2.4 => Material::Metal(Metal::random()),
-            _ => Material::Dielectric(Dielectric::random()),
+            r if r < 1.2 => Material::Lambertian(Lambertian::random(rng)),
+            r if r < 2.4 => Material::Metal(Metal::random(rng)),
+            _ => Material::Dielectric(Dielectric::random(rng)),
         }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Lambertian {
-    pub albedo: Color,
+    pub albedo: Texture,
 }
 
 impl Lambertian {
-    pub fn new(albedo: Color) -> Self {
-        Self { albedo }
+    pub fn new<T: Into<Texture>>(albedo: T) -> Self {
+        Self {
+            albedo: albedo.into(),
+        }
     }
 }
 
 impl Scatterable for Lambertian {
-    fn scatter(&self, _ray: &Ray, hit: &HitRecord) -> Option<ScatterRecord> {
-        let scatter_direction = hit.normal + Vector3::random_unit_vector();
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut R,
+    ) -> Option<ScatterRecord> {
+        let mut scatter_direction = hit.normal + Vector3::random_unit_vector(rng);
+        if scatter_direction.squared_length() < 1e-8 {
+            scatter_direction = hit.normal;
+        }
 
         let record = ScatterRecord {
             ray: Ray::new(hit.point, scatter_direction),
-            attenuation: self.albedo,
+            attenuation: self.albedo.value(hit.uv, hit.point),
+            pdf: Some(self.pdf(ray, hit, scatter_direction)),
         };
 
         Some(record)
     }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> Color {
+        self.albedo.value(hit.uv, hit.point) * self.pdf(ray, hit, direction)
+    }
+
+    fn pdf(&self, _ray: &Ray, hit: &HitRecord, direction: Vector3) -> f32 {
+        // Cosine weighted, the normal faces the incoming ray.
+        (hit.normal * direction.normalized()).max(0.0) / std::f32::consts::PI
+    }
 }
 
 impl Random for Lambertian {
-    fn random() -> Self {
-        let albedo = Color::rgb(f32::random(), f32::random(), f32::random());
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let albedo = Color::rgb(f32::random(rng), f32::random(rng), f32::random(rng));
 
-        Lambertian { albedo }
+        Lambertian::new(albedo)
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Metal {
-    albedo: Color,
-    fuzz: f32,
+    albedo: Texture,
+    /// Clamped to [0, 1] where it is evaluated.
+    fuzz: Texture,
 }
 
 impl Metal {
-    pub fn new(albedo: Color, mut fuzz: f32) -> Metal {
-        if fuzz > 1.0 {
-            fuzz = 1.0;
+    pub fn new<T: Into<Texture>, F: Into<Texture>>(albedo: T, fuzz: F) -> Metal {
+        Metal {
+            albedo: albedo.into(),
+            fuzz: fuzz.into(),
         }
-        if fuzz < 0.0 {
-            fuzz = 0.0;
-        }
-        Metal { albedo, fuzz }
     }
 }
 impl Scatterable for Metal {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut R,
+    ) -> Option<ScatterRecord> {
         let reflected = Metal::reflect(ray.direction(), hit.normal);
+        let fuzz = self.fuzz.scalar(hit.uv, hit.point).clamp(0.0, 1.0);
         let scattered = Ray::new(
             hit.point,
-            reflected + self.fuzz * Vector3::random_in_unit_sphere(),
+            reflected + fuzz * Vector3::random_in_unit_sphere(rng),
         );
 
         if scattered.direction() * hit.normal > 0.0 {
             let record = ScatterRecord {
                 ray: scattered,
-                attenuation: self.albedo,
+                attenuation: self.albedo.value(hit.uv, hit.point),
+                pdf: None,
             };
             Some(record)
         } else {
@@ -118,9 +230,9 @@ impl Scatterable for Metal {
 }
 
 impl Random for Metal {
-    fn random() -> Self {
-        let albedo = Color::rgb(f32::random(), f32::random(), f32::random());
-        let fuzz = f32::random();
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let albedo = Color::rgb(f32::random(rng), f32::random(rng), f32::random(rng));
+        let fuzz = f32::random(rng);
 
         Metal::new(albedo, fuzz)
     }
@@ -154,7 +266,12 @@ impl Dielectric {
 }
 
 impl Scatterable for Dielectric {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut R,
+    ) -> Option<ScatterRecord> {
         let ratio = match hit.face {
             Face::Front => 1.0 / self.ir,
             Face::Back => self.ir,
@@ -164,7 +281,7 @@ impl Scatterable for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let direction = if sin_theta * ratio > 1.0
-            || Dielectric::refractance(cos_theta, ratio) > f32::random()
+            || Dielectric::refractance(cos_theta, ratio) > f32::random(rng)
         {
             Dielectric::reflect(unit_dir, hit.normal)
         } else {
@@ -176,12 +293,211 @@ impl Scatterable for Dielectric {
         Some(ScatterRecord {
             attenuation: Color::rgb(1.0, 1.0, 1.0),
             ray: scattered,
+            pdf: None,
         })
     }
 }
 
 impl Random for Dielectric {
-    fn random() -> Self {
-        Dielectric::new(f32::random_range(0.0, 5.0))
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Dielectric::new(f32::random_range(rng, 0.0, 5.0))
+    }
+}
+
+/// Emits the same radiance in every direction from the front face of the
+/// surface and absorbs all incoming light.
+#[derive(Clone, Copy)]
+pub struct DiffuseLight {
+    radiance: Color,
+}
+
+impl DiffuseLight {
+    pub fn new(color: Color, intensity: f32) -> DiffuseLight {
+        DiffuseLight {
+            radiance: Color::rgba(
+                color.r * intensity,
+                color.g * intensity,
+                color.b * intensity,
+                0.0,
+            ),
+        }
+    }
+
+    pub fn radiance(&self) -> Color {
+        self.radiance
+    }
+}
+
+impl Scatterable for DiffuseLight {
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        _ray: &Ray,
+        _hit: &HitRecord,
+        _rng: &mut R,
+    ) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, _ray: &Ray, hit: &HitRecord) -> Color {
+        match hit.face {
+            Face::Front => self.radiance,
+            Face::Back => Color::rgba(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand_pcg::Pcg32;
+
+    fn hit(face: Face, material: &Material) -> HitRecord<'_> {
+        HitRecord {
+            point: Vector3::xyz(0.0, 0.0, 0.0),
+            normal: Vector3::xyz(0.0, 1.0, 0.0),
+            material,
+            t: 1.0,
+            face,
+            uv: (0.0, 0.0),
+            barycentric: None,
+            interior: None,
+            entity: 0,
+            primitive: 0,
+        }
+    }
+
+    #[test]
+    fn diffuse_light_emits_from_the_front() {
+        let ray = Ray::new(Vector3::xyz(0.0, 1.0, 0.0), Vector3::xyz(0.0, -1.0, 0.0));
+        let light = Material::DiffuseLight(DiffuseLight::new(Color::rgb(1.0, 0.5, 0.25), 4.0));
+        let front = hit(Face::Front, &light);
+        let emitted = front.material.emitted(&ray, &front);
+        assert_eq!((emitted.r, emitted.g, emitted.b), (4.0, 2.0, 1.0));
+        assert!(front
+            .material
+            .scatter(&ray, &front, &mut rand::thread_rng())
+            .is_none());
+
+        let back = hit(Face::Back, &light);
+        assert_eq!(back.material.emitted(&ray, &back).r, 0.0);
+    }
+
+    #[test]
+    fn other_materials_do_not_emit() {
+        let ray = Ray::new(Vector3::xyz(0.0, 1.0, 0.0), Vector3::xyz(0.0, -1.0, 0.0));
+        let material = Material::Lambertian(Lambertian::new(Color::rgb(1.0, 1.0, 1.0)));
+        let record = hit(Face::Front, &material);
+        let emitted = record.material.emitted(&ray, &record);
+        assert_eq!((emitted.r, emitted.g, emitted.b), (0.0, 0.0, 0.0));
+    }
+
+    fn ray() -> Ray {
+        Ray::new(Vector3::xyz(-0.3, 1.0, 0.2), Vector3::xyz(0.3, -1.0, -0.2))
+    }
+
+    /// Checks that the weight and the density of scattered rays agree with
+    /// `eval` and `pdf`.
+    fn assert_consistent(material: &Material, face: Face) {
+        let ray = ray();
+        let record = hit(face, material);
+        let mut rng = Pcg32::new(3, 0);
+        let mut scattered = 0;
+        for _ in 0..1000 {
+            let scatter = match material.scatter(&ray, &record, &mut rng) {
+                Some(scatter) => scatter,
+                None => continue,
+            };
+            scattered += 1;
+            let direction = scatter.ray.direction();
+            let pdf = material.pdf(&ray, &record, direction);
+            let f = material.eval(&ray, &record, direction);
+            let expected = scatter.pdf.unwrap();
+            assert!(
+                (pdf - expected).abs() <= 1e-3 * expected,
+                "{} {}",
+                pdf,
+                expected
+            );
+            for (weight, f) in [(scatter.attenuation.r, f.r), (scatter.attenuation.b, f.b)].iter() {
+                assert!(
+                    (weight * pdf - f).abs() <= 1e-3 * f.max(1e-3),
+                    "{} {}",
+                    weight * pdf,
+                    f
+                );
+            }
+        }
+        assert!(scattered > 900);
+    }
+
+    /// Integral of `pdf` over the sphere of directions.
+    fn pdf_integral(material: &Material, face: Face) -> f32 {
+        let ray = ray();
+        let record = hit(face, material);
+        let mut rng = Pcg32::new(4, 0);
+        let n = 200_000;
+        let sum: f32 = (0..n)
+            .map(|_| material.pdf(&ray, &record, Vector3::random_unit_vector(&mut rng)))
+            .sum();
+        sum * 4.0 * std::f32::consts::PI / n as f32
+    }
+
+    #[test]
+    fn conductor_is_consistent() {
+        let gold = Material::Conductor(Conductor::preset(ConductorPreset::Gold, 0.4));
+        assert_consistent(&gold, Face::Front);
+
+        let integral = pdf_integral(&gold, Face::Front);
+        assert!(integral > 0.9 && integral < 1.02, "{}", integral);
+
+        let direction = Vector3::xyz(0.3, 1.0, -0.2);
+        let color = gold.eval(&ray(), &hit(Face::Front, &gold), direction);
+        assert!(color.r > color.g && color.g > color.b);
+    }
+
+    #[test]
+    fn rough_dielectric_is_consistent() {
+        let glass = Material::RoughDielectric(RoughDielectric::new(1.5, 0.5));
+        for face in [Face::Front, Face::Back] {
+            assert_consistent(&glass, face.clone());
+            let integral = pdf_integral(&glass, face);
+            assert!((integral - 1.0).abs() < 0.05, "{}", integral);
+        }
+    }
+
+    #[test]
+    fn principled_is_consistent() {
+        let base = Color::rgb(0.8, 0.4, 0.2);
+        let plastic = Principled::new(base);
+        let metal = Principled {
+            metallic: 1.0.into(),
+            roughness: 0.3.into(),
+            anisotropic: 0.8.into(),
+            ..Principled::new(base)
+        };
+        let coated = Principled {
+            sheen: 1.0.into(),
+            specular_tint: 0.5.into(),
+            clearcoat: 1.0.into(),
+            clearcoat_gloss: 0.5.into(),
+            ..Principled::new(base)
+        };
+        let glass = Principled {
+            transmission: 1.0.into(),
+            ..Principled::new(base)
+        };
+        for principled in [plastic, metal, coated] {
+            let material = Material::Principled(Box::new(principled));
+            assert_consistent(&material, Face::Front);
+            let integral = pdf_integral(&material, Face::Front);
+            assert!(integral > 0.9 && integral < 1.03, "{}", integral);
+        }
+
+        let glass = Material::Principled(Box::new(glass));
+        for face in [Face::Front, Face::Back] {
+            assert_consistent(&glass, face.clone());
+            let integral = pdf_integral(&glass, face);
+            assert!((integral - 1.0).abs() < 0.05, "{}", integral);
+        }
     }
 }
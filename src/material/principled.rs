@@ -0,0 +1,290 @@
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    color::Color,
+    hit::{Face, HitRecord},
+    ray::Ray,
+    texture::Texture,
+    vector::Vector3,
+};
+
+use super::{
+    microfacet::{dielectric, reflect, sample_dielectric, Frame, Ggx},
+    ScatterRecord, Scatterable,
+};
+
+/// One material for everything from plastic to metal, cloth and glass, with
+/// the parameters of Disney's principled BSDF (Burley, "Physically Based
+/// Shading at Disney", 2012, and "Extending the Disney BRDF to a BSDF with
+/// Integrated Subsurface Scattering", 2015). All parameters except `ior`
+/// are in [0, 1].
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Texture,
+    pub metallic: Texture,
+    pub roughness: Texture,
+    /// Reflectance of the dielectric part at normal incidence, 0.5 is 4%.
+    pub specular: Texture,
+    /// Tints the dielectric reflection towards the base color.
+    pub specular_tint: Texture,
+    /// Extra reflection at grazing angles, for cloth.
+    pub sheen: Texture,
+    pub sheen_tint: Texture,
+    /// Colorless specular layer on top of everything else.
+    pub clearcoat: Texture,
+    pub clearcoat_gloss: Texture,
+    /// Stretches the highlights along circles around the y axis.
+    pub anisotropic: Texture,
+    /// Blends the dielectric part into rough glass.
+    pub transmission: Texture,
+    pub ior: f32,
+}
+
+impl Principled {
+    /// Rough plastic of the given color, the other parameters have the
+    /// defaults of Blender.
+    pub fn new<T: Into<Texture>>(base_color: T) -> Self {
+        Self {
+            base_color: base_color.into(),
+            metallic: 0.0.into(),
+            roughness: 0.5.into(),
+            specular: 0.5.into(),
+            specular_tint: 0.0.into(),
+            sheen: 0.0.into(),
+            sheen_tint: 0.5.into(),
+            clearcoat: 0.0.into(),
+            clearcoat_gloss: 1.0.into(),
+            anisotropic: 0.0.into(),
+            transmission: 0.0.into(),
+            ior: 1.45,
+        }
+    }
+
+    /// Shading frame, the direction towards the origin of the ray in the
+    /// frame and the lobes at the hit point.
+    fn setup(&self, ray: &Ray, hit: &HitRecord) -> Option<(Frame, Vector3, Lobes)> {
+        let frame = Frame::around(hit.normal, Vector3::xyz(0.0, 1.0, 0.0));
+        let wo = frame.local(-ray.direction().normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        Some((frame, wo, Lobes::new(self, hit)))
+    }
+}
+
+/// The parameters evaluated at a hit point.
+struct Lobes {
+    base: Color,
+    roughness: f32,
+    /// Reflectance of the specular lobe at normal incidence.
+    specular: Color,
+    sheen: Color,
+    /// Already scaled down to the strength of the original model.
+    clearcoat: f32,
+    clearcoat_alpha: f32,
+    ggx: Ggx,
+    /// Index of refraction below the surface relative to above it.
+    eta: f32,
+    diffuse_weight: f32,
+    specular_weight: f32,
+    glass_weight: f32,
+    /// Probabilities of sampling the diffuse, specular, glass and clearcoat
+    /// lobes.
+    probabilities: [f32; 4],
+}
+
+impl Lobes {
+    fn new(material: &Principled, hit: &HitRecord) -> Self {
+        let value = |texture: &Texture| texture.scalar(hit.uv, hit.point).clamp(0.0, 1.0);
+        let base = material.base_color.value(hit.uv, hit.point);
+        let metallic = value(&material.metallic);
+        let roughness = value(&material.roughness);
+        let transmission = value(&material.transmission);
+
+        let white = Color::rgb(1.0, 1.0, 1.0);
+        let luminance = base.luminance();
+        let tint = if luminance > 0.0 {
+            base / luminance
+        } else {
+            white
+        };
+        let mix = |a: Color, b: Color, t: f32| (1.0 - t) * a + t * b;
+        let dielectric_specular =
+            0.08 * value(&material.specular) * mix(white, tint, value(&material.specular_tint));
+
+        let aspect = (1.0 - 0.9 * value(&material.anisotropic)).sqrt();
+        let alpha = roughness * roughness;
+        let eta = match hit.face {
+            Face::Front => material.ior,
+            Face::Back => 1.0 / material.ior,
+        };
+
+        let diffuse_weight = (1.0 - metallic) * (1.0 - transmission);
+        let glass_weight = (1.0 - metallic) * transmission;
+        let specular_weight = 1.0 - glass_weight;
+        let clearcoat = 0.25 * value(&material.clearcoat);
+        let total = diffuse_weight + specular_weight + glass_weight + clearcoat;
+
+        Self {
+            base,
+            roughness,
+            specular: mix(dielectric_specular, base, metallic),
+            sheen: value(&material.sheen) * mix(white, tint, value(&material.sheen_tint)),
+            clearcoat,
+            clearcoat_alpha: 0.1 + (0.001 - 0.1) * value(&material.clearcoat_gloss),
+            ggx: Ggx::new(alpha / aspect, alpha * aspect),
+            eta,
+            diffuse_weight,
+            specular_weight,
+            glass_weight,
+            probabilities: [
+                diffuse_weight / total,
+                specular_weight / total,
+                glass_weight / total,
+                clearcoat / total,
+            ],
+        }
+    }
+
+    /// BSDF times the cosine and the density of choosing `wi`, for local
+    /// directions.
+    fn eval(&self, wo: Vector3, wi: Vector3) -> (Color, f32) {
+        let [diffuse_probability, specular_probability, glass_probability, clearcoat_probability] =
+            self.probabilities;
+        let mut f = Color::rgba(0.0, 0.0, 0.0, 0.0);
+        let mut pdf = 0.0;
+
+        if wi.z > 0.0 {
+            let h = (wo + wi).normalized();
+            let cos_d = wi * h;
+
+            if self.diffuse_weight > 0.0 {
+                // Burley diffuse with retroreflection at grazing angles.
+                let retro = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+                let diffuse = (1.0 + (retro - 1.0) * schlick_weight(wi.z))
+                    * (1.0 + (retro - 1.0) * schlick_weight(wo.z))
+                    / PI;
+                f += (self.diffuse_weight * wi.z)
+                    * (diffuse * self.base + schlick_weight(cos_d) * self.sheen);
+                pdf += diffuse_probability * wi.z / PI;
+            }
+
+            if self.specular_weight > 0.0 && wo * h > 0.0 {
+                let white = Color::rgb(1.0, 1.0, 1.0);
+                let fresnel = self.specular + schlick_weight(cos_d) * (white - self.specular);
+                let ggx = &self.ggx;
+                f += (self.specular_weight * ggx.d(h) * ggx.g(wo, wi) / (4.0 * wo.z)) * fresnel;
+                pdf += specular_probability * ggx.pdf(wo, h) / (4.0 * (wo * h));
+            }
+
+            if self.clearcoat > 0.0 && wo * h > 0.0 {
+                let d = gtr1(h.z, self.clearcoat_alpha);
+                let g = smith_g1(wo.z, 0.25) * smith_g1(wi.z, 0.25);
+                let fresnel = 0.04 + 0.96 * schlick_weight(cos_d);
+                let value = self.clearcoat * d * g * fresnel / (4.0 * wo.z);
+                f += Color::rgb(value, value, value);
+                pdf += clearcoat_probability * d * h.z / (4.0 * (wo * h));
+            }
+        }
+
+        if self.glass_weight > 0.0 {
+            let (glass, glass_pdf) = dielectric(&self.ggx, wo, wi, self.eta);
+            // Light is tinted once on the way in and once on the way out.
+            let tint = if wi.z < 0.0 {
+                Color::rgb(self.base.r.sqrt(), self.base.g.sqrt(), self.base.b.sqrt())
+            } else {
+                Color::rgb(1.0, 1.0, 1.0)
+            };
+            f += (self.glass_weight * glass) * tint;
+            pdf += glass_probability * glass_pdf;
+        }
+
+        (f, pdf)
+    }
+
+    /// Picks one of the lobes and samples a direction from it.
+    fn sample<R: Rng + ?Sized>(&self, wo: Vector3, rng: &mut R) -> Option<Vector3> {
+        let [diffuse_probability, specular_probability, glass_probability, _] = self.probabilities;
+        let (u, u1, u2) = (rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>());
+
+        let wi = if u < diffuse_probability {
+            let r = u1.sqrt();
+            let phi = 2.0 * PI * u2;
+            Vector3::xyz(r * phi.cos(), r * phi.sin(), (1.0 - u1).max(0.0).sqrt())
+        } else if u < diffuse_probability + specular_probability {
+            reflect(wo, self.ggx.sample(wo, (u1, u2)))
+        } else if u < diffuse_probability + specular_probability + glass_probability {
+            sample_dielectric(&self.ggx, wo, self.eta, (u1, u2, rng.gen()))?
+        } else {
+            let a2 = self.clearcoat_alpha * self.clearcoat_alpha;
+            let cos = ((1.0 - a2.powf(1.0 - u1)) / (1.0 - a2)).max(0.0).sqrt();
+            let sin = (1.0 - cos * cos).max(0.0).sqrt();
+            let phi = 2.0 * PI * u2;
+            reflect(wo, Vector3::xyz(sin * phi.cos(), sin * phi.sin(), cos))
+        };
+        if wi.z == 0.0 {
+            return None;
+        }
+        Some(wi)
+    }
+}
+
+impl Scatterable for Principled {
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut R,
+    ) -> Option<ScatterRecord> {
+        let (frame, wo, lobes) = self.setup(ray, hit)?;
+        let wi = lobes.sample(wo, rng)?;
+        let (f, pdf) = lobes.eval(wo, wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, frame.world(wi)),
+            attenuation: f / pdf,
+            pdf: Some(pdf),
+        })
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> Color {
+        match self.setup(ray, hit) {
+            Some((frame, wo, lobes)) => lobes.eval(wo, frame.local(direction.normalized())).0,
+            None => Color::rgba(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> f32 {
+        match self.setup(ray, hit) {
+            Some((frame, wo, lobes)) => lobes.eval(wo, frame.local(direction.normalized())).1,
+            None => 0.0,
+        }
+    }
+}
+
+/// `(1 - cos)^5`, the angular part of Schlick's Fresnel approximation.
+fn schlick_weight(cos: f32) -> f32 {
+    (1.0 - cos).clamp(0.0, 1.0).powi(5)
+}
+
+/// Generalized Trowbridge-Reitz distribution with an exponent of 1, which
+/// has the long tails of the clearcoat highlight.
+fn gtr1(cos: f32, alpha: f32) -> f32 {
+    if cos <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let t = 1.0 + (a2 - 1.0) * cos * cos;
+    (a2 - 1.0) / (PI * a2.ln() * t)
+}
+
+/// Smith masking of an isotropic GGX distribution.
+fn smith_g1(cos: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    2.0 * cos / (cos + (a2 + (1.0 - a2) * cos * cos).sqrt())
+}
@@ -0,0 +1,90 @@
+use rand::Rng;
+
+use crate::{
+    color::Color,
+    hit::{Face, HitRecord},
+    ray::Ray,
+    texture::Texture,
+    vector::Vector3,
+};
+
+use super::{
+    microfacet::{dielectric, sample_dielectric, Frame, Ggx},
+    ScatterRecord, Scatterable,
+};
+
+/// Frosted glass: a GGX distribution of smooth dielectric microfacets that
+/// both reflect and refract.
+#[derive(Clone)]
+pub struct RoughDielectric {
+    ir: f32,
+    roughness: Texture,
+}
+
+impl RoughDielectric {
+    pub fn new<T: Into<Texture>>(ir: f32, roughness: T) -> Self {
+        Self {
+            ir,
+            roughness: roughness.into(),
+        }
+    }
+
+    /// Shading frame, distribution, the direction towards the origin of the
+    /// ray in the frame and the index of refraction on the other side of
+    /// the surface relative to the side of the ray.
+    fn setup(&self, ray: &Ray, hit: &HitRecord) -> Option<(Frame, Ggx, Vector3, f32)> {
+        let frame = Frame::new(hit.normal);
+        let wo = frame.local(-ray.direction().normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let ggx = Ggx::isotropic(self.roughness.scalar(hit.uv, hit.point));
+        let eta = match hit.face {
+            Face::Front => self.ir,
+            Face::Back => 1.0 / self.ir,
+        };
+        Some((frame, ggx, wo, eta))
+    }
+}
+
+impl Scatterable for RoughDielectric {
+    fn scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut R,
+    ) -> Option<ScatterRecord> {
+        let (frame, ggx, wo, eta) = self.setup(ray, hit)?;
+        let wi = sample_dielectric(&ggx, wo, eta, (rng.gen(), rng.gen(), rng.gen()))?;
+        let (_, pdf) = dielectric(&ggx, wo, wi, eta);
+        // The Fresnel term cancels out of the weight.
+        let scale = if wi.z > 0.0 { 1.0 } else { 1.0 / (eta * eta) };
+        let weight = scale * ggx.g(wo, wi) / ggx.g1(wo);
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, frame.world(wi)),
+            attenuation: Color::rgb(weight, weight, weight),
+            pdf: Some(pdf),
+        })
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> Color {
+        let f = match self.setup(ray, hit) {
+            Some((frame, ggx, wo, eta)) => {
+                let wi = frame.local(direction.normalized());
+                dielectric(&ggx, wo, wi, eta).0
+            }
+            None => 0.0,
+        };
+        Color::rgb(f, f, f)
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> f32 {
+        match self.setup(ray, hit) {
+            Some((frame, ggx, wo, eta)) => {
+                let wi = frame.local(direction.normalized());
+                dielectric(&ggx, wo, wi, eta).1
+            }
+            None => 0.0,
+        }
+    }
+}
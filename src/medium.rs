@@ -0,0 +1,222 @@
+//! Participating media: fog, smoke and the absorption inside tinted glass.
+//!
+//! A [`Medium`] fills the interior of a closed entity or the whole scene as
+//! fog. Light travelling through it is absorbed and scattered out of its
+//! way at constant rates per unit of distance, and scattered light changes
+//! direction according to a [`HenyeyGreenstein`] phase function.
+
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use crate::{color::Color, ray::Ray, vector::Vector3};
+
+/// Phase function of Henyey and Greenstein (1941). The asymmetry `g` is the
+/// mean cosine of the scattering angle: positive values scatter forward,
+/// negative values back and zero in all directions alike.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HenyeyGreenstein {
+    g: f32,
+}
+
+impl HenyeyGreenstein {
+    /// `g` is clamped to (-1, 1), the limits would be a delta distribution.
+    pub fn new(g: f32) -> Self {
+        Self {
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    /// Density of light travelling along `direction` being scattered into
+    /// `scattered`, per steradian. Both are unit vectors.
+    pub fn eval(&self, direction: Vector3, scattered: Vector3) -> f32 {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * (direction * scattered);
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.max(0.0).sqrt())
+    }
+
+    /// Samples a scattered direction with the density of
+    /// [`HenyeyGreenstein::eval`].
+    pub fn sample(&self, direction: Vector3, u: (f32, f32)) -> Vector3 {
+        let g = self.g;
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u.0
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u.0);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        let (tangent, bitangent) = direction.basis();
+        (sin * phi.cos()) * tangent + (sin * phi.sin()) * bitangent + cos * direction
+    }
+}
+
+/// A homogeneous medium with absorption and scattering coefficients per
+/// unit of distance for red, green and blue.
+#[derive(Debug, Clone)]
+pub struct Medium {
+    absorption: Color,
+    scattering: Color,
+    phase: HenyeyGreenstein,
+}
+
+impl Medium {
+    pub fn new(absorption: Color, scattering: Color, g: f32) -> Self {
+        Self {
+            absorption,
+            scattering,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+
+    pub fn phase(&self) -> &HenyeyGreenstein {
+        &self.phase
+    }
+
+    /// Fraction of the light that travels `distance` through the medium
+    /// without being absorbed or scattered.
+    pub fn transmittance(&self, distance: f32) -> Color {
+        let extinction = self.absorption + self.scattering;
+        let channel = |sigma: f32| {
+            if sigma > 0.0 {
+                (-sigma * distance).exp()
+            } else {
+                1.0
+            }
+        };
+        Color::rgb(
+            channel(extinction.r),
+            channel(extinction.g),
+            channel(extinction.b),
+        )
+    }
+
+    /// Samples the distance a path travels along `ray` from `t_min` before
+    /// it scatters, choosing the extinction of one of the channels. Returns
+    /// the ray parameter of the scattering event, `None` when the ray
+    /// reaches `t_max` first, and the weight of the sample.
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        rng: &mut R,
+    ) -> (Option<f32>, Color) {
+        let black = Color::rgba(0.0, 0.0, 0.0, 0.0);
+        let extinction = self.absorption + self.scattering;
+        let sigma = match rng.gen_range(0, 3) {
+            0 => extinction.r,
+            1 => extinction.g,
+            _ => extinction.b,
+        };
+
+        let length = ray.direction().length();
+        let distance = (t_max - t_min) * length;
+        let free_flight = if sigma > 0.0 {
+            -(1.0 - rng.gen::<f32>()).ln() / sigma
+        } else {
+            f32::INFINITY
+        };
+        let mean = |c: Color| (c.r + c.g + c.b) / 3.0;
+
+        if free_flight < distance {
+            let transmittance = self.transmittance(free_flight);
+            let pdf = mean(extinction * transmittance);
+            if pdf <= 0.0 {
+                return (None, black);
+            }
+            let weight = transmittance * self.scattering / pdf;
+            (Some(t_min + free_flight / length), weight)
+        } else {
+            let transmittance = self.transmittance(distance);
+            let probability = mean(transmittance);
+            if probability <= 0.0 {
+                return (None, black);
+            }
+            (None, transmittance / probability)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand_pcg::Pcg32;
+
+    #[test]
+    fn phase_function_is_normalized_and_sampled() {
+        let direction = Vector3::xyz(0.3, -0.8, 0.52).normalized();
+        let mut rng = Pcg32::new(5, 0);
+        for g in [-0.7, 0.0, 0.5, 0.9] {
+            let phase = HenyeyGreenstein::new(g);
+            let n = 100_000;
+            let integral: f32 = (0..n)
+                .map(|_| phase.eval(direction, Vector3::random_unit_vector(&mut rng)))
+                .sum::<f32>()
+                * 4.0
+                * PI
+                / n as f32;
+            assert!((integral - 1.0).abs() < 0.03, "{}: {}", g, integral);
+
+            let mean_cos = (0..n)
+                .map(|_| {
+                    let scattered = phase.sample(direction, (rng.gen(), rng.gen()));
+                    assert!((scattered.length() - 1.0).abs() < 1e-3);
+                    scattered * direction
+                })
+                .sum::<f32>()
+                / n as f32;
+            assert!((mean_cos - g).abs() < 0.01, "{}: {}", g, mean_cos);
+        }
+    }
+
+    #[test]
+    fn transmittance() {
+        let medium = Medium::new(Color::rgb(0.5, 0.0, 1.0), Color::rgb(0.5, 0.0, 0.0), 0.0);
+        let transmittance = medium.transmittance(2.0);
+        assert!((transmittance.r - (-2.0f32).exp()).abs() < 1e-6);
+        assert_eq!(transmittance.g, 1.0);
+        assert!((transmittance.b - (-2.0f32).exp()).abs() < 1e-6);
+        assert_eq!(medium.transmittance(f32::INFINITY).g, 1.0);
+    }
+
+    #[test]
+    fn free_flight_sampling_is_unbiased() {
+        let medium = Medium::new(Color::rgb(0.2, 0.5, 0.0), Color::rgb(0.3, 0.5, 1.0), 0.0);
+        let ray = Ray::new(Vector3::xyz(0.0, 0.0, 0.0), Vector3::xyz(0.0, 0.0, 2.0));
+        let mut rng = Pcg32::new(6, 0);
+        let n = 200_000;
+        let mut passed = Color::rgba(0.0, 0.0, 0.0, 0.0);
+        let mut scattered = Color::rgba(0.0, 0.0, 0.0, 0.0);
+        for _ in 0..n {
+            match medium.sample(&ray, 0.0, 1.0, &mut rng) {
+                (Some(t), weight) => {
+                    assert!(t > 0.0 && t < 1.0);
+                    scattered += weight;
+                }
+                (None, weight) => passed += weight,
+            }
+        }
+
+        // Light reaches the end of the ray with the transmittance, the rest
+        // is scattered with the albedo or absorbed.
+        let transmittance = medium.transmittance(2.0);
+        let albedo = [0.6, 0.5, 1.0];
+        let channels = [
+            (passed.r, scattered.r, transmittance.r, albedo[0]),
+            (passed.g, scattered.g, transmittance.g, albedo[1]),
+            (passed.b, scattered.b, transmittance.b, albedo[2]),
+        ];
+        for (passed, scattered, transmittance, albedo) in channels.iter() {
+            let passed = passed / n as f32;
+            let scattered = scattered / n as f32;
+            assert!((passed - transmittance).abs() < 0.01, "{}", passed);
+            assert!(
+                (scattered - albedo * (1.0 - transmittance)).abs() < 0.01,
+                "{}",
+                scattered
+            );
+        }
+    }
+}
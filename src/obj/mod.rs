@@ -0,0 +1,451 @@
+//! Wavefront OBJ importer.
+//!
+//! Supports positions, texture coordinates and normals, polygonal faces
+//! (triangulated as fans), groups, objects, negative indices and `.mtl`
+//! material libraries. Every group and material pair becomes a separate
+//! `Mesh`.
+
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use crate::{
+    color::Color,
+    entity::mesh::{Mesh, MeshFace},
+    material::{Lambertian, Material},
+    vector::Vector3,
+};
+
+pub mod mtl;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    Parse {
+        source: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl ObjError {
+    pub(crate) fn parse<M: Into<String>>(source: &str, line: usize, message: M) -> Self {
+        ObjError::Parse {
+            source: source.to_string(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "{}", error),
+            ObjError::Parse {
+                source,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", source, line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> Self {
+        ObjError::Io(error)
+    }
+}
+
+/// Loads an OBJ file. Material libraries are looked up next to it.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Mesh>, ObjError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    parse(&path.display().to_string(), &text, |name| {
+        mtl::load(directory.join(name))
+    })
+}
+
+/// Parses OBJ source. `source` is only used in error messages and
+/// `load_library` resolves `mtllib` statements.
+pub fn parse<F>(source: &str, text: &str, mut load_library: F) -> Result<Vec<Mesh>, ObjError>
+where
+    F: FnMut(&str) -> Result<HashMap<String, Material>, ObjError>,
+{
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut materials: HashMap<String, Material> = HashMap::new();
+    let mut material = default_material();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut current = Group::new(material.clone());
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let error = |message: String| ObjError::parse(source, number, message);
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments = tokens.collect::<Vec<_>>();
+
+        match keyword {
+            "v" => positions.push(parse_vector(&arguments, 3, 4).map_err(error)?),
+            "vn" => normals.push(parse_vector(&arguments, 3, 3).map_err(error)?),
+            "vt" => {
+                let values = parse_floats(&arguments, 1, 3).map_err(error)?;
+                uvs.push((values[0], values.get(1).copied().unwrap_or(0.0)));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(error(format!(
+                        "face needs at least 3 vertices, got {}",
+                        arguments.len()
+                    )));
+                }
+                let counts = (positions.len(), uvs.len(), normals.len());
+                let vertices = arguments
+                    .iter()
+                    .map(|vertex| parse_vertex(vertex, counts))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                let has_uvs = vertices.iter().all(|v| v.1.is_some());
+                let has_normals = vertices.iter().all(|v| v.2.is_some());
+                for i in 1..vertices.len() - 1 {
+                    let corners = [vertices[0], vertices[i], vertices[i + 1]];
+                    current.faces.push(MeshFace {
+                        positions: [corners[0].0, corners[1].0, corners[2].0],
+                        uvs: if has_uvs {
+                            Some([
+                                corners[0].1.unwrap(),
+                                corners[1].1.unwrap(),
+                                corners[2].1.unwrap(),
+                            ])
+                        } else {
+                            None
+                        },
+                        normals: if has_normals {
+                            Some([
+                                corners[0].2.unwrap(),
+                                corners[1].2.unwrap(),
+                                corners[2].2.unwrap(),
+                            ])
+                        } else {
+                            None
+                        },
+                    });
+                }
+            }
+            "g" | "o" => {
+                groups.push(current);
+                current = Group::new(material.clone());
+            }
+            "usemtl" => {
+                let name = single_argument(&arguments).map_err(error)?;
+                material = materials
+                    .get(name)
+                    .ok_or_else(|| error(format!("unknown material: {}", name)))?
+                    .clone();
+                groups.push(current);
+                current = Group::new(material.clone());
+            }
+            "mtllib" => {
+                if arguments.is_empty() {
+                    return Err(error("expected a file name".to_string()));
+                }
+                for name in arguments {
+                    let library = load_library(name).map_err(|e| match e {
+                        ObjError::Io(e) => {
+                            error(format!("cannot read material library {}: {}", name, e))
+                        }
+                        e => e,
+                    })?;
+                    materials.extend(library);
+                }
+            }
+            // Smoothing groups, lines, points, free-form geometry and display
+            // attributes are not needed for rendering.
+            _ => {}
+        }
+    }
+    groups.push(current);
+
+    Ok(groups
+        .into_iter()
+        .filter(|group| !group.faces.is_empty())
+        .map(|group| group.into_mesh(&positions, &uvs, &normals))
+        .collect())
+}
+
+fn default_material() -> Material {
+    Material::Lambertian(Lambertian::new(Color::rgb(0.8, 0.8, 0.8)))
+}
+
+type Vertex = (usize, Option<usize>, Option<usize>);
+
+struct Group {
+    material: Material,
+    faces: Vec<MeshFace>,
+}
+
+impl Group {
+    fn new(material: Material) -> Self {
+        Self {
+            material,
+            faces: Vec::new(),
+        }
+    }
+
+    /// Builds a mesh with only the elements the group refers to.
+    fn into_mesh(self, positions: &[Vector3], uvs: &[(f32, f32)], normals: &[Vector3]) -> Mesh {
+        let mut position_map = Remap::default();
+        let mut uv_map = Remap::default();
+        let mut normal_map = Remap::default();
+
+        let faces = self
+            .faces
+            .iter()
+            .map(|face| MeshFace {
+                positions: position_map.indices(&face.positions),
+                uvs: face.uvs.map(|indices| uv_map.indices(&indices)),
+                normals: face.normals.map(|indices| normal_map.indices(&indices)),
+            })
+            .collect();
+
+        Mesh::new(
+            position_map.collect(positions),
+            normal_map.collect(normals),
+            uv_map.collect(uvs),
+            faces,
+            self.material,
+        )
+    }
+}
+
+#[derive(Default)]
+struct Remap {
+    map: HashMap<usize, usize>,
+    order: Vec<usize>,
+}
+
+impl Remap {
+    fn indices(&mut self, indices: &[usize; 3]) -> [usize; 3] {
+        [
+            self.index(indices[0]),
+            self.index(indices[1]),
+            self.index(indices[2]),
+        ]
+    }
+
+    fn index(&mut self, index: usize) -> usize {
+        let order = &mut self.order;
+        *self.map.entry(index).or_insert_with(|| {
+            order.push(index);
+            order.len() - 1
+        })
+    }
+
+    fn collect<T: Copy>(&self, buffer: &[T]) -> Vec<T> {
+        self.order.iter().map(|&i| buffer[i]).collect()
+    }
+}
+
+fn single_argument<'a>(arguments: &[&'a str]) -> Result<&'a str, String> {
+    match arguments {
+        [argument] => Ok(argument),
+        _ => Err(format!("expected 1 argument, got {}", arguments.len())),
+    }
+}
+
+pub(crate) fn parse_floats(arguments: &[&str], min: usize, max: usize) -> Result<Vec<f32>, String> {
+    if arguments.len() < min || arguments.len() > max {
+        let expected = if min == max {
+            min.to_string()
+        } else {
+            format!("{} to {}", min, max)
+        };
+        return Err(format!(
+            "expected {} numbers, got {}",
+            expected,
+            arguments.len()
+        ));
+    }
+    arguments
+        .iter()
+        .map(|a| {
+            a.parse::<f32>()
+                .map_err(|_| format!("invalid number: {}", a))
+        })
+        .collect()
+}
+
+pub(crate) fn parse_vector(arguments: &[&str], min: usize, max: usize) -> Result<Vector3, String> {
+    let values = parse_floats(arguments, min, max)?;
+    Ok(Vector3::xyz(values[0], values[1], values[2]))
+}
+
+/// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero based indices.
+fn parse_vertex(vertex: &str, counts: (usize, usize, usize)) -> Result<Vertex, String> {
+    let mut parts = vertex.split('/');
+    let position = match parts.next() {
+        Some(part) if !part.is_empty() => resolve(part, counts.0, "position")?,
+        _ => return Err(format!("invalid face vertex: {}", vertex)),
+    };
+    let uv = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve(part, counts.1, "texture coordinate")?),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve(part, counts.2, "normal")?),
+        Some(_) => return Err(format!("invalid face vertex: {}", vertex)),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex: {}", vertex));
+    }
+
+    Ok((position, uv, normal))
+}
+
+/// Converts a one based or negative (relative) index.
+fn resolve(index: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let value = index
+        .parse::<i64>()
+        .map_err(|_| format!("invalid {} index: {}", kind, index))?;
+    let resolved = if value > 0 {
+        value - 1
+    } else {
+        count as i64 + value
+    };
+    if value == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} index {} is out of range", kind, value));
+    }
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn no_libraries(name: &str) -> Result<HashMap<String, Material>, ObjError> {
+        Err(ObjError::Io(io::Error::new(io::ErrorKind::NotFound, name)))
+    }
+
+    fn error_line(result: Result<Vec<Mesh>, ObjError>) -> usize {
+        match result {
+            Err(ObjError::Parse { line, .. }) => line,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn quad_is_triangulated() {
+        let text = "
+            # unit quad
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vn 0 0 1
+            vt 0 0
+            vt 1 1
+            f 1/1/1 2/1/1 3/2/1 4/2/1
+        ";
+        let meshes = parse("quad.obj", text, no_libraries).unwrap();
+
+        assert_eq!(meshes.len(), 1);
+        let mesh = &meshes[0];
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(mesh.normals().len(), 1);
+        assert_eq!(mesh.uvs().len(), 2);
+        assert_eq!(mesh.faces().len(), 2);
+        assert_eq!(mesh.faces()[0].positions, [0, 1, 2]);
+        assert_eq!(mesh.faces()[1].positions, [0, 2, 3]);
+        assert_eq!(mesh.faces()[1].normals, Some([0, 0, 0]));
+        assert_eq!(mesh.faces()[1].uvs, Some([0, 1, 1]));
+    }
+
+    #[test]
+    fn negative_indices_and_groups() {
+        let text = "
+            g first
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            f -3 -2 -1
+            g second
+            v 0 0 1
+            v 1 0 1
+            v 0 1 1
+            vn 0 0 1
+            f -3//-1 -2//-1 -1//-1
+        ";
+        let meshes = parse("groups.obj", text, no_libraries).unwrap();
+
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].positions()[2], Vector3::xyz(0.0, 1.0, 0.0));
+        assert_eq!(meshes[1].positions()[0], Vector3::xyz(0.0, 0.0, 1.0));
+        assert_eq!(meshes[1].faces()[0].normals, Some([0, 0, 0]));
+        assert_eq!(meshes[0].faces()[0].normals, None);
+    }
+
+    #[test]
+    fn materials() {
+        let text = "
+            mtllib scene.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            usemtl glass
+            f 1 2 3
+            usemtl gold
+            f 1 2 3
+        ";
+        let meshes = parse("scene.obj", text, |name| {
+            assert_eq!(name, "scene.mtl");
+            mtl::parse(
+                name,
+                "newmtl glass\nd 0.2\nNi 1.33\nnewmtl gold\nKd 0.1 0.1 0.1\nKs 1 0.8 0.3\nNs 900",
+            )
+        })
+        .unwrap();
+
+        assert_eq!(meshes.len(), 2);
+        assert!(matches!(meshes[0].material(), Material::Dielectric(_)));
+        assert!(matches!(meshes[1].material(), Material::Metal(_)));
+    }
+
+    #[test]
+    fn errors_report_line_numbers() {
+        let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+
+        let bad_number = format!("{}v 1 x 0\n", vertices);
+        assert_eq!(error_line(parse("a.obj", &bad_number, no_libraries)), 4);
+
+        let out_of_range = format!("{}f 1 2 4\n", vertices);
+        assert_eq!(error_line(parse("a.obj", &out_of_range, no_libraries)), 4);
+
+        let zero_index = format!("\n{}f 0 1 2\n", vertices);
+        assert_eq!(error_line(parse("a.obj", &zero_index, no_libraries)), 5);
+
+        let short_face = format!("{}f 1 2\n", vertices);
+        assert_eq!(error_line(parse("a.obj", &short_face, no_libraries)), 4);
+
+        let unknown_material = format!("{}usemtl missing\n", vertices);
+        assert_eq!(
+            error_line(parse("a.obj", &unknown_material, no_libraries)),
+            4
+        );
+
+        let missing_library = "\n\nmtllib missing.mtl\n";
+        assert_eq!(error_line(parse("a.obj", missing_library, no_libraries)), 3);
+
+        let bad_vertex = format!("{}f 1/ 2//x 3\n", vertices);
+        let error = parse("a.obj", &bad_vertex, no_libraries).err().unwrap();
+        assert!(error.to_string().starts_with("a.obj:4: "));
+    }
+}
@@ -0,0 +1,219 @@
+//! Material libraries referenced by OBJ files.
+//!
+//! Only the parameters that map onto the existing materials are read:
+//! materials with a non-black emission `Ke` become `DiffuseLight`,
+//! transparent materials (`d` or `Tr`) become `Dielectric` with the `Ni`
+//! index of refraction, materials with a specular color brighter than the
+//! diffuse one become `Metal` with the fuzz derived from `Ns`, and
+//! everything else is `Lambertian` with `Kd` as albedo.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    color::Color,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+};
+
+use super::{parse_floats, ObjError};
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Material>, ObjError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    parse(&path.display().to_string(), &text)
+}
+
+pub fn parse(source: &str, text: &str) -> Result<HashMap<String, Material>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Parameters)> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let error = |message: String| ObjError::parse(source, number, message);
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments = tokens.collect::<Vec<_>>();
+
+        if keyword == "newmtl" {
+            if arguments.len() != 1 {
+                return Err(error(format!(
+                    "expected 1 argument, got {}",
+                    arguments.len()
+                )));
+            }
+            if let Some((name, parameters)) = current.take() {
+                materials.insert(name, parameters.to_material());
+            }
+            current = Some((arguments[0].to_string(), Parameters::default()));
+            continue;
+        }
+
+        let parameters = match current {
+            Some((_, ref mut parameters)) => parameters,
+            None => return Err(error(format!("{} before newmtl", keyword))),
+        };
+        match keyword {
+            "Kd" => parameters.diffuse = parse_color(&arguments).map_err(error)?,
+            "Ks" => parameters.specular = parse_color(&arguments).map_err(error)?,
+            "Ke" => parameters.emission = parse_color(&arguments).map_err(error)?,
+            "Ns" => parameters.shininess = parse_float(&arguments).map_err(error)?,
+            "Ni" => parameters.ior = parse_ior(&arguments).map_err(error)?,
+            "d" => parameters.dissolve = parse_float(&arguments).map_err(error)?,
+            "Tr" => parameters.dissolve = 1.0 - parse_float(&arguments).map_err(error)?,
+            // Ambient color, illumination models and texture maps are ignored.
+            _ => {}
+        }
+    }
+
+    if let Some((name, parameters)) = current {
+        materials.insert(name, parameters.to_material());
+    }
+
+    Ok(materials)
+}
+
+struct Parameters {
+    diffuse: Color,
+    specular: Color,
+    emission: Color,
+    shininess: f32,
+    ior: f32,
+    dissolve: f32,
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            diffuse: Color::rgb(0.8, 0.8, 0.8),
+            specular: Color::rgb(0.0, 0.0, 0.0),
+            emission: Color::rgb(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            ior: 1.5,
+            dissolve: 1.0,
+        }
+    }
+}
+
+impl Parameters {
+    fn to_material(&self) -> Material {
+        if self.emission.luminance() > 0.0 {
+            return Material::DiffuseLight(DiffuseLight::new(self.emission, 1.0));
+        }
+
+        if self.dissolve < 1.0 {
+            return Material::Dielectric(Dielectric::new(self.ior));
+        }
+
+        if self.specular.luminance() > self.diffuse.luminance() {
+            // Phong exponent to roughness, as in Walter et al. 2007.
+            let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+            return Material::Metal(Metal::new(self.specular, fuzz));
+        }
+
+        Material::Lambertian(Lambertian::new(self.diffuse))
+    }
+}
+
+fn parse_float(arguments: &[&str]) -> Result<f32, String> {
+    Ok(parse_floats(arguments, 1, 1)?[0])
+}
+
+fn parse_ior(arguments: &[&str]) -> Result<f32, String> {
+    let ior = parse_float(arguments)?;
+    if !(ior.is_finite() && ior > 0.0) {
+        return Err(format!("index of refraction must be positive, got {}", ior));
+    }
+    Ok(ior)
+}
+
+/// `Kd r g b`, a single value is used for all channels. Spectral and XYZ
+/// colors are not supported.
+fn parse_color(arguments: &[&str]) -> Result<Color, String> {
+    if let Some(&kind) = arguments.first() {
+        if kind == "spectral" || kind == "xyz" {
+            return Err(format!("{} colors are not supported", kind));
+        }
+    }
+    let values = parse_floats(arguments, 1, 3)?;
+    match values.len() {
+        1 => Ok(Color::rgb(values[0], values[0], values[0])),
+        3 => Ok(Color::rgb(values[0], values[1], values[2])),
+        n => Err(format!("expected 1 or 3 numbers, got {}", n)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::texture::Texture;
+
+    #[test]
+    fn material_mapping() {
+        let text = "
+            newmtl matte
+            Kd 0.2 0.4 0.6
+            Ks 0.1 0.1 0.1
+
+            newmtl mirror
+            Kd 0 0 0
+            Ks 0.9
+            Ns 1000
+
+            newmtl water
+            Tr 0.9
+            Ni 1.33
+
+            newmtl lamp
+            Kd 0.8 0.8 0.8
+            Ke 10 9 8
+        ";
+        let materials = parse("test.mtl", text).unwrap();
+
+        assert_eq!(materials.len(), 4);
+        match materials["matte"] {
+            Material::Lambertian(Lambertian {
+                albedo: Texture::Constant(albedo),
+            }) => {
+                assert_eq!(albedo.r, 0.2);
+                assert_eq!(albedo.b, 0.6);
+            }
+            _ => panic!("expected a lambertian material"),
+        }
+        assert!(matches!(materials["mirror"], Material::Metal(_)));
+        assert!(matches!(materials["water"], Material::Dielectric(_)));
+        match materials["lamp"] {
+            Material::DiffuseLight(ref light) => assert_eq!(light.radiance().g, 9.0),
+            _ => panic!("expected a light"),
+        }
+    }
+
+    #[test]
+    fn errors_report_line_numbers() {
+        let error = parse("test.mtl", "newmtl a\nKd 1 2\n").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "test.mtl:2: expected 1 or 3 numbers, got 2"
+        );
+
+        let error = parse("test.mtl", "Kd 1 1 1\n").err().unwrap();
+        assert_eq!(error.to_string(), "test.mtl:1: Kd before newmtl");
+
+        let error = parse("test.mtl", "newmtl a\n\nNi glass\n").err().unwrap();
+        assert_eq!(error.to_string(), "test.mtl:3: invalid number: glass");
+
+        let error = parse("test.mtl", "newmtl a\nNi 0\n").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "test.mtl:2: index of refraction must be positive, got 0"
+        );
+
+        let error = parse("test.mtl", "newmtl a\nNi inf\n").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "test.mtl:2: index of refraction must be positive, got inf"
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::io::{self, Write};
+
+use crate::canvas::{Canvas, ImageCanvas};
+
+use super::{invalid_input, reject_empty, Alpha, OutputOptions};
+
+const MAGIC: u32 = 20_000_630;
+const VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PixelType {
+    Half,
+    Float,
+}
+
+impl PixelType {
+    fn id(self) -> i32 {
+        match self {
+            PixelType::Half => 1,
+            PixelType::Float => 2,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PixelType::Half => 2,
+            PixelType::Float => 4,
+        }
+    }
+}
+
+/// Additional named channel, e.g. `Z` or `N.X`. `data` holds one value per
+/// pixel, row by row.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub name: String,
+    pub data: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExrOptions {
+    pub pixel_type: PixelType,
+    pub channels: Vec<Channel>,
+}
+
+impl Default for ExrOptions {
+    fn default() -> Self {
+        Self {
+            pixel_type: PixelType::Half,
+            channels: Vec::new(),
+        }
+    }
+}
+
+/// Writes an uncompressed single-part scanline OpenEXR image with linear,
+/// unclamped values.
+pub fn write<W: Write>(
+    mut writer: W,
+    image: &ImageCanvas,
+    options: &OutputOptions,
+    exr: &ExrOptions,
+) -> io::Result<()> {
+    reject_empty(image)?;
+
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    let pixels = image.pixels();
+
+    let red = pixels.iter().map(|c| c.r).collect::<Vec<_>>();
+    let green = pixels.iter().map(|c| c.g).collect::<Vec<_>>();
+    let blue = pixels.iter().map(|c| c.b).collect::<Vec<_>>();
+    let alpha = pixels.iter().map(|c| c.a).collect::<Vec<_>>();
+
+    let mut channels: Vec<(&str, &[f32])> = vec![("R", &red), ("G", &green), ("B", &blue)];
+    if options.alpha == Alpha::Keep {
+        channels.push(("A", &alpha));
+    }
+    for channel in exr.channels.iter() {
+        if channel.data.len() != pixels.len() {
+            return Err(invalid_input(format!(
+                "channel {} has {} values, expected {}",
+                channel.name,
+                channel.data.len(),
+                pixels.len()
+            )));
+        }
+        if channel.name.is_empty() || channels.iter().any(|(name, _)| *name == channel.name) {
+            return Err(invalid_input(format!(
+                "invalid channel name: {:?}",
+                channel.name
+            )));
+        }
+        channels.push((&channel.name, &channel.data));
+    }
+    // Channels are stored in alphabetical order.
+    channels.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC.to_le_bytes());
+    header.extend_from_slice(&VERSION.to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for (name, _) in channels.iter() {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&exr.pixel_type.id().to_le_bytes());
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    attribute(&mut header, "channels", "chlist", &chlist);
+    attribute(&mut header, "compression", "compression", &[0]);
+
+    let mut window = Vec::new();
+    for value in &[0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    let line_size = width * channels.len() * exr.pixel_type.size();
+    let first_line = header.len() + height * 8;
+    for y in 0..height {
+        let offset = (first_line + y * (line_size + 8)) as u64;
+        header.extend_from_slice(&offset.to_le_bytes());
+    }
+    writer.write_all(&header)?;
+
+    let mut line = Vec::with_capacity(line_size + 8);
+    for y in 0..height {
+        line.clear();
+        line.extend_from_slice(&(y as i32).to_le_bytes());
+        line.extend_from_slice(&(line_size as i32).to_le_bytes());
+        for (_, data) in channels.iter() {
+            for &value in data[y * width..(y + 1) * width].iter() {
+                match exr.pixel_type {
+                    PixelType::Half => line.extend_from_slice(&to_half(value).to_le_bytes()),
+                    PixelType::Float => line.extend_from_slice(&value.to_le_bytes()),
+                }
+            }
+        }
+        writer.write_all(&line)?;
+    }
+
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Converts to IEEE 754 half precision, rounding to nearest even.
+pub(crate) fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let rest = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = rest > halfway || (rest == halfway && half & 1 == 1);
+        return sign | (half + round as u32) as u16;
+    }
+
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    let round = rest > 0x1000 || (rest == 0x1000 && half & 1 == 1);
+    sign | (half + round as u32) as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn half_conversion() {
+        assert_eq!(to_half(0.0), 0x0000);
+        assert_eq!(to_half(-0.0), 0x8000);
+        assert_eq!(to_half(1.0), 0x3c00);
+        assert_eq!(to_half(0.5), 0x3800);
+        assert_eq!(to_half(-2.0), 0xc000);
+        assert_eq!(to_half(65504.0), 0x7bff);
+        assert_eq!(to_half(1e6), 0x7c00);
+        assert_eq!(to_half(f32::INFINITY), 0x7c00);
+        assert_eq!(to_half(f32::NAN) & 0x7c00, 0x7c00);
+        assert_eq!(to_half(2f32.powi(-24)), 0x0001);
+        assert_eq!(to_half(2f32.powi(-14)), 0x0400);
+        assert_eq!(to_half(1e-9), 0x0000);
+        assert_eq!(to_half(1.0 + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(to_half(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+    }
+
+    #[test]
+    fn write_float_with_extra_channel() {
+        let mut image = ImageCanvas::new(2, 1);
+        image.draw_point(&Color::rgb(10.0, 2.0, 3.0), 0, 0);
+        image.draw_point(&Color::rgb(4.0, 5.0, 6.0), 1, 0);
+        let exr = ExrOptions {
+            pixel_type: PixelType::Float,
+            channels: vec![Channel {
+                name: "Z".to_string(),
+                data: vec![7.0, 8.0],
+            }],
+        };
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &image, &OutputOptions::default(), &exr).unwrap();
+
+        assert_eq!(&bytes[..4], &[0x76, 0x2f, 0x31, 0x01]);
+        let offset_table = bytes.len() - (8 + 2 * 4 * 4) - 8;
+        let offset = u64::from_le_bytes([
+            bytes[offset_table],
+            bytes[offset_table + 1],
+            bytes[offset_table + 2],
+            bytes[offset_table + 3],
+            bytes[offset_table + 4],
+            bytes[offset_table + 5],
+            bytes[offset_table + 6],
+            bytes[offset_table + 7],
+        ]);
+        assert_eq!(offset as usize, offset_table + 8);
+
+        let values = bytes[offset_table + 16..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>();
+        // B, G, R, Z
+        assert_eq!(values, vec![3.0, 6.0, 2.0, 5.0, 10.0, 4.0, 7.0, 8.0]);
+    }
+
+    #[test]
+    fn channel_size_is_checked() {
+        let image = ImageCanvas::new(2, 2);
+        let exr = ExrOptions {
+            pixel_type: PixelType::Half,
+            channels: vec![Channel {
+                name: "Z".to_string(),
+                data: vec![0.0],
+            }],
+        };
+        let result = write(Vec::new(), &image, &OutputOptions::default(), &exr);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
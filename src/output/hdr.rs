@@ -0,0 +1,362 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+use crate::{
+    canvas::{Canvas, ImageCanvas},
+    color::Color,
+};
+
+use super::{reject_alpha, reject_empty, OutputOptions};
+
+const MIN_RUN: usize = 4;
+const MAX_RUN: usize = 127;
+const MAX_DUMP: usize = 128;
+/// Largest image `read` accepts, 16384 by 16384 pixels, so a corrupt header
+/// can't make it allocate an arbitrary amount of memory.
+const MAX_PIXELS: usize = 1 << 28;
+
+/// Writes a Radiance RGBE image. Scanlines are run-length encoded when the
+/// width allows it, values are stored linear and unclamped.
+pub fn write<W: Write>(
+    mut writer: W,
+    image: &ImageCanvas,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    reject_empty(image)?;
+    reject_alpha(options, "Radiance HDR")?;
+
+    let width = image.width() as usize;
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        image.height(),
+        image.width()
+    )?;
+
+    let mut data = Vec::with_capacity(image.pixels().len() * 4);
+    for row in image.pixels().chunks(width) {
+        let rgbe = row.iter().map(to_rgbe).collect::<Vec<_>>();
+        if (8..=0x7fff).contains(&width) {
+            data.extend_from_slice(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8]);
+            for component in 0..4 {
+                let line = rgbe.iter().map(|p| p[component]).collect::<Vec<_>>();
+                write_rle(&line, &mut data);
+            }
+        } else {
+            rgbe.iter().for_each(|p| data.extend_from_slice(p));
+        }
+    }
+    writer.write_all(&data)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ImageCanvas> {
+    read(BufReader::new(File::open(path)?))
+}
+
+/// Reads a Radiance RGBE image stored top to bottom, flat or with run-length
+/// encoded scanlines. The old run-length encoding and XYZE files are not
+/// supported.
+pub fn read<R: BufRead>(mut reader: R) -> io::Result<ImageCanvas> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_data("not a Radiance HDR file".to_string()));
+    }
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("missing resolution".to_string()));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported format {}", format)));
+            }
+        }
+    }
+
+    line.clear();
+    reader.read_line(&mut line)?;
+    let (width, height) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => match (width.parse::<u32>(), height.parse::<u32>()) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => return Err(invalid_data(format!("invalid resolution {}", line.trim()))),
+        },
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported orientation {}",
+                line.trim()
+            )))
+        }
+    };
+    if width == 0 || height == 0 {
+        return Err(invalid_data(format!("empty image {}", line.trim())));
+    }
+    match (width as usize).checked_mul(height as usize) {
+        Some(pixels) if pixels <= MAX_PIXELS => {}
+        _ => return Err(invalid_data(format!("image too large {}", line.trim()))),
+    }
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let mut image = ImageCanvas::new(width, height);
+    let row_length = width as usize;
+    let mut offset = 0;
+    let mut row = vec![[0; 4]; row_length];
+    for y in 0..height {
+        let encoded = (8..=0x7fff).contains(&row_length)
+            && data.len() >= offset + 4
+            && data[offset..offset + 2] == [2, 2]
+            && data[offset + 2] & 0x80 == 0;
+        if encoded {
+            if ((data[offset + 2] as usize) << 8 | data[offset + 3] as usize) != row_length {
+                return Err(invalid_data("scanline width mismatch".to_string()));
+            }
+            offset += 4;
+            for component in 0..4 {
+                let (values, read) = read_rle(&data[offset..], row_length)?;
+                row.iter_mut()
+                    .zip(values)
+                    .for_each(|(pixel, value)| pixel[component] = value);
+                offset += read;
+            }
+        } else {
+            let bytes = data
+                .get(offset..offset + 4 * row_length)
+                .ok_or_else(truncated)?;
+            row.iter_mut()
+                .zip(bytes.chunks(4))
+                .for_each(|(pixel, rgbe)| pixel.copy_from_slice(rgbe));
+            offset += 4 * row_length;
+        }
+        for (x, rgbe) in row.iter().enumerate() {
+            image.draw_point(&from_rgbe(rgbe), x as u32, y);
+        }
+    }
+
+    Ok(image)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn truncated() -> io::Error {
+    invalid_data("unexpected end of pixel data".to_string())
+}
+
+pub(crate) fn from_rgbe(rgbe: &[u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::rgb(0.0, 0.0, 0.0);
+    }
+    let scale = 2f32.powi(rgbe[3] as i32 - 136);
+    Color::rgb(
+        rgbe[0] as f32 * scale,
+        rgbe[1] as f32 * scale,
+        rgbe[2] as f32 * scale,
+    )
+}
+
+/// Decodes `len` values of one run-length encoded component and returns
+/// them with the number of bytes read.
+fn read_rle(data: &[u8], len: usize) -> io::Result<(Vec<u8>, usize)> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+    while out.len() < len {
+        let count = *data.get(i).ok_or_else(truncated)? as usize;
+        if count > 128 {
+            let value = *data.get(i + 1).ok_or_else(truncated)?;
+            out.resize(out.len() + count - 128, value);
+            i += 2;
+        } else {
+            let values = data.get(i + 1..i + 1 + count).ok_or_else(truncated)?;
+            out.extend_from_slice(values);
+            i += 1 + count;
+        }
+        if count == 0 || out.len() > len {
+            return Err(invalid_data("invalid scanline encoding".to_string()));
+        }
+    }
+    Ok((out, i))
+}
+
+pub(crate) fn to_rgbe(color: &Color) -> [u8; 4] {
+    let r = color.r.max(0.0);
+    let g = color.g.max(0.0);
+    let b = color.b.max(0.0);
+    let v = r.max(g).max(b);
+    if v < 1e-32 || !v.is_finite() {
+        return [0, 0, 0, 0];
+    }
+
+    let mut exponent = v.log2().floor() as i32 + 1;
+    let mut scale = 256.0 / 2f32.powi(exponent);
+    if v * scale >= 256.0 {
+        exponent += 1;
+        scale *= 0.5;
+    }
+
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+/// Adaptive run-length encoding of one component of a scanline, as in the
+/// reference `rgbe.c` implementation.
+fn write_rle(data: &[u8], out: &mut Vec<u8>) {
+    let mut current = 0;
+    while current < data.len() {
+        let mut run_begin = current;
+        let mut run_count = 0;
+        let mut old_run_count = 0;
+        while run_count < MIN_RUN && run_begin < data.len() {
+            run_begin += run_count;
+            old_run_count = run_count;
+            run_count = 1;
+            while run_begin + run_count < data.len()
+                && run_count < MAX_RUN
+                && data[run_begin] == data[run_begin + run_count]
+            {
+                run_count += 1;
+            }
+        }
+
+        if old_run_count > 1 && old_run_count == run_begin - current {
+            out.push((128 + old_run_count) as u8);
+            out.push(data[current]);
+            current = run_begin;
+        }
+
+        while current < run_begin {
+            let count = (run_begin - current).min(MAX_DUMP);
+            out.push(count as u8);
+            out.extend_from_slice(&data[current..current + count]);
+            current += count;
+        }
+
+        if run_count >= MIN_RUN {
+            out.push((128 + run_count) as u8);
+            out.push(data[run_begin]);
+            current += run_count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rgbe_conversion() {
+        assert_eq!(to_rgbe(&Color::rgb(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(&Color::rgb(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(&Color::rgb(4.0, 0.0, 0.0)), [128, 0, 0, 131]);
+        assert_eq!(to_rgbe(&Color::rgb(0.75, 0.0, 0.0)), [192, 0, 0, 128]);
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let mut data = vec![7; 300];
+        data.extend_from_slice(&[1, 2, 3, 3, 4, 5, 5, 5, 5, 5, 6]);
+        data.extend((0..200).map(|i| i as u8));
+
+        let mut encoded = Vec::new();
+        write_rle(&data, &mut encoded);
+        assert!(encoded.len() < data.len());
+
+        let (decoded, read) = read_rle(&encoded, data.len()).unwrap();
+        assert_eq!(decoded, data);
+        assert_eq!(read, encoded.len());
+    }
+
+    #[test]
+    fn write_keeps_values_above_one() {
+        let mut image = ImageCanvas::new(8, 1);
+        for x in 0..8 {
+            image.draw_point(&Color::rgb(16.0, 1.0, 0.0), x, 0);
+        }
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &image, &OutputOptions::default()).unwrap();
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n";
+        assert_eq!(&bytes[..header.len()], &header[..]);
+        let body = &bytes[header.len()..];
+        assert_eq!(&body[..4], &[2, 2, 0, 8]);
+
+        let mut offset = 4;
+        let mut components = Vec::new();
+        for _ in 0..4 {
+            let (component, read) = read_rle(&body[offset..], 8).unwrap();
+            components.push(component[0]);
+            offset += read;
+        }
+        assert_eq!(offset, body.len());
+        assert_eq!(components, to_rgbe(&Color::rgb(16.0, 1.0, 0.0)).to_vec());
+        assert_eq!(components, vec![128, 8, 0, 133]);
+    }
+
+    #[test]
+    fn read_round_trip() {
+        for width in [3, 16].iter() {
+            let mut image = ImageCanvas::new(*width, 2);
+            for x in 0..*width {
+                image.draw_point(&Color::rgb(x as f32, 0.25, 10.0), x, 0);
+                image.draw_point(&Color::rgb(0.5, 0.0, 3.0), x, 1);
+            }
+            let mut bytes = Vec::new();
+            write(&mut bytes, &image, &OutputOptions::default()).unwrap();
+
+            let read = read(&bytes[..]).unwrap();
+            assert_eq!(read.width(), *width);
+            assert_eq!(read.height(), 2);
+            // The components share the exponent of the largest one.
+            for (a, b) in read.pixels().iter().zip(image.pixels()) {
+                let precision = b.r.max(b.g).max(b.b) / 128.0;
+                assert!((a.r - b.r).abs() <= precision);
+                assert!((a.g - b.g).abs() <= precision);
+                assert!((a.b - b.b).abs() <= precision);
+            }
+        }
+    }
+
+    #[test]
+    fn read_errors() {
+        let error = |bytes: &[u8]| read(bytes).err().unwrap().to_string();
+        assert_eq!(error(b"P6\n"), "not a Radiance HDR file");
+        assert_eq!(
+            error(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"),
+            "unsupported format 32-bit_rle_xyze"
+        );
+        assert_eq!(
+            error(b"#?RADIANCE\n\n+Y 1 +X 1\n"),
+            "unsupported orientation +Y 1 +X 1"
+        );
+        assert_eq!(
+            error(b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80\x80\x80"),
+            "unexpected end of pixel data"
+        );
+        assert_eq!(
+            error(b"#?RADIANCE\n\n-Y 4294967295 +X 0\n"),
+            "empty image -Y 4294967295 +X 0"
+        );
+        assert_eq!(error(b"#?RADIANCE\n\n-Y 0 +X 1\n"), "empty image -Y 0 +X 1");
+        assert_eq!(
+            error(b"#?RADIANCE\n\n-Y 4294967295 +X 4294967295\n"),
+            "image too large -Y 4294967295 +X 4294967295"
+        );
+        assert_eq!(
+            error(b"#?RADIANCE\n\n-Y 16385 +X 16384\n"),
+            "image too large -Y 16385 +X 16384"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::canvas::{Canvas, ImageCanvas};
+
+pub mod exr;
+pub mod hdr;
+pub mod pfm;
+pub mod png;
+pub mod ppm;
+
+/// Transfer function applied to linear values before they are quantized
+/// into an 8-bit format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transfer {
+    /// Values are written as they are.
+    Linear,
+    /// Simple power curve.
+    Gamma(f32),
+    /// Piecewise sRGB curve, also used by the preview in the window.
+    Srgb,
+}
+
+impl Transfer {
+    pub fn encode(&self, value: f32) -> f32 {
+        let value = value.max(0.0);
+        match *self {
+            Transfer::Linear => value,
+            Transfer::Gamma(gamma) => value.powf(1.0 / gamma),
+            Transfer::Srgb => {
+                if value <= 0.003_130_8 {
+                    value * 12.92
+                } else {
+                    1.055 * value.powf(1.0 / 2.4) - 0.055
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alpha {
+    Keep,
+    Discard,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OutputOptions {
+    pub transfer: Transfer,
+    pub alpha: Alpha,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            transfer: Transfer::Srgb,
+            alpha: Alpha::Discard,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+    PpmAscii,
+    Pfm,
+    Hdr,
+    Exr,
+}
+
+impl ImageFormat {
+    /// Guesses the format from the file extension. `.ppm` files are written
+    /// in the binary flavour.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            "pfm" => Some(ImageFormat::Pfm),
+            "hdr" => Some(ImageFormat::Hdr),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+}
+
+pub fn write<W: Write>(
+    writer: W,
+    format: ImageFormat,
+    image: &ImageCanvas,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    match format {
+        ImageFormat::Png => png::write(writer, image, options),
+        ImageFormat::Ppm => ppm::write(writer, image, options, ppm::Encoding::Binary),
+        ImageFormat::PpmAscii => ppm::write(writer, image, options, ppm::Encoding::Ascii),
+        ImageFormat::Pfm => pfm::write(writer, image, options),
+        ImageFormat::Hdr => hdr::write(writer, image, options),
+        ImageFormat::Exr => exr::write(writer, image, options, &exr::ExrOptions::default()),
+    }
+}
+
+pub fn save<P: AsRef<Path>>(
+    path: P,
+    image: &ImageCanvas,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let format = ImageFormat::from_path(path)
+        .ok_or_else(|| invalid_input(format!("unsupported image format: {}", path.display())))?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer, format, image, options)?;
+    writer.flush()
+}
+
+pub(crate) fn quantize(value: f32, transfer: Transfer) -> u8 {
+    (transfer.encode(value).min(1.0) * 255.0 + 0.5) as u8
+}
+
+pub(crate) fn invalid_input<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidInput, error)
+}
+
+/// Images without pixels can't be represented by most formats and would
+/// have no scanlines to write.
+pub(crate) fn reject_empty(image: &ImageCanvas) -> io::Result<()> {
+    if image.width() == 0 || image.height() == 0 {
+        return Err(invalid_input(format!(
+            "cannot write an empty {}x{} image",
+            image.width(),
+            image.height()
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn reject_alpha(options: &OutputOptions, format: &str) -> io::Result<()> {
+    if options.alpha == Alpha::Keep {
+        return Err(invalid_input(format!("{} does not support alpha", format)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transfer_encode() {
+        assert_eq!(Transfer::Linear.encode(0.25), 0.25);
+        assert_eq!(Transfer::Gamma(2.0).encode(0.25), 0.5);
+        assert!((Transfer::Srgb.encode(0.5) - 0.735_357).abs() < 1e-5);
+        assert_eq!(Transfer::Srgb.encode(-1.0), 0.0);
+    }
+
+    #[test]
+    fn quantize_clamps() {
+        assert_eq!(quantize(0.0, Transfer::Srgb), 0);
+        assert_eq!(quantize(1.0, Transfer::Srgb), 255);
+        assert_eq!(quantize(4.0, Transfer::Linear), 255);
+        assert_eq!(quantize(0.5, Transfer::Linear), 128);
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(ImageFormat::from_path("a.png"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path("a/b.PPM"), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::from_path("c.pfm"), Some(ImageFormat::Pfm));
+        assert_eq!(ImageFormat::from_path("d.hdr"), Some(ImageFormat::Hdr));
+        assert_eq!(ImageFormat::from_path("d.exr"), Some(ImageFormat::Exr));
+        assert_eq!(ImageFormat::from_path("c.bmp"), None);
+        assert_eq!(ImageFormat::from_path("c"), None);
+    }
+
+    #[test]
+    fn write_rejects_empty_images() {
+        let options = OutputOptions::default();
+        for image in [ImageCanvas::new(0, 2), ImageCanvas::new(2, 0)].iter() {
+            for format in [
+                ImageFormat::Png,
+                ImageFormat::Ppm,
+                ImageFormat::PpmAscii,
+                ImageFormat::Pfm,
+                ImageFormat::Hdr,
+                ImageFormat::Exr,
+            ]
+            .iter()
+            {
+                let error = write(Vec::new(), *format, image, &options).unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{:?}", format);
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::io::{self, Write};
+
+use crate::canvas::{Canvas, ImageCanvas};
+
+use super::{reject_alpha, reject_empty, OutputOptions};
+
+/// Writes a little-endian RGB PFM. Float images always store linear values,
+/// so the transfer function from `options` is not applied.
+pub fn write<W: Write>(
+    mut writer: W,
+    image: &ImageCanvas,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    reject_empty(image)?;
+    reject_alpha(options, "PFM")?;
+
+    write!(writer, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+
+    // PFM scanlines go from the bottom of the image to the top.
+    let mut data = Vec::with_capacity(image.pixels().len() * 12);
+    for row in image.pixels().chunks(image.width() as usize).rev() {
+        for color in row {
+            data.extend_from_slice(&color.r.to_le_bytes());
+            data.extend_from_slice(&color.g.to_le_bytes());
+            data.extend_from_slice(&color.b.to_le_bytes());
+        }
+    }
+    writer.write_all(&data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn write_bottom_to_top() {
+        let mut image = ImageCanvas::new(1, 2);
+        image.draw_point(&Color::rgb(1.0, 2.0, 3.0), 0, 0);
+        image.draw_point(&Color::rgb(4.0, 5.0, 6.0), 0, 1);
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &image, &OutputOptions::default()).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let values = bytes[header.len()..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![4.0, 5.0, 6.0, 1.0, 2.0, 3.0]);
+    }
+}
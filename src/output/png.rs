@@ -0,0 +1,92 @@
+use std::io::{self, Write};
+
+use crate::canvas::{Canvas, ImageCanvas};
+
+use super::{quantize, reject_empty, Alpha, OutputOptions, Transfer};
+
+pub fn write<W: Write>(writer: W, image: &ImageCanvas, options: &OutputOptions) -> io::Result<()> {
+    reject_empty(image)?;
+
+    let mut encoder = ::png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_depth(::png::BitDepth::Eight);
+    encoder.set_color(match options.alpha {
+        Alpha::Keep => ::png::ColorType::RGBA,
+        Alpha::Discard => ::png::ColorType::RGB,
+    });
+
+    let mut writer = encoder.write_header()?;
+    match options.transfer {
+        Transfer::Srgb => writer.write_chunk(*b"sRGB", &[0])?,
+        Transfer::Gamma(gamma) => writer.write_chunk(*b"gAMA", &gama(1.0 / gamma))?,
+        Transfer::Linear => writer.write_chunk(*b"gAMA", &gama(1.0))?,
+    }
+
+    let channels = if options.alpha == Alpha::Keep { 4 } else { 3 };
+    let mut data = Vec::with_capacity(image.pixels().len() * channels);
+    for color in image.pixels() {
+        data.push(quantize(color.r, options.transfer));
+        data.push(quantize(color.g, options.transfer));
+        data.push(quantize(color.b, options.transfer));
+        if options.alpha == Alpha::Keep {
+            data.push(quantize(color.a, Transfer::Linear));
+        }
+    }
+    writer.write_image_data(&data)?;
+
+    Ok(())
+}
+
+/// Encodes the `gAMA` chunk, which stores the file gamma times 100000.
+fn gama(gamma: f32) -> [u8; 4] {
+    ((gamma * 100_000.0).round() as u32).to_be_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{color::Color, output::Transfer};
+
+    fn decode(bytes: &[u8]) -> (::png::OutputInfo, Vec<u8>) {
+        let decoder = ::png::Decoder::new(bytes);
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        (info, data)
+    }
+
+    #[test]
+    fn write_rgb() {
+        let mut image = ImageCanvas::new(2, 1);
+        image.draw_point(&Color::rgb(1.0, 0.0, 0.25), 0, 0);
+        image.draw_point(&Color::rgba(2.0, 0.5, 0.0, 0.5), 1, 0);
+
+        let options = OutputOptions {
+            transfer: Transfer::Linear,
+            alpha: Alpha::Discard,
+        };
+        let mut bytes = Vec::new();
+        write(&mut bytes, &image, &options).unwrap();
+
+        let (info, data) = decode(&bytes);
+        assert_eq!(info.color_type, ::png::ColorType::RGB);
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(data, vec![255, 0, 64, 255, 128, 0]);
+    }
+
+    #[test]
+    fn write_rgba() {
+        let mut image = ImageCanvas::new(1, 1);
+        image.draw_point(&Color::rgba(0.0, 0.0, 0.0, 0.5), 0, 0);
+
+        let options = OutputOptions {
+            transfer: Transfer::Srgb,
+            alpha: Alpha::Keep,
+        };
+        let mut bytes = Vec::new();
+        write(&mut bytes, &image, &options).unwrap();
+
+        let (info, data) = decode(&bytes);
+        assert_eq!(info.color_type, ::png::ColorType::RGBA);
+        assert_eq!(data, vec![0, 0, 0, 128]);
+    }
+}
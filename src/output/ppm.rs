@@ -0,0 +1,121 @@
+use std::io::{self, Write};
+
+use crate::canvas::{Canvas, ImageCanvas};
+
+use super::{quantize, reject_alpha, reject_empty, OutputOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    /// `P3`, one decimal value per channel.
+    Ascii,
+    /// `P6`, one byte per channel.
+    Binary,
+}
+
+pub fn write<W: Write>(
+    mut writer: W,
+    image: &ImageCanvas,
+    options: &OutputOptions,
+    encoding: Encoding,
+) -> io::Result<()> {
+    reject_empty(image)?;
+    reject_alpha(options, "PPM")?;
+
+    let magic = match encoding {
+        Encoding::Ascii => "P3",
+        Encoding::Binary => "P6",
+    };
+    write!(
+        writer,
+        "{}\n{} {}\n255\n",
+        magic,
+        image.width(),
+        image.height()
+    )?;
+
+    match encoding {
+        Encoding::Ascii => {
+            for row in image.pixels().chunks(image.width() as usize) {
+                let line = row
+                    .iter()
+                    .map(|color| {
+                        format!(
+                            "{} {} {}",
+                            quantize(color.r, options.transfer),
+                            quantize(color.g, options.transfer),
+                            quantize(color.b, options.transfer)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                writeln!(writer, "{}", line)?;
+            }
+        }
+        Encoding::Binary => {
+            let mut data = Vec::with_capacity(image.pixels().len() * 3);
+            for color in image.pixels() {
+                data.push(quantize(color.r, options.transfer));
+                data.push(quantize(color.g, options.transfer));
+                data.push(quantize(color.b, options.transfer));
+            }
+            writer.write_all(&data)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        color::Color,
+        output::{Alpha, Transfer},
+    };
+
+    fn image() -> ImageCanvas {
+        let mut image = ImageCanvas::new(2, 2);
+        image.draw_point(&Color::rgb(1.0, 0.0, 0.0), 0, 0);
+        image.draw_point(&Color::rgb(0.0, 1.0, 0.0), 1, 0);
+        image.draw_point(&Color::rgb(0.0, 0.0, 1.0), 0, 1);
+        image.draw_point(&Color::rgb(1.0, 1.0, 1.0), 1, 1);
+        image
+    }
+
+    #[test]
+    fn write_ascii() {
+        let options = OutputOptions {
+            transfer: Transfer::Linear,
+            alpha: Alpha::Discard,
+        };
+        let mut bytes = Vec::new();
+        write(&mut bytes, &image(), &options, Encoding::Ascii).unwrap();
+
+        let expected = "P3\n2 2\n255\n255 0 0 0 255 0\n0 0 255 255 255 255\n";
+        assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+    }
+
+    #[test]
+    fn write_binary() {
+        let options = OutputOptions {
+            transfer: Transfer::Linear,
+            alpha: Alpha::Discard,
+        };
+        let mut bytes = Vec::new();
+        write(&mut bytes, &image(), &options, Encoding::Binary).unwrap();
+
+        let mut expected = b"P6\n2 2\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255]);
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn alpha_is_rejected() {
+        let options = OutputOptions {
+            transfer: Transfer::Srgb,
+            alpha: Alpha::Keep,
+        };
+        let result = write(Vec::new(), &image(), &options, Encoding::Binary);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
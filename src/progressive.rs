@@ -0,0 +1,151 @@
+//! Progressive rendering: the frame is refined in passes of a few samples
+//! per pixel, so a usable image is available long before the target sample
+//! count is reached.
+
+use std::time::Duration;
+
+use crate::{
+    adaptive::{AdaptiveOptions, PixelStats, SampleCounts},
+    canvas::Canvas,
+    color::Color,
+    tile::Tile,
+};
+
+pub struct ProgressiveOptions {
+    /// Samples per pixel added by every pass.
+    pub pass_samples: u32,
+    /// Stops after the first pass that ends past this budget, even if the
+    /// target sample count isn't reached yet.
+    pub time_budget: Option<Duration>,
+}
+
+impl Default for ProgressiveOptions {
+    fn default() -> Self {
+        Self {
+            pass_samples: 1,
+            time_budget: None,
+        }
+    }
+}
+
+/// State of the render after a finished pass.
+#[derive(Debug, Clone, Copy)]
+pub struct Pass {
+    /// Zero based index of the pass.
+    pub index: u32,
+    /// Samples per pixel accumulated so far.
+    pub samples: u32,
+    /// Time since the start of the first pass.
+    pub elapsed: Duration,
+}
+
+/// Running statistics of the samples of every pixel, stored row by row.
+pub struct Accumulator {
+    width: u32,
+    height: u32,
+    stats: Vec<PixelStats>,
+}
+
+impl Accumulator {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            stats: vec![PixelStats::default(); (width * height) as usize],
+        }
+    }
+
+    /// Adds the samples of a rendered tile.
+    pub fn add_tile(&mut self, tile: &Tile) {
+        for x in 0..tile.width {
+            for y in 0..tile.height {
+                let index = self.index(x + tile.x, y + tile.y);
+                self.stats[index].merge(&tile.stats[(x * tile.height + y) as usize]);
+            }
+        }
+    }
+
+    pub fn samples(&self, x: u32, y: u32) -> u32 {
+        self.stats[self.index(x, y)].samples()
+    }
+
+    /// Mean of the samples of a pixel, black if it has none yet.
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.stats[self.index(x, y)].mean()
+    }
+
+    /// Row by row flags of the pixels that still need samples.
+    pub fn unconverged(&self, options: &AdaptiveOptions) -> Vec<bool> {
+        self.stats
+            .iter()
+            .map(|s| !s.is_converged(options))
+            .collect()
+    }
+
+    pub fn sample_counts(&self) -> SampleCounts {
+        let mut counts = SampleCounts::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                counts.set(x, y, self.samples(x, y));
+            }
+        }
+        counts
+    }
+
+    /// Draws the current estimate of the whole frame.
+    pub fn draw<T: Canvas + ?Sized>(&self, canvas: &mut T) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                canvas.draw_point(&self.pixel(x, y), x, y);
+            }
+        }
+        canvas.flush();
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::canvas::ImageCanvas;
+
+    #[test]
+    fn accumulates_means() {
+        let mut accumulator = Accumulator::new(3, 2);
+        let mut tile = Tile::new(1, 0, 2, 2);
+        for stats in tile.stats.iter_mut() {
+            stats.add(Color::rgb(1.0, 2.0, 3.0));
+            stats.add(Color::rgb(1.0, 2.0, 3.0));
+        }
+        accumulator.add_tile(&tile);
+        let mut tile = Tile::new(1, 0, 2, 2);
+        for stats in tile.stats.iter_mut() {
+            stats.add(Color::rgb(1.0, 1.0, 1.0));
+        }
+        accumulator.add_tile(&tile);
+
+        assert_eq!(accumulator.samples(0, 0), 0);
+        assert_eq!(accumulator.samples(2, 1), 3);
+        let pixel = accumulator.pixel(2, 1);
+        assert!((pixel.g - 5.0 / 3.0).abs() < 1e-6);
+        assert!((pixel.b - 7.0 / 3.0).abs() < 1e-6);
+        assert_eq!(accumulator.sample_counts().get(1, 0), 3);
+
+        let options = AdaptiveOptions {
+            min_samples: 2,
+            threshold: 1.0,
+        };
+        assert_eq!(
+            accumulator.unconverged(&options),
+            [true, false, false, true, false, false]
+        );
+
+        let mut canvas = ImageCanvas::new(3, 2);
+        accumulator.draw(&mut canvas);
+        assert_eq!(canvas.pixel(0, 1).r, 0.0);
+        assert!((canvas.pixel(1, 1).g - 5.0 / 3.0).abs() < 1e-6);
+    }
+}
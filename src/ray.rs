@@ -1,6 +1,6 @@
 use crate::vector::Vector3;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     orig: Vector3,
     dir: Vector3,
@@ -0,0 +1,82 @@
+use super::{hash, hash_to_unit, pixel_hash, Sampler, ONE_MINUS_EPSILON};
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Halton sequence over the samples of a pixel. Dimension `d` uses the
+/// radical inverse in the `d`-th prime base, shifted by a random offset per
+/// pixel and dimension (Cranley-Patterson rotation) so neighbouring pixels
+/// don't share the same pattern. Dimensions past the table fall back to
+/// hashed random numbers.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel = pixel_hash(self.seed, x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let offset = hash(&[self.pixel, dimension as u64]);
+        match PRIMES.get(dimension) {
+            Some(&base) => {
+                let value = radical_inverse(base, self.index) + hash_to_unit(offset);
+                let value = if value >= 1.0 { value - 1.0 } else { value };
+                value.min(ONE_MINUS_EPSILON)
+            }
+            None => hash_to_unit(hash(&[offset, self.index as u64])),
+        }
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+/// Mirrors the digits of `index` in `base` around the decimal point.
+fn radical_inverse(base: u32, mut index: u32) -> f32 {
+    let inverse_base = 1.0 / base as f64;
+    let mut reversed = 0u64;
+    let mut inverse_power = 1.0;
+    while index > 0 {
+        let digit = index % base;
+        index /= base;
+        reversed = reversed * base as u64 + digit as u64;
+        inverse_power *= inverse_base;
+    }
+    ((reversed as f64 * inverse_power) as f32).min(ONE_MINUS_EPSILON)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn radical_inverse_mirrors_digits() {
+        assert_eq!(radical_inverse(2, 0), 0.0);
+        assert_eq!(radical_inverse(2, 1), 0.5);
+        assert_eq!(radical_inverse(2, 6), 0.375);
+        assert!((radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-6);
+    }
+}
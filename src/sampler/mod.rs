@@ -0,0 +1,299 @@
+//! Sample generators for the camera dimensions of a path.
+//!
+//! A sampler is positioned on a pixel sample with
+//! [`Sampler::start_pixel_sample`] and then hands out consecutive 1D and 2D
+//! dimensions. Like the rest of the renderer, every value only depends on the
+//! seed, the pixel and the sample index.
+
+use std::{fmt, str::FromStr};
+
+use rand::Rng;
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::util::{self, mix};
+
+mod halton;
+mod sobol;
+mod stratified;
+
+pub use halton::HaltonSampler;
+pub use sobol::SobolSampler;
+pub use stratified::StratifiedSampler;
+
+pub trait Sampler {
+    /// Moves the sampler to sample `index` of pixel (`x`, `y`) and resets the
+    /// dimension counter.
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32);
+
+    fn next_1d(&mut self) -> f32;
+
+    fn next_2d(&mut self) -> (f32, f32);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerKind {
+    /// Uniform random numbers.
+    Independent,
+    /// Jittered strata, shuffled per dimension.
+    Stratified,
+    /// Halton sequence with a per-pixel random rotation.
+    Halton,
+    /// Owen-scrambled Sobol sequence.
+    #[default]
+    Sobol,
+}
+
+impl SamplerKind {
+    /// Creates a sampler that expects `samples` samples per pixel.
+    pub fn create(self, seed: u64, samples: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(seed, samples)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!(
+                "unknown sampler `{}`, expected independent, stratified, halton or sobol",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SamplerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        // Use a different stream than the generator of the path itself.
+        let seed = mix(seed);
+        Self {
+            seed,
+            rng: util::sample_rng(seed, 0, 0, 0),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.rng = util::sample_rng(self.seed, x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        self.rng.gen()
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.rng.gen(), self.rng.gen())
+    }
+}
+
+/// Maps a point of the unit square onto the unit disk, keeping the
+/// stratification of the input (Shirley-Chiu concentric mapping).
+pub fn concentric_disk((u, v): (f32, f32)) -> (f32, f32) {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, std::f32::consts::FRAC_PI_4 * (b / a))
+    } else {
+        (
+            b,
+            std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_4 * (a / b),
+        )
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Largest float below one.
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0, |h, &v| mix(h ^ v))
+}
+
+fn pixel_hash(seed: u64, x: u32, y: u32) -> u64 {
+    hash(&[seed, ((y as u64) << 32) | x as u64])
+}
+
+/// Top 24 bits of the hash as a float in [0, 1).
+fn hash_to_unit(h: u64) -> f32 {
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Top 24 bits of a fixed point fraction as a float in [0, 1).
+fn bits_to_unit(bits: u32) -> f32 {
+    (bits >> 8) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KINDS: [SamplerKind; 4] = [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ];
+
+    #[test]
+    fn values_are_in_unit_interval() {
+        for kind in KINDS {
+            let mut sampler = kind.create(3, 16);
+            for index in 0..64 {
+                sampler.start_pixel_sample(5, 7, index);
+                for _ in 0..8 {
+                    let v = sampler.next_1d();
+                    let (a, b) = sampler.next_2d();
+                    for v in [v, a, b] {
+                        assert!((0.0..1.0).contains(&v), "{} gave {}", kind, v);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn samples_are_reproducible() {
+        for kind in KINDS {
+            let mut a = kind.create(3, 16);
+            let mut b = kind.create(3, 16);
+            b.start_pixel_sample(1, 1, 1);
+            b.next_2d();
+            a.start_pixel_sample(4, 2, 9);
+            b.start_pixel_sample(4, 2, 9);
+            for _ in 0..4 {
+                assert_eq!(a.next_2d(), b.next_2d());
+                assert_eq!(a.next_1d(), b.next_1d());
+            }
+        }
+    }
+
+    /// Checks that the first `n * n` 2D samples of a dimension fall into
+    /// different cells of an `n` x `n` grid.
+    fn stratified_2d(kind: SamplerKind, n: u32, dimension: usize) -> bool {
+        let mut sampler = kind.create(11, n * n);
+        let mut cells = vec![false; (n * n) as usize];
+        for index in 0..n * n {
+            sampler.start_pixel_sample(3, 8, index);
+            for _ in 0..dimension {
+                sampler.next_2d();
+            }
+            let (u, v) = sampler.next_2d();
+            let cell = (v * n as f32) as u32 * n + (u * n as f32) as u32;
+            if std::mem::replace(&mut cells[cell as usize], true) {
+                return false;
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn stratified_and_sobol_cover_every_stratum() {
+        for dimension in 0..3 {
+            assert!(stratified_2d(SamplerKind::Stratified, 4, dimension));
+            assert!(stratified_2d(SamplerKind::Sobol, 4, dimension));
+            assert!(stratified_2d(SamplerKind::Sobol, 8, dimension));
+        }
+    }
+
+    #[test]
+    fn halton_is_stratified_in_1d() {
+        let mut sampler = SamplerKind::Halton.create(0, 8);
+        let mut cells = [false; 8];
+        for index in 0..8 {
+            sampler.start_pixel_sample(2, 3, index);
+            let cell = (sampler.next_1d() * 8.0) as usize;
+            assert!(!std::mem::replace(&mut cells[cell], true));
+        }
+    }
+
+    /// Mean absolute error of estimating the area of a quarter disk.
+    fn quarter_disk_error(kind: SamplerKind, samples: u32) -> f32 {
+        let mut sampler = kind.create(1, samples);
+        let pixels = 64;
+        let mut error = 0.0;
+        for pixel in 0..pixels {
+            let mut inside = 0;
+            for index in 0..samples {
+                sampler.start_pixel_sample(pixel, 0, index);
+                let (u, v) = sampler.next_2d();
+                if u * u + v * v < 1.0 {
+                    inside += 1;
+                }
+            }
+            let estimate = inside as f32 / samples as f32;
+            error += (estimate - std::f32::consts::FRAC_PI_4).abs();
+        }
+        error / pixels as f32
+    }
+
+    #[test]
+    fn low_discrepancy_samplers_converge_faster() {
+        let independent = quarter_disk_error(SamplerKind::Independent, 64);
+        for kind in [
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+        ] {
+            let error = quarter_disk_error(kind, 64);
+            assert!(
+                error < independent * 0.5,
+                "{}: {} vs {}",
+                kind,
+                error,
+                independent
+            );
+        }
+    }
+
+    #[test]
+    fn concentric_disk_stays_inside() {
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (x, y) = concentric_disk((i as f32 / 10.0, j as f32 / 10.0));
+                assert!(x * x + y * y <= 1.0 + 1e-5);
+            }
+        }
+        let (x, y) = concentric_disk((1.0, 0.5));
+        assert!((x - 1.0).abs() < 1e-6 && y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn parses_names() {
+        for kind in KINDS {
+            assert_eq!(kind.to_string().parse::<SamplerKind>(), Ok(kind));
+        }
+        assert!("random".parse::<SamplerKind>().is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use super::{bits_to_unit, hash, pixel_hash, Sampler};
+
+/// Owen-scrambled Sobol points (Burley, "Practical Hash-based Owen
+/// Scrambling"). Every 2D dimension uses the first two Sobol dimensions with
+/// its own scrambling and its own shuffle of the sample order, which keeps
+/// the pairs well stratified without correlating them with each other.
+pub struct SobolSampler {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn next_seed(&mut self) -> u64 {
+        let seed = hash(&[self.pixel, self.dimension]);
+        self.dimension += 1;
+        seed
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel = pixel_hash(self.seed, x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let seed = self.next_seed();
+        let index = nested_uniform_scramble(self.index, seed as u32);
+        bits_to_unit(nested_uniform_scramble(
+            index.reverse_bits(),
+            (seed >> 32) as u32,
+        ))
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let seed = self.next_seed();
+        let index = nested_uniform_scramble(self.index, seed as u32);
+        let second = hash(&[seed]);
+        (
+            bits_to_unit(nested_uniform_scramble(
+                index.reverse_bits(),
+                (seed >> 32) as u32,
+            )),
+            bits_to_unit(nested_uniform_scramble(sobol_second(index), second as u32)),
+        )
+    }
+}
+
+/// Second dimension of the Sobol sequence, as a 32 bit fixed point fraction.
+fn sobol_second(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+/// Owen scrambling of a fixed point fraction: flips every bit depending on
+/// the bits above it.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Hash that only mixes the lower bits into the higher ones.
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sobol_matches_reference() {
+        let expected = [0.0, 0.5, 0.75, 0.25, 0.625, 0.125, 0.375, 0.875];
+        for (index, &value) in expected.iter().enumerate() {
+            assert_eq!(
+                sobol_second(index as u32) as f64 / (1u64 << 32) as f64,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn scramble_is_a_bijection_on_prefixes() {
+        let mut seen = [false; 16];
+        for i in 0..16u32 {
+            let x = nested_uniform_scramble(i << 28, 0x1234_5678) >> 28;
+            assert!(!std::mem::replace(&mut seen[x as usize], true));
+        }
+    }
+}
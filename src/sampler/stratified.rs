@@ -0,0 +1,117 @@
+use super::{hash, hash_to_unit, pixel_hash, Sampler, ONE_MINUS_EPSILON};
+
+/// Splits every dimension into `samples` jittered strata (a grid of roughly
+/// square cells for 2D) and visits them in a random order that differs per
+/// pixel and dimension, so the dimensions stay uncorrelated.
+pub struct StratifiedSampler {
+    seed: u64,
+    samples: u32,
+    columns: u32,
+    rows: u32,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples: u32) -> Self {
+        let samples = samples.max(1);
+        let columns = (samples as f32).sqrt().ceil() as u32;
+        let rows = samples.div_ceil(columns);
+        Self {
+            seed,
+            samples,
+            columns,
+            rows,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Returns the stratum of the current sample out of `count` and the hash
+    /// used for jittering inside of it.
+    fn stratum(&mut self, count: u32) -> (u32, u64) {
+        let dimension_hash = hash(&[self.pixel, self.dimension]);
+        self.dimension += 1;
+        // Samples past the expected count start another shuffled round.
+        let round = self.index / self.samples;
+        let permutation = hash(&[dimension_hash, round as u64]) as u32;
+        let stratum = permutation_element(self.index % self.samples, count, permutation);
+        (stratum, hash(&[dimension_hash, self.index as u64 + 1]))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel = pixel_hash(self.seed, x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let (stratum, jitter) = self.stratum(self.samples);
+        ((stratum as f32 + hash_to_unit(jitter)) / self.samples as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let (stratum, jitter) = self.stratum(self.columns * self.rows);
+        let (column, row) = (stratum % self.columns, stratum / self.columns);
+        let u = (column as f32 + hash_to_unit(jitter)) / self.columns as f32;
+        let v = (row as f32 + hash_to_unit(jitter << 24)) / self.rows as f32;
+        (u.min(ONE_MINUS_EPSILON), v.min(ONE_MINUS_EPSILON))
+    }
+}
+
+/// Element `i` of a random permutation of `0..count` selected by `seed`,
+/// computed without storing the permutation (Kensler, "Correlated
+/// Multi-Jittered Sampling").
+fn permutation_element(mut i: u32, count: u32, seed: u32) -> u32 {
+    let mut w = count - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < count {
+            return i.wrapping_add(seed) % count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn permutation_is_a_bijection() {
+        for count in [1, 2, 7, 16, 33] {
+            for seed in [0, 1, 0xdead_beef] {
+                let mut seen = vec![false; count as usize];
+                for i in 0..count {
+                    let element = permutation_element(i, count, seed);
+                    assert!(!std::mem::replace(&mut seen[element as usize], true));
+                }
+            }
+        }
+    }
+}
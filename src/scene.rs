@@ -1,13 +1,66 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::camera::Camera;
 use crate::{
+    aabb::Aabb,
+    bvh::Bvh,
+    color::Color,
     entity::Entity,
     hit::{HitRecord, Hittable},
+    light::{AreaLight, EnvironmentMap, Light, LightSet, Shape, Sky},
+    material::Material,
+    medium::Medium,
     ray::Ray,
+    vector::Vector3,
 };
-use crate::camera::Camera;
+
+/// Radiance of the rays that leave the scene.
+#[derive(Debug, Clone, Default)]
+pub enum Background {
+    /// Blend from white at the horizon to light blue straight up.
+    #[default]
+    Gradient,
+    Solid(Color),
+    /// Sampled as one of the lights of the scene.
+    Environment(Arc<EnvironmentMap>),
+    /// Sampled as two lights of the scene, the sky and the sun.
+    Sky(Arc<Sky>),
+}
+
+impl Background {
+    /// Whether the background is one of the lights of the scene, whose
+    /// radiance reaches the integrator through [`LightSet::escaped`].
+    pub fn is_light(&self) -> bool {
+        matches!(self, Background::Environment(_) | Background::Sky(_))
+    }
+
+    pub fn radiance(&self, ray: &Ray) -> Color {
+        match *self {
+            Background::Gradient => {
+                let dir = ray.direction().normalized();
+                let t = 0.5 * (dir.y + 1.0);
+                let v = (1.0 - t) * Vector3::xyz(1.0, 1.0, 1.0) + t * Vector3::xyz(0.5, 0.7, 1.0);
+                Color::rgb(v.x, v.y, v.z)
+            }
+            Background::Solid(color) => color,
+            Background::Environment(ref map) => map.radiance(ray.direction()),
+            Background::Sky(ref sky) => {
+                sky.radiance(ray.direction()) + Light::Directional(sky.sun()).escaped(ray)
+            }
+        }
+    }
+}
 
 pub struct Scene {
     pub camera: Camera,
+    pub background: Background,
+    /// Medium filling the space outside of the entities. The camera is
+    /// assumed to be in it rather than inside any entity.
+    pub fog: Option<Medium>,
     entities: Vec<Entity>,
+    lights: Vec<Light>,
+    bvh: OnceLock<Bvh>,
+    light_set: OnceLock<LightSet>,
 }
 
 impl Scene {
@@ -15,25 +68,177 @@ impl Scene {
         Self {
             entities: Vec::new(),
             camera,
+            background: Background::default(),
+            fog: None,
+            lights: Vec::new(),
+            bvh: OnceLock::new(),
+            light_set: OnceLock::new(),
         }
     }
 
     pub fn add(&mut self, entity: Entity) {
         self.entities.push(entity);
+        self.bvh = OnceLock::new();
+        self.light_set = OnceLock::new();
     }
-}
 
-impl Hittable for Scene {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    /// Adds a light that isn't part of the geometry. Area lights added this
+    /// way are still visible to camera and scattered rays, see
+    /// [`Scene::hit_light`].
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+        self.light_set = OnceLock::new();
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Acceleration structure over the entities. It is built on first use
+    /// and dropped whenever the scene changes.
+    pub fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds = self
+                .entities
+                .iter()
+                .map(|e| e.bounding_box())
+                .collect::<Vec<_>>();
+            Bvh::build(&bounds)
+        })
+    }
+
+    /// The lights added to the scene together with area lights for the
+    /// entities with an emissive material and the sky or environment map,
+    /// collected on first use like the acceleration structure. The lights
+    /// added to the scene keep their indices.
+    pub fn lights(&self) -> &LightSet {
+        self.light_set.get_or_init(|| {
+            let mut lights = self.lights.clone();
+            match self.background {
+                Background::Environment(ref map) => lights.push(Light::Environment(map.clone())),
+                Background::Sky(ref sky) => {
+                    lights.push(Light::Sky(sky.clone()));
+                    lights.push(Light::Directional(sky.sun()));
+                }
+                _ => {}
+            }
+            let mut entities = Vec::with_capacity(self.entities.len());
+            for entity in self.entities.iter() {
+                let first = lights.len();
+                let mut add = |shape, material: &Material| {
+                    if let Material::DiffuseLight(light) = material {
+                        lights.push(Light::Area(AreaLight::new(shape, light.radiance())));
+                    }
+                };
+                match entity {
+                    Entity::Sphere(sphere) => add(
+                        Shape::Sphere {
+                            center: sphere.center(),
+                            radius: sphere.radius(),
+                        },
+                        sphere.material(),
+                    ),
+                    Entity::Triangle(triangle) => {
+                        add(Shape::Triangle(*triangle.vertices()), triangle.material())
+                    }
+                    Entity::Mesh(mesh) => {
+                        let positions = mesh.positions();
+                        for face in mesh.faces() {
+                            let [a, b, c] = face.positions;
+                            add(
+                                Shape::Triangle([positions[a], positions[b], positions[c]]),
+                                mesh.material(),
+                            );
+                        }
+                    }
+                }
+                entities.push(if lights.len() > first {
+                    Some(first)
+                } else {
+                    None
+                });
+            }
+            let bounds = self.bvh().bounding_box();
+            let radius = if bounds.is_empty() {
+                0.0
+            } else {
+                bounds.extent().length() / 2.0
+            };
+            LightSet::new(lights, radius).with_entities(entities)
+        })
+    }
+
+    /// Closest intersection with one of the area lights added with
+    /// [`Scene::add_light`], with the index of the light and the radiance
+    /// it emits back along `ray`.
+    pub fn hit_light(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(usize, f32, Color)> {
+        let mut result = None;
+        let mut t_closest = t_max;
+        for (index, light) in self.lights.iter().enumerate() {
+            if let Some((t, radiance)) = light.hit(ray, t_min, t_closest) {
+                t_closest = t;
+                result = Some((index, t, radiance));
+            }
+        }
+        result
+    }
+
+    /// Fraction of the light that travels along `ray` up to `t_max`, starting
+    /// in `medium`. Rays pass through interfaces between media and are
+    /// blocked by every other surface.
+    pub fn transmittance(&self, ray: &Ray, t_max: f32, medium: Option<&Medium>) -> Color {
+        let mut transmittance = Color::rgb(1.0, 1.0, 1.0);
+        let mut medium = medium;
+        let mut t_min = 0.001;
+        let length = ray.direction().length();
+        loop {
+            let hit = self.hit(ray, t_min, t_max);
+            let t = hit.as_ref().map_or(t_max, |hit| hit.t);
+            if let Some(medium) = medium {
+                transmittance = transmittance * medium.transmittance((t - t_min) * length);
+            }
+            match hit {
+                None => return transmittance,
+                Some(hit) if matches!(hit.material, Material::Interface) => {
+                    medium = hit.medium_towards(ray.direction(), medium, self.fog.as_ref());
+                    t_min = t + 0.001;
+                }
+                Some(_) => return Color::rgba(0.0, 0.0, 0.0, 0.0),
+            }
+        }
+    }
+
+    /// Intersects every entity without the acceleration structure.
+    pub fn hit_brute_force(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let mut result: Option<HitRecord> = None;
         let mut t_closest = t_max;
-        self.entities.iter().for_each(|e| {
+        self.entities.iter().enumerate().for_each(|(index, e)| {
             if let Some(hit) = e.hit(ray, t_min, t_closest) {
                 t_closest = hit.t;
-                result = Some(hit);
+                result = Some(HitRecord {
+                    entity: index,
+                    ..hit
+                });
             }
         });
 
         result
     }
 }
+
+impl Hittable for Scene {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.bvh()
+            .hit(ray, t_min, t_max, |index, ray, t_min, t_max| {
+                let hit = self.entities[index].hit(ray, t_min, t_max)?;
+                Some(HitRecord {
+                    entity: index,
+                    ..hit
+                })
+            })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh().bounding_box()
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    canvas::{Canvas, ImageCanvas},
+    color::Color,
+    output::hdr,
+};
+
+use super::WrapMode;
+
+/// Image looked up with bilinear filtering. `v` grows from the bottom of the
+/// image to the top.
+#[derive(Clone)]
+pub struct ImageTexture {
+    image: Arc<ImageCanvas>,
+    wrap: WrapMode,
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<ImageCanvas>, wrap: WrapMode) -> Self {
+        Self { image, wrap }
+    }
+
+    pub fn value(&self, (u, v): (f32, f32)) -> Color {
+        let width = self.image.width() as usize;
+        let height = self.image.height() as usize;
+        if width == 0 || height == 0 {
+            return Color::rgb(0.0, 0.0, 0.0);
+        }
+
+        // Texel centers are at half-integer coordinates.
+        let x = u * width as f32 - 0.5;
+        let y = (1.0 - v) * height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let texel = |dx: i64, dy: i64| {
+            let x = self.wrap.apply(x0 as i64 + dx, width);
+            let y = self.wrap.apply(y0 as i64 + dy, height);
+            self.image.pixel(x as u32, y as u32)
+        };
+
+        (1.0 - fy) * ((1.0 - fx) * texel(0, 0) + fx * texel(1, 0))
+            + fy * ((1.0 - fx) * texel(0, 1) + fx * texel(1, 1))
+    }
+}
+
+impl fmt::Debug for ImageTexture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageTexture")
+            .field("width", &self.image.width())
+            .field("height", &self.image.height())
+            .field("wrap", &self.wrap)
+            .finish()
+    }
+}
+
+/// Loads a PNG, JPEG or Radiance HDR image in linear color, chosen by the
+/// extension. 8 and 16-bit images are assumed to be sRGB encoded, alpha is
+/// dropped.
+pub fn load_image<P: AsRef<Path>>(path: P) -> io::Result<ImageCanvas> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "png" => load_png(path),
+        "jpg" | "jpeg" => load_jpeg(path),
+        "hdr" => hdr::load(path),
+        _ => Err(invalid_data(format!(
+            "unsupported image format: {}",
+            path.display()
+        ))),
+    }
+}
+
+fn load_png(path: &Path) -> io::Result<ImageCanvas> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let (info, mut reader) = decoder
+        .read_info()
+        .map_err(|e| invalid_data(e.to_string()))?;
+    let mut data = vec![0; info.buffer_size()];
+    reader
+        .next_frame(&mut data)
+        .map_err(|e| invalid_data(e.to_string()))?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::RGB => 3,
+        png::ColorType::RGBA => 4,
+        png::ColorType::Indexed => {
+            return Err(invalid_data("unexpanded palette".to_string()));
+        }
+    };
+    let samples = match info.bit_depth {
+        png::BitDepth::Sixteen => data
+            .chunks(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as f32 / 65535.0)
+            .collect::<Vec<_>>(),
+        _ => data.iter().map(|b| *b as f32 / 255.0).collect(),
+    };
+    Ok(to_canvas(info.width, info.height, channels, &samples))
+}
+
+fn load_jpeg(path: &Path) -> io::Result<ImageCanvas> {
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(File::open(path)?));
+    let data = decoder.decode().map_err(|e| invalid_data(e.to_string()))?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| invalid_data("missing image information".to_string()))?;
+    let channels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => 1,
+        jpeg_decoder::PixelFormat::RGB24 => 3,
+        jpeg_decoder::PixelFormat::CMYK32 => {
+            return Err(invalid_data("CMYK images are not supported".to_string()));
+        }
+    };
+    let samples = data.iter().map(|b| *b as f32 / 255.0).collect::<Vec<_>>();
+    Ok(to_canvas(
+        info.width as u32,
+        info.height as u32,
+        channels,
+        &samples,
+    ))
+}
+
+/// Builds a linear image from interleaved sRGB samples, gray with an
+/// optional alpha or color with an optional alpha.
+fn to_canvas(width: u32, height: u32, channels: usize, samples: &[f32]) -> ImageCanvas {
+    let mut image = ImageCanvas::new(width, height);
+    for (index, pixel) in samples.chunks(channels).enumerate() {
+        let (r, g, b) = if channels < 3 {
+            (pixel[0], pixel[0], pixel[0])
+        } else {
+            (pixel[0], pixel[1], pixel[2])
+        };
+        let color = Color::rgb(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+        let index = index as u32;
+        image.draw_point(&color, index % width, index / width);
+    }
+    image
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::output::{self, OutputOptions};
+
+    fn texture(wrap: WrapMode) -> ImageTexture {
+        // Black on the left, white on the right.
+        let mut image = ImageCanvas::new(2, 2);
+        for y in 0..2 {
+            image.draw_point(&Color::rgb(1.0, 1.0, 1.0), 1, y);
+        }
+        ImageTexture::new(Arc::new(image), wrap)
+    }
+
+    #[test]
+    fn bilinear_filtering() {
+        let texture = texture(WrapMode::Clamp);
+        assert_eq!(texture.value((0.25, 0.5)).r, 0.0);
+        assert_eq!(texture.value((0.75, 0.5)).r, 1.0);
+        assert_eq!(texture.value((0.5, 0.5)).r, 0.5);
+        assert_eq!(texture.value((0.375, 0.9)).r, 0.25);
+        assert_eq!(texture.value((1.5, 0.5)).r, 1.0);
+
+        // Repeating blends the right edge with the left one.
+        let texture = self::texture(WrapMode::Repeat);
+        assert_eq!(texture.value((0.0, 0.5)).r, 0.5);
+        assert_eq!(texture.value((1.25, 0.5)).r, 0.0);
+    }
+
+    #[test]
+    fn loads_png_as_linear() {
+        let directory = std::env::temp_dir().join("raytracer-texture-test");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("gray.png");
+        let mut image = ImageCanvas::new(3, 2);
+        image.draw_point(&Color::rgb(0.214, 0.5, 1.0), 2, 1);
+        output::save(&path, &image, &OutputOptions::default()).unwrap();
+
+        let loaded = load_image(&path).unwrap();
+        assert_eq!((loaded.width(), loaded.height()), (3, 2));
+        let pixel = loaded.pixel(2, 1);
+        assert!((pixel.r - 0.214).abs() < 0.005, "{}", pixel.r);
+        assert!((pixel.g - 0.5).abs() < 0.005, "{}", pixel.g);
+        assert_eq!(pixel.b, 1.0);
+        assert_eq!(loaded.pixel(0, 0).r, 0.0);
+
+        let error = load_image(directory.join("gray.tga")).err().unwrap();
+        assert!(error.to_string().starts_with("unsupported image format"));
+    }
+}
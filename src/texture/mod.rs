@@ -0,0 +1,218 @@
+//! Colors that vary over surfaces.
+//!
+//! Textures are evaluated at the surface coordinates and the position of a
+//! hit. Nested textures are shared with `Arc` so materials stay cheap to
+//! clone.
+
+use std::{str::FromStr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{color::Color, vector::Vector3};
+
+mod image;
+mod noise;
+
+pub use self::image::{load_image, ImageTexture};
+pub use self::noise::{Noise, NoisePattern};
+
+#[derive(Debug, Clone)]
+pub enum Texture {
+    Constant(Color),
+    /// Alternates between two textures in a 3D grid of cubes with sides of
+    /// `size`.
+    Checker {
+        even: Arc<Texture>,
+        odd: Arc<Texture>,
+        size: f32,
+    },
+    Image(ImageTexture),
+    /// Looks up `texture` at the coordinates multiplied by `scale` and moved
+    /// by `offset`. The first two components apply to the surface
+    /// coordinates, all three to the position.
+    Transform {
+        texture: Arc<Texture>,
+        scale: Vector3,
+        offset: Vector3,
+    },
+    /// Blends from `low` to `high` by a noise pattern evaluated at the
+    /// position multiplied by `scale`.
+    Noise {
+        noise: Arc<Noise>,
+        pattern: NoisePattern,
+        scale: f32,
+        octaves: u32,
+        low: Arc<Texture>,
+        high: Arc<Texture>,
+    },
+}
+
+impl Texture {
+    pub fn value(&self, uv: (f32, f32), point: Vector3) -> Color {
+        match *self {
+            Texture::Constant(color) => color,
+            Texture::Checker {
+                ref even,
+                ref odd,
+                size,
+            } => {
+                let cell = |x: f32| (x / size).floor() as i64;
+                if (cell(point.x) + cell(point.y) + cell(point.z)).rem_euclid(2) == 0 {
+                    even.value(uv, point)
+                } else {
+                    odd.value(uv, point)
+                }
+            }
+            Texture::Image(ref image) => image.value(uv),
+            Texture::Transform {
+                ref texture,
+                scale,
+                offset,
+            } => {
+                let uv = (uv.0 * scale.x + offset.x, uv.1 * scale.y + offset.y);
+                let point = Vector3::xyz(
+                    point.x * scale.x + offset.x,
+                    point.y * scale.y + offset.y,
+                    point.z * scale.z + offset.z,
+                );
+                texture.value(uv, point)
+            }
+            Texture::Noise {
+                ref noise,
+                pattern,
+                scale,
+                octaves,
+                ref low,
+                ref high,
+            } => {
+                let t = pattern.value(noise, scale * point, octaves);
+                (1.0 - t) * low.value(uv, point) + t * high.value(uv, point)
+            }
+        }
+    }
+
+    /// Value for parameters that are a single number, such as roughness.
+    pub fn scalar(&self, uv: (f32, f32), point: Vector3) -> f32 {
+        self.value(uv, point).luminance()
+    }
+}
+
+impl From<Color> for Texture {
+    fn from(color: Color) -> Self {
+        Texture::Constant(color)
+    }
+}
+
+impl From<f32> for Texture {
+    fn from(value: f32) -> Self {
+        Texture::Constant(Color::rgb(value, value, value))
+    }
+}
+
+/// How image textures are continued outside of [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapMode {
+    /// Maps a texel index onto `0..size`.
+    fn apply(&self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let index = match *self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::Clamp => index.clamp(0, size - 1),
+            WrapMode::Mirror => {
+                let period = index.rem_euclid(2 * size);
+                if period < size {
+                    period
+                } else {
+                    2 * size - 1 - period
+                }
+            }
+        };
+        index as usize
+    }
+}
+
+impl FromStr for WrapMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "repeat" => Ok(WrapMode::Repeat),
+            "clamp" => Ok(WrapMode::Clamp),
+            "mirror" => Ok(WrapMode::Mirror),
+            _ => Err(format!("unknown wrap mode: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checker() {
+        let texture = Texture::Checker {
+            even: Arc::new(Color::rgb(1.0, 1.0, 1.0).into()),
+            odd: Arc::new(Color::rgb(0.0, 0.0, 0.0).into()),
+            size: 0.5,
+        };
+        let at = |x, y, z| texture.value((0.0, 0.0), Vector3::xyz(x, y, z)).r;
+
+        assert_eq!(at(0.1, 0.1, 0.1), 1.0);
+        assert_eq!(at(0.6, 0.1, 0.1), 0.0);
+        assert_eq!(at(0.6, 0.6, 0.1), 1.0);
+        assert_eq!(at(-0.1, 0.1, 0.1), 0.0);
+    }
+
+    #[test]
+    fn transform() {
+        let checker = Texture::Checker {
+            even: Arc::new(Color::rgb(1.0, 1.0, 1.0).into()),
+            odd: Arc::new(Color::rgb(0.0, 0.0, 0.0).into()),
+            size: 1.0,
+        };
+        let texture = Texture::Transform {
+            texture: Arc::new(checker),
+            scale: Vector3::xyz(2.0, 2.0, 2.0),
+            offset: Vector3::xyz(0.5, 0.0, 0.0),
+        };
+        let at = |x| texture.value((0.0, 0.0), Vector3::xyz(x, 0.0, 0.0)).r;
+
+        assert_eq!(at(0.1), 1.0);
+        assert_eq!(at(0.3), 0.0);
+        assert_eq!(at(0.8), 1.0);
+    }
+
+    #[test]
+    fn noise_blends_between_textures() {
+        let texture = Texture::Noise {
+            noise: Arc::new(Noise::new(5)),
+            pattern: NoisePattern::Marble,
+            scale: 3.0,
+            octaves: 4,
+            low: Arc::new(0.2.into()),
+            high: Arc::new(0.6.into()),
+        };
+        let values = (0..100)
+            .map(|i| texture.scalar((0.0, 0.0), Vector3::xyz(0.05 * i as f32, 0.3, 0.1)))
+            .collect::<Vec<_>>();
+
+        assert!(values.iter().all(|v| (0.2 - 1e-5..=0.6 + 1e-5).contains(v)));
+        assert!(values.iter().any(|v| *v < 0.3) && values.iter().any(|v| *v > 0.5));
+    }
+
+    #[test]
+    fn wrap_modes() {
+        let wrap = |mode: WrapMode| (-3..6).map(|i| mode.apply(i, 3)).collect::<Vec<_>>();
+        assert_eq!(wrap(WrapMode::Repeat), vec![0, 1, 2, 0, 1, 2, 0, 1, 2]);
+        assert_eq!(wrap(WrapMode::Clamp), vec![0, 0, 0, 0, 1, 2, 2, 2, 2]);
+        assert_eq!(wrap(WrapMode::Mirror), vec![2, 1, 0, 0, 1, 2, 2, 1, 0]);
+    }
+}
@@ -0,0 +1,218 @@
+use rand::Rng;
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::{util::mix, vector::Vector3};
+
+/// Gradient noise (Perlin, 2002) with a permutation table shuffled from a
+/// seed, so the same seed always gives the same pattern.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    permutation: Vec<u8>,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Pcg32::new(mix(seed), 0);
+        let mut table = (0..=255).collect::<Vec<u8>>();
+        for i in (1..table.len()).rev() {
+            table.swap(i, rng.gen_range(0, i + 1));
+        }
+        // Doubled so that the hashes of neighbouring lattice points never
+        // wrap around.
+        let permutation = table.iter().chain(table.iter()).copied().collect();
+
+        Self { permutation }
+    }
+
+    /// Noise in about [-1, 1], zero at the integer lattice points.
+    pub fn noise(&self, p: Vector3) -> f32 {
+        let (fx, fy, fz) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (x, y, z) = (p.x - fx, p.y - fy, p.z - fz);
+        let cell = |f: f32| (f as i64 & 255) as usize;
+        let (xi, yi, zi) = (cell(fx), cell(fy), cell(fz));
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let perm = &self.permutation;
+        let hash = |i: usize| perm[i] as usize;
+        let a = hash(xi) + yi;
+        let b = hash(xi + 1) + yi;
+        let (aa, ab) = (hash(a) + zi, hash(a + 1) + zi);
+        let (ba, bb) = (hash(b) + zi, hash(b + 1) + zi);
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(perm[aa], x, y, z), grad(perm[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(perm[ab], x, y - 1.0, z),
+                    grad(perm[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(perm[aa + 1], x, y, z - 1.0),
+                    grad(perm[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(perm[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(perm[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Fractal Brownian motion: `octaves` layers of noise, each at twice the
+    /// frequency and half the amplitude of the previous one. The result is
+    /// normalized to about [-1, 1].
+    pub fn fbm(&self, p: Vector3, octaves: u32) -> f32 {
+        self.octaves(p, octaves, |n| n)
+    }
+
+    /// Like [`Noise::fbm`] but summing the absolute value of every octave,
+    /// which gives creases where the noise crosses zero. In [0, 1].
+    pub fn turbulence(&self, p: Vector3, octaves: u32) -> f32 {
+        self.octaves(p, octaves, f32::abs)
+    }
+
+    fn octaves<F: Fn(f32) -> f32>(&self, p: Vector3, octaves: u32, f: F) -> f32 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * f(self.noise(frequency * p));
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / total
+    }
+}
+
+/// Patterns built from noise, all in [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoisePattern {
+    #[default]
+    Fbm,
+    Turbulence,
+    /// Veins along the x axis, distorted by turbulence.
+    Marble,
+    /// Rings around the y axis, distorted by noise.
+    Wood,
+    /// Fine speckles of high contrast.
+    Granite,
+}
+
+impl NoisePattern {
+    pub fn value(&self, noise: &Noise, p: Vector3, octaves: u32) -> f32 {
+        let t = match *self {
+            NoisePattern::Fbm => 0.5 + 0.5 * noise.fbm(p, octaves),
+            NoisePattern::Turbulence => noise.turbulence(p, octaves),
+            NoisePattern::Marble => 0.5 + 0.5 * (p.x + 10.0 * noise.turbulence(p, octaves)).sin(),
+            NoisePattern::Wood => {
+                let rings = 4.0 * (p.x * p.x + p.z * p.z).sqrt() + noise.fbm(p, octaves);
+                rings - rings.floor()
+            }
+            NoisePattern::Granite => {
+                let t = 0.5 + 0.5 * noise.fbm(4.0 * p, octaves);
+                smoothstep(0.45, 0.55, t)
+            }
+        };
+        t.clamp(0.0, 1.0)
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Dot product with one of the 12 directions to the edges of a cube,
+/// chosen by the hash.
+fn grad(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn points() -> impl Iterator<Item = Vector3> {
+        (0..1000).map(|i| {
+            let i = i as f32;
+            Vector3::xyz(0.37 * i - 50.0, (0.71 * i).sin() * 20.0, 0.13 * i)
+        })
+    }
+
+    #[test]
+    fn deterministic() {
+        let p = Vector3::xyz(1.3, -2.7, 0.4);
+        assert_eq!(Noise::new(7).noise(p), Noise::new(7).noise(p));
+        assert_ne!(Noise::new(7).noise(p), Noise::new(8).noise(p));
+    }
+
+    #[test]
+    fn zero_on_the_lattice() {
+        let noise = Noise::new(0);
+        assert_eq!(noise.noise(Vector3::xyz(3.0, -5.0, 12.0)), 0.0);
+    }
+
+    #[test]
+    fn bounded_and_continuous() {
+        let noise = Noise::new(3);
+        let delta = Vector3::xyz(1e-3, 1e-3, 1e-3);
+        for p in points() {
+            let n = noise.noise(p);
+            assert!(n.abs() <= 1.1, "{}", n);
+            assert!((noise.noise(p + delta) - n).abs() < 0.02);
+            assert!(noise.fbm(p, 6).abs() <= 1.1);
+            assert!((0.0..=1.0).contains(&noise.turbulence(p, 6)));
+        }
+    }
+
+    #[test]
+    fn patterns_cover_the_unit_interval() {
+        let noise = Noise::new(11);
+        for pattern in [
+            NoisePattern::Fbm,
+            NoisePattern::Turbulence,
+            NoisePattern::Marble,
+            NoisePattern::Wood,
+            NoisePattern::Granite,
+        ]
+        .iter()
+        {
+            let values = points()
+                .map(|p| pattern.value(&noise, p, 4))
+                .collect::<Vec<_>>();
+            let min = values.iter().cloned().fold(f32::INFINITY, f32::min);
+            let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+            assert!(min >= 0.0 && max <= 1.0);
+            assert!(max - min > 0.3, "{:?}: {} to {}", pattern, min, max);
+        }
+    }
+}
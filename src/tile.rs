@@ -1,8 +1,53 @@
-use crate::color::Color;
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{adaptive::PixelStats, color::Color};
+
+/// Order in which the tiles are handed out to the workers.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TileOrder {
+    /// Row by row, from the top left corner.
+    #[default]
+    Scanline,
+    /// Along a Hilbert curve, which keeps consecutive tiles close together.
+    Hilbert,
+    /// Outwards from the center of the image.
+    Spiral,
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scanline" => Ok(TileOrder::Scanline),
+            "hilbert" => Ok(TileOrder::Hilbert),
+            "spiral" => Ok(TileOrder::Spiral),
+            _ => Err(format!(
+                "unknown tile order `{}`, expected scanline, hilbert or spiral",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TileOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TileOrder::Scanline => "scanline",
+            TileOrder::Hilbert => "hilbert",
+            TileOrder::Spiral => "spiral",
+        };
+        write!(f, "{}", name)
+    }
+}
 
 pub struct TileConfig {
     pub width: u32,
     pub height: u32,
+    pub order: TileOrder,
 }
 
 impl TileConfig {
@@ -10,8 +55,14 @@ impl TileConfig {
         Self {
             width,
             height,
+            order: TileOrder::default(),
         }
     }
+
+    pub fn with_order(mut self, order: TileOrder) -> Self {
+        self.order = order;
+        self
+    }
 }
 
 pub struct Tile {
@@ -19,7 +70,10 @@ pub struct Tile {
     pub y: u32,
     pub width: u32,
     pub height: u32,
+    /// Final colors, column by column.
     pub data: Vec<Color>,
+    /// Samples behind every color, in the same order as `data`.
+    pub stats: Vec<PixelStats>,
 }
 
 impl Tile {
@@ -31,21 +85,212 @@ impl Tile {
             width,
             height,
             data: vec![Color::default(); capacity],
+            stats: vec![PixelStats::default(); capacity],
+        }
+    }
+}
+
+/// Splits the surface into tiles of the configured size. Tiles on the right
+/// and the bottom edges are clipped to the surface, so every pixel belongs to
+/// exactly one tile.
+pub fn split_surface(width: u32, height: u32, config: &TileConfig) -> Vec<Tile> {
+    let tile_width = config.width.max(1);
+    let tile_height = config.height.max(1);
+    let columns = width.div_ceil(tile_width);
+    let rows = height.div_ceil(tile_height);
+
+    let cells = match config.order {
+        TileOrder::Scanline => scanline(columns, rows),
+        TileOrder::Hilbert => hilbert(columns, rows),
+        TileOrder::Spiral => spiral(columns, rows),
+    };
+
+    cells
+        .into_iter()
+        .map(|(column, row)| {
+            let x = column * tile_width;
+            let y = row * tile_height;
+            Tile::new(x, y, tile_width.min(width - x), tile_height.min(height - y))
+        })
+        .collect()
+}
+
+fn scanline(columns: u32, rows: u32) -> Vec<(u32, u32)> {
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect()
+}
+
+/// Walks a Hilbert curve over the smallest power of two square that covers
+/// the grid and keeps the cells inside of it.
+fn hilbert(columns: u32, rows: u32) -> Vec<(u32, u32)> {
+    let size = columns.max(rows).next_power_of_two();
+    let count = size as u64 * size as u64;
+    (0..count)
+        .map(|d| hilbert_cell(size, d))
+        .filter(|&(column, row)| column < columns && row < rows)
+        .collect()
+}
+
+/// Converts a distance along the Hilbert curve into a cell of a
+/// `size` x `size` grid.
+fn hilbert_cell(size: u32, d: u64) -> (u32, u32) {
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut t = d;
+    let mut s = 1u64;
+    while s < size as u64 {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x as u32, y as u32)
+}
+
+/// Square spiral that starts in the central cell and skips the cells
+/// outside of the grid.
+fn spiral(columns: u32, rows: u32) -> Vec<(u32, u32)> {
+    let total = (columns * rows) as usize;
+    let mut cells = Vec::with_capacity(total);
+    let (mut x, mut y) = (((columns as i64) - 1) / 2, ((rows as i64) - 1) / 2);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut direction = 0;
+    let mut length = 1;
+
+    let visit = |x: i64, y: i64, cells: &mut Vec<(u32, u32)>| {
+        if x >= 0 && y >= 0 && x < columns as i64 && y < rows as i64 {
+            cells.push((x as u32, y as u32));
+        }
+    };
+
+    if total > 0 {
+        visit(x, y, &mut cells);
+    }
+    while cells.len() < total {
+        for _ in 0..2 {
+            let (dx, dy) = directions[direction];
+            for _ in 0..length {
+                x += dx;
+                y += dy;
+                visit(x, y, &mut cells);
+            }
+            direction = (direction + 1) % 4;
         }
+        length += 1;
     }
+
+    cells
 }
 
-pub fn split_surface(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Vec<Tile> {
-    let tiles_col = width / tile_width;
-    let tiles_row = height / tile_height;
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ORDERS: [TileOrder; 3] = [TileOrder::Scanline, TileOrder::Hilbert, TileOrder::Spiral];
 
-    let mut tiles = Vec::with_capacity((tiles_col * tiles_row) as usize);
-    for x in 0..tiles_col {
-        for y in 0..tiles_row {
-            let tile = Tile::new(x * tile_width, y * tile_height, tile_width, tile_height);
-            tiles.push(tile);
+    fn coverage(width: u32, height: u32, config: &TileConfig) -> Vec<u32> {
+        let mut coverage = vec![0; (width * height) as usize];
+        for tile in split_surface(width, height, config) {
+            assert!(tile.width > 0 && tile.height > 0);
+            assert!(tile.width <= config.width && tile.height <= config.height);
+            assert_eq!(tile.data.len(), (tile.width * tile.height) as usize);
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    coverage[(y * width + x) as usize] += 1;
+                }
+            }
         }
+        coverage
     }
 
-    tiles
-}
\ No newline at end of file
+    #[test]
+    fn every_pixel_is_covered_once() {
+        let sizes = [(1, 1), (7, 3), (64, 64), (100, 37), (640, 360), (333, 251)];
+        let tiles = [(1, 1), (8, 8), (16, 9), (128, 72), (50, 300), (1000, 1000)];
+        for &(width, height) in sizes.iter() {
+            for &(tile_width, tile_height) in tiles.iter() {
+                for &order in ORDERS.iter() {
+                    let config = TileConfig::new(tile_width, tile_height).with_order(order);
+                    let coverage = coverage(width, height, &config);
+                    assert!(
+                        coverage.iter().all(|&c| c == 1),
+                        "{}x{} with {}x{} {} tiles",
+                        width,
+                        height,
+                        tile_width,
+                        tile_height,
+                        order
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn edge_tiles_are_clipped() {
+        let tiles = split_surface(10, 7, &TileConfig::new(4, 4));
+
+        let sizes = tiles
+            .iter()
+            .map(|t| (t.x, t.y, t.width, t.height))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            vec![
+                (0, 0, 4, 4),
+                (4, 0, 4, 4),
+                (8, 0, 2, 4),
+                (0, 4, 4, 3),
+                (4, 4, 4, 3),
+                (8, 4, 2, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_surface() {
+        for &order in ORDERS.iter() {
+            let config = TileConfig::new(16, 16).with_order(order);
+            assert!(split_surface(0, 10, &config).is_empty());
+            assert!(split_surface(10, 0, &config).is_empty());
+        }
+    }
+
+    #[test]
+    fn hilbert_tiles_are_adjacent() {
+        let config = TileConfig::new(1, 1).with_order(TileOrder::Hilbert);
+        let tiles = split_surface(16, 16, &config);
+        for pair in tiles.windows(2) {
+            let dx = (pair[0].x as i32 - pair[1].x as i32).abs();
+            let dy = (pair[0].y as i32 - pair[1].y as i32).abs();
+            assert_eq!(dx + dy, 1);
+        }
+    }
+
+    #[test]
+    fn spiral_starts_in_the_center() {
+        let config = TileConfig::new(10, 10).with_order(TileOrder::Spiral);
+        let tiles = split_surface(50, 30, &config);
+
+        assert_eq!((tiles[0].x, tiles[0].y), (20, 10));
+        assert_eq!((tiles[1].x, tiles[1].y), (30, 10));
+        assert_eq!((tiles[2].x, tiles[2].y), (30, 20));
+    }
+
+    #[test]
+    fn parse_order() {
+        for &order in ORDERS.iter() {
+            assert_eq!(order.to_string().parse::<TileOrder>(), Ok(order));
+        }
+        assert!("zigzag".parse::<TileOrder>().is_err());
+    }
+}
@@ -1,27 +1,44 @@
 use rand::Rng;
+use rand_pcg::Pcg32;
 
 pub trait Random {
-    fn random() -> Self;
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self;
 }
 
 impl Random for f32 {
-    fn random() -> Self {
-        let mut rng = rand::thread_rng();
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
         rng.gen()
     }
 }
 
 pub trait RandomRange<T = Self> {
-    fn random_range(min: T, max: T) -> Self;
+    fn random_range<R: Rng + ?Sized>(rng: &mut R, min: T, max: T) -> Self;
 }
 
 impl RandomRange for f32 {
-    fn random_range(min: Self, max: Self) -> Self {
-        let mut rng = rand::thread_rng();
+    fn random_range<R: Rng + ?Sized>(rng: &mut R, min: Self, max: Self) -> Self {
         rng.gen_range(min, max)
     }
 }
 
+/// Generator for a single camera sample. It only depends on the global seed,
+/// the pixel and the index of the sample, so images don't depend on the
+/// number of threads or the order of the tiles. All of them are hashed into
+/// the state: PCG streams that share a state are correlated.
+pub fn sample_rng(seed: u64, x: u32, y: u32, sample: u32) -> Pcg32 {
+    let pixel = ((y as u64) << 32) | x as u64;
+    let state = mix(mix(mix(seed) ^ pixel) ^ sample as u64);
+    Pcg32::new(state, 0)
+}
+
+/// SplitMix64 finalizer.
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 pub fn deg_to_rad(degrees: f32) -> f32 {
     degrees * std::f32::consts::PI / 180.0
 }
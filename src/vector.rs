@@ -36,25 +36,54 @@ impl Vector3 {
         r
     }
 
-    pub fn random_in_unit_sphere() -> Vector3 {
+    pub fn min(&self, other: Vector3) -> Vector3 {
+        Vector3::xyz(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    pub fn max(&self, other: Vector3) -> Vector3 {
+        Vector3::xyz(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
+    }
+
+    /// Two unit vectors that together with this unit vector form a right
+    /// handed orthonormal basis (Duff et al., "Building an Orthonormal
+    /// Basis, Revisited").
+    pub fn basis(&self) -> (Vector3, Vector3) {
+        let sign = 1f32.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+        (
+            Vector3::xyz(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Vector3::xyz(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
+
+    pub fn random_in_unit_sphere<R: Rng + ?Sized>(rng: &mut R) -> Vector3 {
         loop {
-            let p = Vector3::random_range(-1.0, 1.0);
+            let p = Vector3::random_range(rng, -1.0, 1.0);
             if p.squared_length() <= 1.0 {
                 return p;
             }
         }
     }
 
-    pub fn random_unit_vector() -> Vector3 {
-        let a = f32::random_range(0.0, std::f32::consts::PI * 2.0);
-        let z = f32::random_range(-1.0, 1.0);
+    pub fn random_unit_vector<R: Rng + ?Sized>(rng: &mut R) -> Vector3 {
+        let a = f32::random_range(rng, 0.0, std::f32::consts::PI * 2.0);
+        let z = f32::random_range(rng, -1.0, 1.0);
         let r = (1.0 - z * z).sqrt();
 
         Vector3::xyz(r * a.cos(), r * a.sin(), z)
     }
 
-    pub fn random_in_hemisphere(normal: Vector3) -> Vector3 {
-        let v = Vector3::random_in_unit_sphere();
+    pub fn random_in_hemisphere<R: Rng + ?Sized>(normal: Vector3, rng: &mut R) -> Vector3 {
+        let v = Vector3::random_in_unit_sphere(rng);
 
         if v * normal > 0.0 {
             v
@@ -63,9 +92,9 @@ impl Vector3 {
         }
     }
 
-    pub fn random_in_unit_disk() -> Vector3 {
+    pub fn random_in_unit_disk<R: Rng + ?Sized>(rng: &mut R) -> Vector3 {
         loop {
-            let v = Vector3::xyz(f32::random(), f32::random(), 0.0);
+            let v = Vector3::xyz(f32::random(rng), f32::random(rng), 0.0);
             if v.squared_length() < 1.0 {
                 return v;
             }
@@ -74,15 +103,13 @@ impl Vector3 {
 }
 
 impl Random for Vector3 {
-    fn random() -> Self {
-        let mut rng = rand::thread_rng();
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Vector3::xyz(rng.gen(), rng.gen(), rng.gen())
     }
 }
 
 impl RandomRange<f32> for Vector3 {
-    fn random_range(min: f32, max: f32) -> Self {
-        let mut rng = rand::thread_rng();
+    fn random_range<R: Rng + ?Sized>(rng: &mut R, min: f32, max: f32) -> Self {
         Vector3::xyz(
             rng.gen_range(min, max),
             rng.gen_range(min, max),
@@ -91,6 +118,19 @@ impl RandomRange<f32> for Vector3 {
     }
 }
 
+impl std::ops::Index<usize> for Vector3 {
+    type Output = f32;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector3 index out of range: {}", index),
+        }
+    }
+}
+
 impl std::ops::Add<Vector3> for Vector3 {
     type Output = Vector3;
 
@@ -289,6 +329,22 @@ mod test {
         )
     }
 
+    #[test]
+    fn min_max() {
+        let a = Vector3::xyz(1.0, -2.0, 3.0);
+        let b = Vector3::xyz(0.0, 5.0, 3.0);
+
+        assert_eq!(a.min(b), Vector3::xyz(0.0, -2.0, 3.0));
+        assert_eq!(a.max(b), Vector3::xyz(1.0, 5.0, 3.0));
+    }
+
+    #[test]
+    fn index() {
+        let a = Vector3::xyz(1.0, 2.0, 3.0);
+
+        assert_eq!((a[0], a[1], a[2]), (1.0, 2.0, 3.0));
+    }
+
     #[test]
     fn cross() {
         let a = Vector3::xyz(1.0, 0.0, 0.0);
@@ -312,4 +368,20 @@ mod test {
 
         assert_eq!(a ^ b, expect);
     }
+
+    #[test]
+    fn basis_is_orthonormal() {
+        for n in [
+            Vector3::xyz(0.0, 0.0, 1.0),
+            Vector3::xyz(0.0, 0.0, -1.0),
+            Vector3::xyz(1.0, 2.0, -3.0).normalized(),
+            Vector3::xyz(-0.5, 0.1, 0.2).normalized(),
+        ] {
+            let (u, v) = n.basis();
+            assert!((u.length() - 1.0).abs() < 1e-5);
+            assert!((v.length() - 1.0).abs() < 1e-5);
+            assert!((u * v).abs() < 1e-5 && (u * n).abs() < 1e-5 && (v * n).abs() < 1e-5);
+            assert!(((u ^ v) - n).length() < 1e-5);
+        }
+    }
 }